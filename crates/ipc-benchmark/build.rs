@@ -0,0 +1,10 @@
+//! Build script, which generates Rust code for the protobuf schemas (and gRPC services) in `proto/`
+
+fn main() -> std::io::Result<()> {
+    // Use a vendored `protoc`, so that building does not require a system-wide install
+    let protoc = protoc_bin_vendored::protoc_bin_path().map_err(std::io::Error::other)?;
+    std::env::set_var("PROTOC", protoc);
+
+    println!("cargo:rerun-if-changed=proto/ipc_benchmark.proto");
+    tonic_prost_build::configure().compile_protos(&["proto/ipc_benchmark.proto"], &["proto/"])
+}
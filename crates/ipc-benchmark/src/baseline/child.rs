@@ -0,0 +1,94 @@
+//! Child-specific implementation of the in-process baseline (the child is a thread)
+
+use anyhow::{ensure, Context as _, Result};
+use tracing::debug;
+use uuid::Uuid;
+
+use crate::baseline::{MessageReceiver, MessageSender};
+use crate::codec::{Codec as _, RpcCodec};
+use crate::{
+    get_monotonic_time_nanos, monotonic_nanos_since, ChildProcess, PingMessage, PongMessage,
+};
+
+/// A "child" that runs on a thread in the same process as the parent
+#[derive(Debug)]
+pub struct BaselineChild {
+    /// UUID that should uniquely identify this child
+    uuid: Uuid,
+
+    /// ID of the parent
+    parent_id: String,
+
+    /// Sender used to send pongs to the parent
+    pong_sender: MessageSender,
+
+    /// Receiver used to receive pings from the parent
+    ping_receiver: MessageReceiver,
+
+    /// Codec used to encode messages
+    codec: RpcCodec,
+}
+
+impl BaselineChild {
+    /// Build a new [`BaselineChild`] with a random UUID
+    pub(super) fn new(
+        parent_id: String,
+        pong_sender: MessageSender,
+        ping_receiver: MessageReceiver,
+        codec: RpcCodec,
+    ) -> Self {
+        Self {
+            uuid: Uuid::now_v7(),
+            parent_id,
+            pong_sender,
+            ping_receiver,
+            codec,
+        }
+    }
+}
+
+impl ChildProcess for BaselineChild {
+    fn id(&self) -> String {
+        self.uuid.to_string()
+    }
+
+    fn run(self) -> Result<()> {
+        debug!("child thread running");
+
+        // Enter reading/writing loop
+        debug!("entering read loop...");
+        loop {
+            let Some(bytes) = self.ping_receiver.recv() else {
+                debug!("parent dropped the channel, exiting");
+                return Ok(());
+            };
+            let PingMessage {
+                sender_id,
+                receiver_id,
+                sent_at_ns,
+                payload,
+                ..
+            } = self
+                .codec
+                .decode(&bytes)
+                .context("failed to parse ping message")?;
+            let ping_latency_ns = monotonic_nanos_since(sent_at_ns)?;
+            ensure!(sender_id == self.parent_id, "sender should be parent");
+            ensure!(receiver_id == self.id(), "receiver should be child");
+
+            let pong = self
+                .codec
+                .encode(&PongMessage {
+                    sender_id: self.id(),
+                    receiver_id: sender_id,
+                    sent_at_ns: get_monotonic_time_nanos()?,
+                    ping_latency_ns,
+                    payload,
+                })
+                .context("failed to serialize pong message")?;
+            self.pong_sender
+                .send(pong)
+                .context("failed to send pong to parent")?;
+        }
+    }
+}
@@ -0,0 +1,234 @@
+/*!
+In-process baselines, where the "child" is a thread running in the same process as the parent.
+
+No process boundary is crossed, but the same [`PingMessage`](crate::PingMessage)/[`PongMessage`](crate::PongMessage)
+JSON encoding and checks are performed, so comparing these numbers with IPC transports shows how much of a
+round-trip is due to the process boundary, and how much is due to serialization.
+
+Messages can be passed in one of a few modes (see [`BaselineMode`]):
+
+- `std-mpsc` (default), [`std::sync::mpsc`] channels
+- `crossbeam`, [`crossbeam_channel`] (unbounded) channels
+- `spin`, a single-slot mailbox per direction, which receivers spin on
+*/
+
+use std::cell::UnsafeCell;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{mpsc, Arc};
+
+use anyhow::{bail, Context as _, Result};
+use serde::{Deserialize, Serialize};
+
+pub mod child;
+pub mod parent;
+
+/// ENV variable for setting the baseline mode
+const ENV_VAR_BASELINE_MODE: &str = "BASELINE_MODE";
+
+/// Number of times to spin (with [`std::hint::spin_loop`]) before yielding the thread
+///
+/// Spinning forever would starve the other side of the exchange on machines with few cores.
+const SPINS_BEFORE_YIELD: u32 = 1024;
+
+/// Kind of in-process channel used to pass messages between the parent and child threads
+///
+/// This is normally configured via ENV ("BASELINE_MODE"), and parsed
+/// into this structure for easy usage from code.
+#[derive(Debug, Default, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum BaselineMode {
+    /// [`std::sync::mpsc`] channels
+    #[default]
+    StdMpsc,
+    /// [`crossbeam_channel`] channels
+    Crossbeam,
+    /// A single-slot mailbox that receivers spin on
+    Spin,
+}
+
+impl std::str::FromStr for BaselineMode {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "std-mpsc" => Ok(Self::StdMpsc),
+            "crossbeam" => Ok(Self::Crossbeam),
+            "spin" => Ok(Self::Spin),
+            _ => bail!("invalid BaselineMode value [{s}]"),
+        }
+    }
+}
+
+impl BaselineMode {
+    /// Retreive from env or use the default (StdMpsc)
+    pub fn from_env_or_default(values: impl Iterator<Item = (String, String)>) -> Self {
+        for (k, v) in values {
+            if k == ENV_VAR_BASELINE_MODE {
+                return <Self as std::str::FromStr>::from_str(&v).unwrap_or(Self::default());
+            }
+        }
+        Self::default()
+    }
+}
+
+/// Build a one-directional channel for (encoded) messages, of the kind specified by the mode
+fn channel(mode: BaselineMode) -> (MessageSender, MessageReceiver) {
+    match mode {
+        BaselineMode::StdMpsc => {
+            let (tx, rx) = mpsc::channel();
+            (MessageSender::StdMpsc(tx), MessageReceiver::StdMpsc(rx))
+        }
+        BaselineMode::Crossbeam => {
+            let (tx, rx) = crossbeam_channel::unbounded();
+            (MessageSender::Crossbeam(tx), MessageReceiver::Crossbeam(rx))
+        }
+        BaselineMode::Spin => {
+            let slot = Arc::new(SpinSlot::default());
+            (
+                MessageSender::Spin(SpinSender(slot.clone())),
+                MessageReceiver::Spin(SpinReceiver(slot)),
+            )
+        }
+    }
+}
+
+/// Sending half of a channel for (encoded) messages
+#[derive(Debug)]
+enum MessageSender {
+    /// [`std::sync::mpsc`] sender
+    StdMpsc(mpsc::Sender<Vec<u8>>),
+    /// [`crossbeam_channel`] sender
+    Crossbeam(crossbeam_channel::Sender<Vec<u8>>),
+    /// [`SpinSlot`] sender
+    Spin(SpinSender),
+}
+
+impl MessageSender {
+    /// Send a message, failing if the receiving half has been dropped
+    fn send(&self, msg: Vec<u8>) -> Result<()> {
+        match self {
+            Self::StdMpsc(tx) => tx.send(msg).context("receiver disconnected"),
+            Self::Crossbeam(tx) => tx.send(msg).context("receiver disconnected"),
+            Self::Spin(tx) => tx.send(msg),
+        }
+    }
+}
+
+/// Receiving half of a channel for (encoded) messages
+#[derive(Debug)]
+enum MessageReceiver {
+    /// [`std::sync::mpsc`] receiver
+    StdMpsc(mpsc::Receiver<Vec<u8>>),
+    /// [`crossbeam_channel`] receiver
+    Crossbeam(crossbeam_channel::Receiver<Vec<u8>>),
+    /// [`SpinSlot`] receiver
+    Spin(SpinReceiver),
+}
+
+impl MessageReceiver {
+    /// Receive a message, returning `None` if the sending half has been dropped
+    fn recv(&self) -> Option<Vec<u8>> {
+        match self {
+            Self::StdMpsc(rx) => rx.recv().ok(),
+            Self::Crossbeam(rx) => rx.recv().ok(),
+            Self::Spin(rx) => rx.recv(),
+        }
+    }
+}
+
+/// A single-slot mailbox, shared by exactly one sender and one receiver
+///
+/// Since ping/pong is strictly request/response, the slot is (almost) always empty when
+/// a message is sent, so no queue is necessary.
+#[derive(Debug, Default)]
+struct SpinSlot {
+    /// Whether the slot currently holds a message
+    full: AtomicBool,
+    /// Whether either side has been dropped
+    closed: AtomicBool,
+    /// The message itself
+    ///
+    /// Only written by the sender while `full` is false, and only read by the receiver while `full` is true
+    value: UnsafeCell<Vec<u8>>,
+}
+
+// SAFETY: access to `value` is synchronized via `full` (see [`SpinSender`] and [`SpinReceiver`]),
+// and there is only ever one sender and one receiver.
+unsafe impl Sync for SpinSlot {}
+
+/// Wait for a bit, by spinning, or yielding the thread if we've been spinning for a while
+fn spin_wait(spins: &mut u32) {
+    if *spins < SPINS_BEFORE_YIELD {
+        *spins += 1;
+        std::hint::spin_loop();
+    } else {
+        std::thread::yield_now();
+    }
+}
+
+/// Sending half of a [`SpinSlot`]
+#[derive(Debug)]
+struct SpinSender(Arc<SpinSlot>);
+
+impl SpinSender {
+    /// Send a message, failing if the receiver has been dropped
+    fn send(&self, msg: Vec<u8>) -> Result<()> {
+        let slot = &self.0;
+        let mut spins = 0;
+        while slot.full.load(Ordering::Acquire) {
+            if slot.closed.load(Ordering::Acquire) {
+                bail!("receiver disconnected");
+            }
+            spin_wait(&mut spins);
+        }
+        if slot.closed.load(Ordering::Acquire) {
+            bail!("receiver disconnected");
+        }
+
+        // SAFETY: the slot is empty, so the receiver will not touch the value until we set `full`
+        unsafe {
+            *slot.value.get() = msg;
+        }
+        slot.full.store(true, Ordering::Release);
+        Ok(())
+    }
+}
+
+impl Drop for SpinSender {
+    fn drop(&mut self) {
+        self.0.closed.store(true, Ordering::Release);
+    }
+}
+
+/// Receiving half of a [`SpinSlot`]
+#[derive(Debug)]
+struct SpinReceiver(Arc<SpinSlot>);
+
+impl SpinReceiver {
+    /// Receive a message, returning `None` if the sender has been dropped
+    fn recv(&self) -> Option<Vec<u8>> {
+        let slot = &self.0;
+        let mut spins = 0;
+        loop {
+            // NOTE: `closed` must be loaded *before* `full`, so that a message sent right before
+            // the sender was dropped is not missed
+            let closed = slot.closed.load(Ordering::Acquire);
+            if slot.full.load(Ordering::Acquire) {
+                // SAFETY: the slot is full, so the sender will not touch the value until we clear `full`
+                let msg = std::mem::take(unsafe { &mut *slot.value.get() });
+                slot.full.store(false, Ordering::Release);
+                return Some(msg);
+            }
+            if closed {
+                return None;
+            }
+            spin_wait(&mut spins);
+        }
+    }
+}
+
+impl Drop for SpinReceiver {
+    fn drop(&mut self) {
+        self.0.closed.store(true, Ordering::Release);
+    }
+}
@@ -0,0 +1,190 @@
+//! Parent-specific implementation of the in-process baseline
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::thread::JoinHandle;
+
+use anyhow::{ensure, Context as _, Result};
+use tracing::{debug, warn};
+use uuid::Uuid;
+
+use crate::baseline::child::BaselineChild;
+use crate::baseline::{channel, BaselineMode, MessageReceiver, MessageSender};
+use crate::codec::{Codec as _, RpcCodec};
+use crate::latency::OneWayLatencies;
+use crate::{
+    get_monotonic_time_nanos, payload_size_bytes_from_env_or_default, synthetic_payload, ChildId,
+    ChildName, ChildProcess as _, PingMessage, Pinger, PongMessage, RpcPong,
+};
+
+/// Bi-directional channel for communication with a single child thread
+struct BaselineChannel {
+    /// Self-reported ID of the child
+    child_id: ChildId,
+
+    /// Sender used to send pings to the child
+    ping_sender: MessageSender,
+
+    /// Receiver used to receive pongs from the child
+    pong_receiver: MessageReceiver,
+}
+
+/// A parent that runs its "children" on threads in the same process
+pub struct BaselineParent {
+    /// UUID of the parent
+    uuid: Uuid,
+
+    /// Synthetic payload sent with every ping (see `RPC_PAYLOAD_SIZE_BYTES`)
+    payload: Vec<u8>,
+
+    /// Kind of channels used to communicate with children
+    mode: BaselineMode,
+
+    /// Codec used to encode messages
+    codec: RpcCodec,
+
+    /// Channels for communicating with children, by child name
+    ///
+    /// SAFETY: We're safe using a `RefCell` here because this structure
+    /// is very much *not* multi-threaded.
+    channels: HashMap<ChildName, RefCell<BaselineChannel>>,
+
+    /// Threads running children, which are joined when the parent is dropped
+    child_threads: Vec<JoinHandle<Result<()>>>,
+}
+
+impl std::fmt::Debug for BaselineParent {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("BaselineParent")
+            .field("uuid", &self.uuid)
+            .field("mode", &self.mode)
+            .field("codec", &self.codec)
+            .finish()
+    }
+}
+
+impl Default for BaselineParent {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl BaselineParent {
+    /// Create a new [`BaselineParent`]
+    #[must_use]
+    pub fn new() -> Self {
+        Self {
+            uuid: Uuid::now_v7(),
+            payload: synthetic_payload(payload_size_bytes_from_env_or_default(std::env::vars())),
+            mode: BaselineMode::from_env_or_default(std::env::vars()),
+            codec: RpcCodec::from_env_or_default(std::env::vars()),
+            channels: HashMap::new(),
+            child_threads: Vec::new(),
+        }
+    }
+
+    /// ID of the parent
+    #[must_use]
+    pub fn id(&self) -> String {
+        self.uuid.to_string()
+    }
+
+    /// Retrieve the kind of channels used to communicate with children
+    #[must_use]
+    pub fn mode(&self) -> BaselineMode {
+        self.mode
+    }
+
+    /// Spawn a child *thread*, which can then be pinged by name
+    pub fn spawn_thread_child(&mut self, name: impl AsRef<str>) -> Result<()> {
+        let name = name.as_ref();
+
+        // Build the channels for both directions
+        let (ping_sender, ping_receiver) = channel(self.mode);
+        let (pong_sender, pong_receiver) = channel(self.mode);
+
+        // Spawn the child thread
+        let child = BaselineChild::new(self.id(), pong_sender, ping_receiver, self.codec);
+        let child_id = child.id();
+        debug!(child_id, mode = ?self.mode, "spawning child thread");
+        let thread = std::thread::Builder::new()
+            .name(format!("baseline-{name}"))
+            .spawn(move || child.run())
+            .context("failed to spawn child thread")?;
+        self.child_threads.push(thread);
+
+        // Save information to local registry
+        self.channels.insert(
+            name.into(),
+            RefCell::new(BaselineChannel {
+                child_id,
+                ping_sender,
+                pong_receiver,
+            }),
+        );
+
+        Ok(())
+    }
+}
+
+impl Drop for BaselineParent {
+    fn drop(&mut self) {
+        // Dropping the channels disconnects the children, which causes them to exit
+        self.channels.clear();
+        for thread in self.child_threads.drain(..) {
+            match thread.join() {
+                Ok(Ok(())) => {}
+                Ok(Err(e)) => warn!("child thread failed: {e:?}"),
+                Err(_) => warn!("child thread panicked"),
+            }
+        }
+    }
+}
+
+impl Pinger for BaselineParent {
+    fn roundtrip_ping(&self, child_name: impl AsRef<str>) -> Result<Option<OneWayLatencies>> {
+        let child = child_name.as_ref();
+        let chan = self
+            .channels
+            .get(child)
+            .with_context(|| format!("failed to find child [{child}]"))?
+            .borrow();
+        let BaselineChannel {
+            child_id,
+            ping_sender,
+            pong_receiver,
+        } = &*chan;
+
+        debug!(child, "sending ping message to child");
+        let ping = self
+            .codec
+            .encode(&PingMessage {
+                sender_id: self.id(),
+                receiver_id: child_id.clone(),
+                sent_at_ns: get_monotonic_time_nanos()?,
+                payload: self.payload.clone(),
+            })
+            .context("failed to serialize ping message")?;
+        ping_sender
+            .send(ping)
+            .context("failed to send ping message to child")?;
+
+        debug!(child, "reading pong message from child");
+        let bytes = pong_receiver
+            .recv()
+            .context("child disconnected before sending pong")?;
+        let pong_msg: PongMessage = self
+            .codec
+            .decode(&bytes)
+            .context("failed to parse pong message")?;
+        let latencies = pong_msg.one_way_latencies()?;
+        ensure!(pong_msg.sender_id() == child_id, "child ID matches");
+        ensure!(pong_msg.receiver_id() == self.id(), "parent ID matches");
+        ensure!(
+            pong_msg.payload() == self.payload.as_slice(),
+            "payload matches"
+        );
+
+        Ok(latencies)
+    }
+}
@@ -0,0 +1,118 @@
+use std::path::PathBuf;
+use std::process::Command;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+use anyhow::{anyhow, bail, Context as _, Result};
+use conv::ValueFrom as _;
+use tracing::{debug, info};
+use tracing_subscriber::layer::SubscriberExt;
+use tracing_subscriber::util::SubscriberInitExt;
+use tracing_subscriber::EnvFilter;
+
+use ipc_benchmark::cpu::CpuAccounting;
+use ipc_benchmark::grpc::GrpcParent;
+use ipc_benchmark::latency::RoundtripLatencies;
+use ipc_benchmark::trials::format_throughput_line;
+use ipc_benchmark::warmup::Warmup;
+use ipc_benchmark::{
+    payload_size_bytes_from_env_or_default, report_payload_throughput, ParentProcess, Pinger,
+};
+
+const DEFAULT_TEST_DURATION_SECONDS: u64 = 10;
+
+fn main() -> Result<()> {
+    tracing_subscriber::Registry::default()
+        .with(EnvFilter::from_default_env())
+        .with(tracing_subscriber::fmt::layer())
+        .try_init()
+        .context("failed to build tracing")?;
+
+    debug!("creating parent child...");
+    let mut parent = GrpcParent::new().context("failed to create parent")?;
+
+    let child_name = "child-1";
+
+    debug!("resolving bin path...");
+    let bin_path = std::env::var("GRPC_CHILD_BIN_PATH")
+        .map(PathBuf::from)
+        .context("missing env var GRPC_CHILD_BIN_PATH")?;
+    if !bin_path.exists() {
+        bail!("missing binary at path [{}]", bin_path.display());
+    }
+    if !bin_path.metadata().is_ok_and(|m| m.is_file()) {
+        bail!("invalid non-binary file at path [{}]", bin_path.display());
+    }
+
+    debug!("spawning child...");
+    let mut child_process = parent
+        .spawn_child(child_name, Command::new(bin_path))
+        .context("failed to spawn child")?;
+
+    let stop = Arc::new(AtomicUsize::new(0));
+    let thread_stop = stop.clone();
+
+    Warmup::from_env(std::env::vars())?.run(|| parent.roundtrip_ping(child_name))?;
+    let cpu_accounting = CpuAccounting::start(&child_process)?;
+    debug!("starting thread to send pings to child process");
+    let ping_thread = std::thread::spawn(move || {
+        let mut invocations: u64 = 0;
+        let mut latencies = RoundtripLatencies::from_env(std::env::vars())?;
+        loop {
+            latencies
+                .time(|| parent.roundtrip_ping(child_name))
+                .context("failed to ping")?;
+            invocations += 1;
+            if thread_stop.load(Ordering::Relaxed) == 1 {
+                return Ok((invocations, latencies))
+                    as Result<(u64, RoundtripLatencies), anyhow::Error>;
+            }
+        }
+    });
+
+    let test_duration_seconds = std::env::var("TEST_DURATION_SECONDS")
+        .context("missing env var")
+        .and_then(|v| v.parse::<u64>().context("failed to parse"))
+        .unwrap_or(DEFAULT_TEST_DURATION_SECONDS);
+    debug!("waiting {test_duration_seconds} seconds in main thread...");
+    std::thread::sleep(std::time::Duration::from_secs(test_duration_seconds));
+
+    debug!("stopping sender thread...");
+    stop.store(1, Ordering::Relaxed);
+    let ping_result = ping_thread
+        .join()
+        .map_err(|_| anyhow!("failed to join pinger thread"))?;
+
+    // NOTE: the child is killed before checking the result, so that failed pings do not leave it running
+    debug!("killing child process...");
+    child_process
+        .kill()
+        .context("failed to kill child process")?;
+    let cpu_cost = cpu_accounting.finish(&mut child_process)?;
+    let (roundtrips, latencies) = ping_result.context("failed to calculate invocations")?;
+
+    let roundtrips_per_second = f64::value_from(roundtrips)
+        .context("failed to convert roundtrips to f64")?
+        / f64::value_from(test_duration_seconds)
+            .context("failed to convert test duration to f64")?;
+
+    info!(
+        roundtrips,
+        test_duration_seconds, roundtrips_per_second, "completed ping-pong round-trips"
+    );
+    eprintln!(
+        "{}",
+        format_throughput_line(roundtrips, test_duration_seconds, roundtrips_per_second)
+    );
+
+    cpu_cost.report(roundtrips, roundtrips_per_second)?;
+
+    latencies.report()?;
+    latencies.write_distribution_from_env(std::env::vars())?;
+
+    report_payload_throughput(
+        payload_size_bytes_from_env_or_default(std::env::vars()),
+        roundtrips_per_second,
+    )?;
+    Ok(())
+}
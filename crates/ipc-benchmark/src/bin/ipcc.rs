@@ -10,8 +10,14 @@ use tracing_subscriber::layer::SubscriberExt;
 use tracing_subscriber::util::SubscriberInitExt;
 use tracing_subscriber::EnvFilter;
 
+use ipc_benchmark::cpu::CpuAccounting;
 use ipc_benchmark::ipcc::parent::IpcChannelParent;
-use ipc_benchmark::{ParentProcess, Pinger};
+use ipc_benchmark::latency::RoundtripLatencies;
+use ipc_benchmark::trials::format_throughput_line;
+use ipc_benchmark::warmup::Warmup;
+use ipc_benchmark::{
+    report_one_way_payload_throughput, report_payload_throughput, ParentProcess, Pinger,
+};
 
 const DEFAULT_TEST_DURATION_SECONDS: u64 = 10;
 
@@ -42,19 +48,27 @@ fn main() -> Result<()> {
         .spawn_child(child_name, Command::new(bin_path))
         .context("failed to spawn child")?;
 
+    // NOTE: this must be retrieved before the parent is moved into the pinger thread
+    let bulk_payload_size_bytes = parent.bulk_payload_size_bytes();
+    let payload_size_bytes = parent.payload_size_bytes();
+
     let stop = Arc::new(AtomicUsize::new(0));
     let thread_stop = stop.clone();
 
+    Warmup::from_env(std::env::vars())?.run(|| parent.roundtrip_ping(child_name))?;
+    let cpu_accounting = CpuAccounting::start(&child_process)?;
     info!("starting thread to send pings to child process");
     let ping_thread = std::thread::spawn(move || {
         let mut invocations: u64 = 0;
+        let mut latencies = RoundtripLatencies::from_env(std::env::vars())?;
         loop {
-            parent
-                .roundtrip_ping(child_name)
+            latencies
+                .time(|| parent.roundtrip_ping(child_name))
                 .context("failed to ping")?;
             invocations += 1;
             if thread_stop.load(Ordering::Relaxed) == 1 {
-                return Ok(invocations) as Result<u64, anyhow::Error>;
+                return Ok((invocations, latencies))
+                    as Result<(u64, RoundtripLatencies), anyhow::Error>;
             }
         }
     });
@@ -68,15 +82,17 @@ fn main() -> Result<()> {
 
     info!("stopping sender thread...");
     stop.store(1, Ordering::Relaxed);
-    let roundtrips = ping_thread
+    let ping_result = ping_thread
         .join()
-        .map_err(|_| anyhow!("failed to join pinger thread"))?
-        .context("failed to calculate invocations")?;
+        .map_err(|_| anyhow!("failed to join pinger thread"))?;
 
+    // NOTE: the child is killed before checking the result, so that failed pings do not leave it running
     info!("killing child process...");
     child_process
         .kill()
         .context("failed to kill child process")?;
+    let cpu_cost = cpu_accounting.finish(&mut child_process)?;
+    let (roundtrips, latencies) = ping_result.context("failed to calculate invocations")?;
 
     let roundtrips_per_second = f64::value_from(roundtrips)
         .context("failed to convert roundtrips to f64")?
@@ -87,6 +103,24 @@ fn main() -> Result<()> {
         roundtrips,
         test_duration_seconds, roundtrips_per_second, "completed ping-pong round-trips"
     );
-    eprintln!("completed [{roundtrips}] ping-pong round-trips [{test_duration_seconds}] seconds ([{roundtrips_per_second}] round-trips/second)");
+    eprintln!(
+        "{}",
+        format_throughput_line(roundtrips, test_duration_seconds, roundtrips_per_second)
+    );
+
+    cpu_cost.report(roundtrips, roundtrips_per_second)?;
+
+    latencies.report()?;
+    latencies.write_distribution_from_env(std::env::vars())?;
+
+    // NOTE: bulk payloads are only carried by pings (pongs do not echo them back), unlike synthetic payloads
+    report_one_way_payload_throughput(
+        bulk_payload_size_bytes.unwrap_or_default(),
+        roundtrips_per_second,
+    )?;
+    report_payload_throughput(
+        payload_size_bytes.unwrap_or_default(),
+        roundtrips_per_second,
+    )?;
     Ok(())
 }
@@ -0,0 +1,24 @@
+use anyhow::Result;
+
+#[cfg(target_os = "linux")]
+fn main() -> Result<()> {
+    use anyhow::Context as _;
+    use tracing_subscriber::layer::SubscriberExt as _;
+    use tracing_subscriber::util::SubscriberInitExt as _;
+
+    use ipc_benchmark::mqueue::MqueueChild;
+    use ipc_benchmark::ChildProcess as _;
+
+    tracing_subscriber::Registry::default()
+        .with(tracing_subscriber::EnvFilter::from_default_env())
+        .with(tracing_subscriber::fmt::layer())
+        .try_init()
+        .context("failed to build tracing")?;
+
+    MqueueChild::new().run()
+}
+
+#[cfg(not(target_os = "linux"))]
+fn main() -> Result<()> {
+    anyhow::bail!("the mqueue transport is only supported on Linux")
+}
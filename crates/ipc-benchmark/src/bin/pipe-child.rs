@@ -0,0 +1,17 @@
+use anyhow::{Context as _, Result};
+use tracing_subscriber::layer::SubscriberExt as _;
+use tracing_subscriber::util::SubscriberInitExt as _;
+
+use ipc_benchmark::pipe::PipeChild;
+use ipc_benchmark::ChildProcess as _;
+
+fn main() -> Result<()> {
+    tracing_subscriber::Registry::default()
+        .with(tracing_subscriber::EnvFilter::from_default_env())
+        // NOTE: STDOUT carries messages to the parent, so logs *must* go to STDERR
+        .with(tracing_subscriber::fmt::layer().with_writer(std::io::stderr))
+        .try_init()
+        .context("failed to build tracing")?;
+
+    PipeChild::new().run()
+}
@@ -9,8 +9,12 @@ use tracing_subscriber::layer::SubscriberExt;
 use tracing_subscriber::util::SubscriberInitExt;
 use tracing_subscriber::EnvFilter;
 
+use ipc_benchmark::cpu::CpuAccounting;
+use ipc_benchmark::latency::RoundtripLatencies;
 use ipc_benchmark::shmem::raw_sync::RawSyncParent;
-use ipc_benchmark::{ParentProcess, Pinger};
+use ipc_benchmark::trials::format_throughput_line;
+use ipc_benchmark::warmup::Warmup;
+use ipc_benchmark::{payload_size_bytes_from_env_or_default, report_payload_throughput, ParentProcess, Pinger};
 
 const DEFAULT_TEST_DURATION_SECONDS: u64 = 10;
 
@@ -48,15 +52,19 @@ fn main() -> Result<()> {
         .unwrap_or(DEFAULT_TEST_DURATION_SECONDS);
     let test_duration = Duration::from_secs(test_duration_seconds);
 
+    Warmup::from_env(std::env::vars())?.run(|| parent.roundtrip_ping(child_name))?;
+    let cpu_accounting = CpuAccounting::start(&child_process)?;
+
     // NOTE: we can't spawn this into another thread, because the Shmem values *cannot* be moved over
     // (it *might* be possible, but at least isn't implemented now)
     let start = Instant::now();
     debug!("starting loop of pings to child process (child is NOT threaded)");
     let mut invocations: u64 = 0;
-    let roundtrips = loop {
-        parent
-            .roundtrip_ping(child_name)
-            .context("failed to ping")?;
+    let mut latencies = RoundtripLatencies::from_env(std::env::vars())?;
+    let ping_result = loop {
+        if let Err(e) = latencies.time(|| parent.roundtrip_ping(child_name)) {
+            break Err(e.context("failed to ping"));
+        }
         invocations += 1;
         // Break if we're over
 
@@ -64,12 +72,15 @@ fn main() -> Result<()> {
         if Instant::now().duration_since(start) > test_duration {
             break Ok(invocations) as Result<u64, anyhow::Error>;
         }
-    }?;
+    };
 
+    // NOTE: the child is killed before checking the result, so that failed pings do not leave it running
     debug!("killing child process...");
     child_process
         .kill()
         .context("failed to kill child process")?;
+    let cpu_cost = cpu_accounting.finish(&mut child_process)?;
+    let roundtrips = ping_result?;
 
     let roundtrips_per_second = f64::value_from(roundtrips)
         .context("failed to convert roundtrips to f64")?
@@ -80,6 +91,19 @@ fn main() -> Result<()> {
         roundtrips,
         test_duration_seconds, roundtrips_per_second, "completed ping-pong round-trips"
     );
-    eprintln!("completed [{roundtrips}] ping-pong round-trips [{test_duration_seconds}] seconds ([{roundtrips_per_second}] round-trips/second)");
+    eprintln!(
+        "{}",
+        format_throughput_line(roundtrips, test_duration_seconds, roundtrips_per_second)
+    );
+
+    cpu_cost.report(roundtrips, roundtrips_per_second)?;
+
+    latencies.report()?;
+    latencies.write_distribution_from_env(std::env::vars())?;
+
+    report_payload_throughput(
+        payload_size_bytes_from_env_or_default(std::env::vars()),
+        roundtrips_per_second,
+    )?;
     Ok(())
 }
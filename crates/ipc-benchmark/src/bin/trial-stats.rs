@@ -0,0 +1,23 @@
+use std::io::Read as _;
+
+use anyhow::{Context as _, Result};
+use tracing_subscriber::layer::SubscriberExt as _;
+use tracing_subscriber::util::SubscriberInitExt as _;
+
+use ipc_benchmark::trials::TrialResults;
+
+/// Summarize the results of repeated trials, from their combined output on STDIN (see `just ipc-trials`)
+fn main() -> Result<()> {
+    tracing_subscriber::Registry::default()
+        .with(tracing_subscriber::EnvFilter::from_default_env())
+        .with(tracing_subscriber::fmt::layer())
+        .try_init()
+        .context("failed to build tracing")?;
+
+    let mut output = String::new();
+    std::io::stdin()
+        .read_to_string(&mut output)
+        .context("failed to read output of trials from STDIN")?;
+
+    TrialResults::parse(&output)?.report()
+}
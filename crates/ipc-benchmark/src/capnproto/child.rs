@@ -0,0 +1,174 @@
+//! Child-specific IPC implementation via Cap'n Proto RPC
+
+use std::future::Future;
+use std::io::{stdin, BufRead};
+
+use anyhow::{Context as _, Result};
+use capnp::capability::Rc;
+use capnp_rpc::rpc_twoparty_capnp::Side;
+use capnp_rpc::{twoparty, RpcSystem};
+use tokio::task::LocalSet;
+use tokio_util::compat::{TokioAsyncReadCompatExt as _, TokioAsyncWriteCompatExt as _};
+use tracing::debug;
+use uuid::Uuid;
+
+use crate::capnproto::pinger_capnp::pinger;
+use crate::capnproto::CapnpRpcInit;
+use crate::{get_monotonic_time_nanos, monotonic_nanos_since, ChildProcess};
+
+/// A child process that performs IPC via Cap'n Proto RPC over a Unix domain socket
+#[derive(Debug)]
+pub struct CapnpRpcChild {
+    /// UUID that should uniquely identify this process
+    uuid: Uuid,
+}
+
+impl Default for CapnpRpcChild {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl CapnpRpcChild {
+    /// Build a new [`CapnpRpcChild`] with a random UUID
+    #[must_use]
+    pub fn new() -> Self {
+        Self {
+            uuid: Uuid::now_v7(),
+        }
+    }
+}
+
+/// Implementation of the `Pinger` interface, served to the parent
+struct PingerServer {
+    /// UUID of the parent
+    parent_id: String,
+
+    /// UUID of the child
+    child_id: String,
+}
+
+impl PingerServer {
+    /// Check the parent ID sent by the parent, and respond with the child ID
+    fn handle_init(
+        &self,
+        params: pinger::InitParams,
+        mut results: pinger::InitResults,
+    ) -> capnp::Result<()> {
+        let parent_id = params.get()?.get_parent_id()?.to_str()?;
+        if parent_id != self.parent_id {
+            return Err(capnp::Error::failed(format!(
+                "parent ID [{parent_id}] did not match"
+            )));
+        }
+        results.get().set_child_id(&self.child_id);
+        Ok(())
+    }
+
+    /// Check a ping sent by the parent, and respond with a pong
+    fn handle_ping(
+        &self,
+        params: pinger::PingParams,
+        mut results: pinger::PingResults,
+    ) -> capnp::Result<()> {
+        let ping = params.get()?.get_ping()?;
+        let ping_latency_ns = monotonic_nanos_since(ping.get_sent_at_ns())
+            .map_err(|e| capnp::Error::failed(format!("{e:#}")))?;
+        let sender_id = ping.get_sender_id()?.to_str()?;
+        if sender_id != self.parent_id {
+            return Err(capnp::Error::failed("sender should be parent".into()));
+        }
+        if ping.get_receiver_id()?.to_str()? != self.child_id {
+            return Err(capnp::Error::failed("receiver should be child".into()));
+        }
+
+        let sent_at_ns =
+            get_monotonic_time_nanos().map_err(|e| capnp::Error::failed(format!("{e:#}")))?;
+        let mut pong = results.get().init_pong();
+        pong.set_sender_id(&self.child_id);
+        pong.set_receiver_id(sender_id);
+        pong.set_sent_at_ns(sent_at_ns);
+        pong.set_ping_latency_ns(ping_latency_ns);
+        pong.set_payload(ping.get_payload()?);
+        Ok(())
+    }
+}
+
+impl pinger::Server for PingerServer {
+    fn init(
+        self: Rc<Self>,
+        params: pinger::InitParams,
+        results: pinger::InitResults,
+    ) -> impl Future<Output = Result<(), capnp::Error>> + 'static {
+        std::future::ready(self.handle_init(params, results))
+    }
+
+    fn ping(
+        self: Rc<Self>,
+        params: pinger::PingParams,
+        results: pinger::PingResults,
+    ) -> impl Future<Output = Result<(), capnp::Error>> + 'static {
+        std::future::ready(self.handle_ping(params, results))
+    }
+}
+
+impl ChildProcess for CapnpRpcChild {
+    fn id(&self) -> String {
+        self.uuid.to_string()
+    }
+
+    fn run(self) -> Result<()> {
+        debug!("child process running");
+
+        debug!("reading capnp-rpc init from STDIN");
+        let mut s = String::new();
+        stdin().lock().read_line(&mut s)?;
+
+        // We expect to receive an init message on STDIN
+        let CapnpRpcInit {
+            parent_id,
+            socket_path,
+        } = serde_json::from_slice(s.as_bytes())
+            .context("failed to read init message from STDIN")?;
+
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_io()
+            .build()
+            .context("failed to build tokio runtime")?;
+        let server = PingerServer {
+            parent_id,
+            child_id: self.id(),
+        };
+
+        LocalSet::new().block_on(&runtime, async move {
+            debug!(socket_path = %socket_path.display(), "connecting to parent socket");
+            let stream = tokio::net::UnixStream::connect(&socket_path)
+                .await
+                .with_context(|| {
+                    format!(
+                        "failed to connect to parent socket @ [{}]",
+                        socket_path.display()
+                    )
+                })?;
+            let (reader, writer) = stream.into_split();
+            let network = twoparty::VatNetwork::new(
+                reader.compat(),
+                writer.compat_write(),
+                Side::Server,
+                capnp::message::ReaderOptions::default(),
+            );
+
+            // Serve the `Pinger` as the bootstrap capability, until the parent goes away
+            debug!("serving pinger...");
+            let client: pinger::Client = capnp_rpc::new_client(server);
+            match RpcSystem::new(Box::new(network), Some(client.client)).await {
+                Ok(()) => Ok(()),
+                Err(e) if e.kind == capnp::ErrorKind::Disconnected => {
+                    debug!("parent closed the connection, exiting");
+                    Ok(())
+                }
+                Err(e) => Err(e).context("RPC system failed"),
+            }
+        })
+    }
+}
@@ -0,0 +1,49 @@
+/*!
+Process IPC via [Cap'n Proto RPC][crate-capnp-rpc], over a [Unix domain socket][wiki-uds].
+
+Unlike the other transports, which hand-roll framing & encoding around a single mechanism, this
+runs a complete RPC framework (promises, a capability table, and Cap'n Proto framing). The child
+serves a `Pinger` interface (see `capnp/pinger.capnp`), and the parent calls it once per round-trip.
+
+The parent binds a listening socket in a private temporary directory, then sends the path
+to the child over STDIN. Once the child connects, it serves the `Pinger` interface as the
+bootstrap capability of the connection, and the handshake is completed with a call to `init`.
+
+Messages are always encoded by Cap'n Proto, so [`crate::codec`] is not used here.
+
+[crate-capnp-rpc]: <https://crates.io/crates/capnp-rpc>
+[wiki-uds]: <https://en.wikipedia.org/wiki/Unix_domain_socket>
+**/
+
+use std::path::PathBuf;
+
+use serde::{Deserialize, Serialize};
+
+pub mod child;
+pub mod parent;
+
+pub use child::CapnpRpcChild;
+pub use parent::CapnpRpcParent;
+
+/// Code generated from `capnp/pinger.capnp`
+#[allow(
+    missing_docs,
+    missing_debug_implementations,
+    clippy::missing_docs_in_private_items,
+    clippy::all,
+    clippy::pedantic
+)]
+pub mod pinger_capnp {
+    include!("pinger_capnp.rs");
+}
+
+/// Message sent to child processes over STDIN that contains
+/// information necessary for the child to connect to the parent
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
+struct CapnpRpcInit {
+    /// UUID of the parent
+    parent_id: String,
+
+    /// Path to the Unix domain socket the parent is listening on
+    socket_path: PathBuf,
+}
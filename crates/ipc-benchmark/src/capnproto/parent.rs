@@ -0,0 +1,226 @@
+//! Parent-specific IPC implementation via Cap'n Proto RPC
+
+use std::collections::HashMap;
+use std::io::Write;
+use std::os::unix::net::UnixListener;
+use std::process::{Child, Command, Stdio};
+
+use anyhow::{ensure, Context as _, Result};
+use capnp_rpc::rpc_twoparty_capnp::Side;
+use capnp_rpc::{twoparty, RpcSystem};
+use tempfile::TempDir;
+use tokio::runtime::Runtime;
+use tokio::task::LocalSet;
+use tokio_util::compat::{TokioAsyncReadCompatExt as _, TokioAsyncWriteCompatExt as _};
+use tracing::debug;
+use uuid::Uuid;
+
+use crate::capnproto::pinger_capnp::pinger;
+use crate::capnproto::CapnpRpcInit;
+use crate::latency::OneWayLatencies;
+use crate::{
+    get_monotonic_time_nanos, payload_size_bytes_from_env_or_default, synthetic_payload, ChildId,
+    ChildName, ParentProcess, Pinger,
+};
+
+/// Connection to a single child
+struct CapnpRpcChannel {
+    /// Self-reported ID of the child
+    child_id: ChildId,
+
+    /// Client for the `Pinger` served by the child
+    client: pinger::Client,
+
+    /// Temporary directory that contains the socket file
+    ///
+    /// NOTE: this must be held so that the directory is not removed while the child is running
+    _socket_dir: TempDir,
+}
+
+/// A parent process that performs IPC via Cap'n Proto RPC over a Unix domain socket
+///
+/// NOTE: Cap'n Proto RPC clients are *not* thread safe, so unlike most parents,
+/// this must be created on the thread that sends pings.
+pub struct CapnpRpcParent {
+    /// UUID of the parent process
+    uuid: Uuid,
+
+    /// Synthetic payload sent with every ping (see `RPC_PAYLOAD_SIZE_BYTES`)
+    payload: Vec<u8>,
+
+    /// Runtime that drives the connections to children (single threaded)
+    runtime: Runtime,
+
+    /// Set of tasks (one RPC system per child) that are driven whenever we wait on a call
+    local: LocalSet,
+
+    /// Connections to children, by child name
+    channels: HashMap<ChildName, CapnpRpcChannel>,
+}
+
+impl std::fmt::Debug for CapnpRpcParent {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("CapnpRpcParent")
+            .field("uuid", &self.uuid)
+            .finish()
+    }
+}
+
+impl CapnpRpcParent {
+    /// Create a new [`CapnpRpcParent`]
+    pub fn new() -> Result<Self> {
+        Ok(Self {
+            uuid: Uuid::now_v7(),
+            payload: synthetic_payload(payload_size_bytes_from_env_or_default(std::env::vars())),
+            runtime: tokio::runtime::Builder::new_current_thread()
+                .enable_io()
+                .build()
+                .context("failed to build tokio runtime")?,
+            local: LocalSet::new(),
+            channels: HashMap::new(),
+        })
+    }
+}
+
+impl ParentProcess for CapnpRpcParent {
+    fn id(&self) -> String {
+        self.uuid.to_string()
+    }
+
+    fn spawn_child(&mut self, name: impl AsRef<str>, mut cmd: Command) -> Result<Child> {
+        let name = name.as_ref();
+
+        // Bind a socket in a private temp dir, before the child is started
+        let socket_dir = tempfile::Builder::new()
+            .prefix("ipc-benchmark-capnp-rpc-")
+            .tempdir()
+            .context("failed to create temp dir for socket")?;
+        let socket_path = socket_dir.path().join("parent.sock");
+        debug!(socket_path = %socket_path.display(), "binding unix socket");
+        let listener = UnixListener::bind(&socket_path)
+            .with_context(|| format!("failed to bind unix socket @ [{}]", socket_path.display()))?;
+
+        // Spawn the child
+        debug!("spawning child");
+        let mut child = cmd
+            .stdin(Stdio::piped())
+            .spawn()
+            .context("failed to spawn child process")?;
+
+        // Send the init message over STDIN
+        let init_msg = CapnpRpcInit {
+            parent_id: self.id(),
+            socket_path,
+        };
+        debug!(?init_msg, "writing init to child STDIN");
+        let mut child_stdin = child.stdin.take().context("failed to get child STDIN")?;
+        child_stdin
+            .write_all(&serde_json::to_vec(&init_msg).context("failed to serialize init msg")?)
+            .context("failed to write init msg")?;
+        child_stdin
+            .write_all(b"\r\n")
+            .context("failed to write new line")?;
+        child_stdin.flush().context("failed to flush child STDIN")?;
+
+        // Wait for the child to connect
+        debug!("waiting for child to connect");
+        let (stream, _addr) = listener
+            .accept()
+            .context("failed to accept connection from child")?;
+        stream
+            .set_nonblocking(true)
+            .context("failed to set unix stream to non-blocking")?;
+
+        // Start an RPC system for the connection, and retrieve the `Pinger` served by the child
+        let client: pinger::Client = {
+            let _guard = self.runtime.enter();
+            let stream = tokio::net::UnixStream::from_std(stream)
+                .context("failed to register unix stream with runtime")?;
+            let (reader, writer) = stream.into_split();
+            let network = twoparty::VatNetwork::new(
+                reader.compat(),
+                writer.compat_write(),
+                Side::Client,
+                capnp::message::ReaderOptions::default(),
+            );
+            let mut rpc_system = RpcSystem::new(Box::new(network), None);
+            let client = rpc_system.bootstrap(Side::Server);
+            self.local.spawn_local(async move {
+                if let Err(e) = rpc_system.await {
+                    debug!(?e, "RPC system for child connection stopped");
+                }
+            });
+            client
+        };
+
+        // Complete the handshake
+        let mut request = client.init_request();
+        request.get().set_parent_id(self.id());
+        let response = self
+            .local
+            .block_on(&self.runtime, request.send().promise)
+            .context("failed to call init on child")?;
+        let child_id = response
+            .get()?
+            .get_child_id()?
+            .to_string()
+            .context("invalid child ID")?;
+        debug!(child_id, "received init response");
+
+        // Save information to local registry
+        self.channels.insert(
+            name.into(),
+            CapnpRpcChannel {
+                child_id,
+                client,
+                _socket_dir: socket_dir,
+            },
+        );
+
+        Ok(child)
+    }
+}
+
+impl Pinger for CapnpRpcParent {
+    fn roundtrip_ping(&self, child_name: impl AsRef<str>) -> Result<Option<OneWayLatencies>> {
+        let child = child_name.as_ref();
+        let CapnpRpcChannel {
+            child_id, client, ..
+        } = self
+            .channels
+            .get(child)
+            .with_context(|| format!("failed to find child [{child}]"))?;
+
+        debug!(child, "calling ping on child");
+        let mut request = client.ping_request();
+        {
+            let mut ping = request.get().init_ping();
+            ping.set_sender_id(self.id());
+            ping.set_receiver_id(child_id);
+            ping.set_sent_at_ns(get_monotonic_time_nanos()?);
+            ping.set_payload(&self.payload);
+        }
+        let response = self
+            .local
+            .block_on(&self.runtime, request.send().promise)
+            .context("failed to call ping on child")?;
+
+        let pong = response.get()?.get_pong()?;
+        let latencies =
+            OneWayLatencies::measure(pong.get_ping_latency_ns(), pong.get_sent_at_ns())?;
+        ensure!(
+            pong.get_sender_id()?.to_str()? == child_id,
+            "child ID matches"
+        );
+        ensure!(
+            pong.get_receiver_id()?.to_str()? == self.id(),
+            "parent ID matches"
+        );
+        ensure!(
+            pong.get_payload()? == self.payload.as_slice(),
+            "payload matches"
+        );
+
+        Ok(Some(latencies))
+    }
+}
@@ -0,0 +1,331 @@
+/*!
+Pluggable encoding (serialization) of messages sent between parents and children.
+
+Every transport encodes messages with a [`Codec`], the choice of which is configured at runtime
+via ENV ("RPC_CODEC", see [`RpcCodec`]) by the parent, and sent to the child as part of the init payload.
+
+Note that init payloads sent over STDIN are *always* JSON, as the child does not know which codec to use yet.
+
+Every message that is encoded must have both a `serde` implementation and a protobuf schema (see [`ProtoMessage`]).
+
+Available codecs:
+
+- `json` (default), via [`serde_json`][crate-serde-json]
+- `bincode`, via [`bincode`][crate-bincode]
+- `postcard`, via [`postcard`][crate-postcard]
+- `msgpack`, MessagePack via [`rmp-serde`][crate-rmp-serde]
+- `cbor`, CBOR via [`ciborium`][crate-ciborium]
+- `protobuf`, via [`prost`][crate-prost] (see [`crate::proto`])
+
+[crate-serde-json]: <https://crates.io/crates/serde_json>
+[crate-bincode]: <https://crates.io/crates/bincode>
+[crate-postcard]: <https://crates.io/crates/postcard>
+[crate-rmp-serde]: <https://crates.io/crates/rmp-serde>
+[crate-ciborium]: <https://crates.io/crates/ciborium>
+[crate-prost]: <https://crates.io/crates/prost>
+**/
+
+use std::io::Write;
+
+use anyhow::{bail, Context as _, Result};
+use prost::Message as _;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+
+use crate::proto::ProtoMessage;
+
+/// ENV variable for setting the codec
+const ENV_VAR_RPC_CODEC: &str = "RPC_CODEC";
+
+/// Encoding and decoding of messages
+pub trait Codec {
+    /// Encode a value, writing the encoded bytes to a writer
+    fn encode_to_writer<T: Serialize + ProtoMessage, W: Write>(
+        &self,
+        obj: &T,
+        writer: W,
+    ) -> Result<()>;
+
+    /// Decode a value from bytes
+    fn decode<T: DeserializeOwned + ProtoMessage>(&self, bytes: &[u8]) -> Result<T>;
+
+    /// Encode a value, appending the encoded bytes to a buffer
+    fn encode_into<T: Serialize + ProtoMessage>(&self, obj: &T, buf: &mut Vec<u8>) -> Result<()> {
+        self.encode_to_writer(obj, buf)
+    }
+
+    /// Encode a value into a new buffer
+    fn encode<T: Serialize + ProtoMessage>(&self, obj: &T) -> Result<Vec<u8>> {
+        let mut buf = Vec::new();
+        self.encode_into(obj, &mut buf)?;
+        Ok(buf)
+    }
+}
+
+/// JSON, via [`serde_json`]
+#[derive(Debug, Default, Clone, Copy)]
+pub struct JsonCodec;
+
+impl Codec for JsonCodec {
+    fn encode_to_writer<T: Serialize + ProtoMessage, W: Write>(
+        &self,
+        obj: &T,
+        writer: W,
+    ) -> Result<()> {
+        serde_json::to_writer(writer, obj).with_context(|| {
+            format!(
+                "failed to serialize [{}] (JSON)",
+                std::any::type_name::<T>()
+            )
+        })
+    }
+
+    fn decode<T: DeserializeOwned + ProtoMessage>(&self, bytes: &[u8]) -> Result<T> {
+        serde_json::from_slice(bytes).with_context(|| {
+            format!(
+                "failed to deserialize message into [{}] (JSON)",
+                std::any::type_name::<T>()
+            )
+        })
+    }
+}
+
+/// Bincode, via [`bincode`]
+#[derive(Debug, Default, Clone, Copy)]
+pub struct BincodeCodec;
+
+impl Codec for BincodeCodec {
+    fn encode_to_writer<T: Serialize + ProtoMessage, W: Write>(
+        &self,
+        obj: &T,
+        writer: W,
+    ) -> Result<()> {
+        bincode::serialize_into(writer, obj).with_context(|| {
+            format!(
+                "failed to serialize [{}] (bincode)",
+                std::any::type_name::<T>()
+            )
+        })
+    }
+
+    fn decode<T: DeserializeOwned + ProtoMessage>(&self, bytes: &[u8]) -> Result<T> {
+        bincode::deserialize(bytes).with_context(|| {
+            format!(
+                "failed to deserialize message into [{}] (bincode)",
+                std::any::type_name::<T>()
+            )
+        })
+    }
+}
+
+/// Postcard, via [`postcard`]
+#[derive(Debug, Default, Clone, Copy)]
+pub struct PostcardCodec;
+
+impl Codec for PostcardCodec {
+    fn encode_to_writer<T: Serialize + ProtoMessage, W: Write>(
+        &self,
+        obj: &T,
+        writer: W,
+    ) -> Result<()> {
+        postcard::to_io(obj, writer).map(|_| ()).with_context(|| {
+            format!(
+                "failed to serialize [{}] (postcard)",
+                std::any::type_name::<T>()
+            )
+        })
+    }
+
+    fn decode<T: DeserializeOwned + ProtoMessage>(&self, bytes: &[u8]) -> Result<T> {
+        postcard::from_bytes(bytes).with_context(|| {
+            format!(
+                "failed to deserialize message into [{}] (postcard)",
+                std::any::type_name::<T>()
+            )
+        })
+    }
+}
+
+/// MessagePack, via [`rmp_serde`]
+///
+/// NOTE: structs are encoded as arrays (rather than maps), to keep messages compact
+#[derive(Debug, Default, Clone, Copy)]
+pub struct MessagePackCodec;
+
+impl Codec for MessagePackCodec {
+    fn encode_to_writer<T: Serialize + ProtoMessage, W: Write>(
+        &self,
+        obj: &T,
+        mut writer: W,
+    ) -> Result<()> {
+        rmp_serde::encode::write(&mut writer, obj).with_context(|| {
+            format!(
+                "failed to serialize [{}] (MessagePack)",
+                std::any::type_name::<T>()
+            )
+        })
+    }
+
+    fn decode<T: DeserializeOwned + ProtoMessage>(&self, bytes: &[u8]) -> Result<T> {
+        rmp_serde::from_slice(bytes).with_context(|| {
+            format!(
+                "failed to deserialize message into [{}] (MessagePack)",
+                std::any::type_name::<T>()
+            )
+        })
+    }
+}
+
+/// CBOR, via [`ciborium`]
+#[derive(Debug, Default, Clone, Copy)]
+pub struct CborCodec;
+
+impl Codec for CborCodec {
+    fn encode_to_writer<T: Serialize + ProtoMessage, W: Write>(
+        &self,
+        obj: &T,
+        writer: W,
+    ) -> Result<()> {
+        ciborium::into_writer(obj, writer).with_context(|| {
+            format!(
+                "failed to serialize [{}] (CBOR)",
+                std::any::type_name::<T>()
+            )
+        })
+    }
+
+    fn decode<T: DeserializeOwned + ProtoMessage>(&self, bytes: &[u8]) -> Result<T> {
+        ciborium::from_reader(bytes).with_context(|| {
+            format!(
+                "failed to deserialize message into [{}] (CBOR)",
+                std::any::type_name::<T>()
+            )
+        })
+    }
+}
+
+/// Protobuf, via [`prost`] (see [`crate::proto`] for the schemas)
+#[derive(Debug, Default, Clone, Copy)]
+pub struct ProtobufCodec;
+
+impl Codec for ProtobufCodec {
+    fn encode_to_writer<T: Serialize + ProtoMessage, W: Write>(
+        &self,
+        obj: &T,
+        mut writer: W,
+    ) -> Result<()> {
+        writer
+            .write_all(&obj.to_proto()?.encode_to_vec())
+            .with_context(|| {
+                format!(
+                    "failed to serialize [{}] (protobuf)",
+                    std::any::type_name::<T>()
+                )
+            })
+    }
+
+    fn decode<T: DeserializeOwned + ProtoMessage>(&self, bytes: &[u8]) -> Result<T> {
+        let proto = T::Proto::decode(bytes).with_context(|| {
+            format!(
+                "failed to deserialize message into [{}] (protobuf)",
+                std::any::type_name::<T>()
+            )
+        })?;
+        T::from_proto(proto)
+    }
+
+    fn encode_into<T: Serialize + ProtoMessage>(&self, obj: &T, buf: &mut Vec<u8>) -> Result<()> {
+        obj.to_proto()?.encode(buf).with_context(|| {
+            format!(
+                "failed to serialize [{}] (protobuf)",
+                std::any::type_name::<T>()
+            )
+        })
+    }
+}
+
+/// Codec used to encode messages, chosen at runtime
+///
+/// This is normally configured via ENV ("RPC_CODEC"), and parsed
+/// into this structure for easy usage from code.
+#[derive(Debug, Default, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum RpcCodec {
+    /// JSON is the default since it's the more likely production use case
+    #[default]
+    Json,
+    /// Bincode
+    Bincode,
+    /// Postcard
+    Postcard,
+    /// MessagePack
+    #[serde(rename = "msgpack")]
+    MessagePack,
+    /// CBOR
+    Cbor,
+    /// Protobuf
+    Protobuf,
+}
+
+impl std::str::FromStr for RpcCodec {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "json" => Ok(Self::Json),
+            "bincode" => Ok(Self::Bincode),
+            "postcard" => Ok(Self::Postcard),
+            "msgpack" => Ok(Self::MessagePack),
+            "cbor" => Ok(Self::Cbor),
+            "protobuf" => Ok(Self::Protobuf),
+            _ => bail!("invalid RpcCodec value [{s}]"),
+        }
+    }
+}
+
+impl RpcCodec {
+    /// Retreive from env or use the default (Json)
+    pub fn from_env_or_default(values: impl Iterator<Item = (String, String)>) -> Self {
+        for (k, v) in values {
+            if k == ENV_VAR_RPC_CODEC {
+                return <Self as std::str::FromStr>::from_str(&v).unwrap_or(Self::default());
+            }
+        }
+        Self::default()
+    }
+}
+
+impl Codec for RpcCodec {
+    fn encode_to_writer<T: Serialize + ProtoMessage, W: Write>(
+        &self,
+        obj: &T,
+        writer: W,
+    ) -> Result<()> {
+        match self {
+            Self::Json => JsonCodec.encode_to_writer(obj, writer),
+            Self::Bincode => BincodeCodec.encode_to_writer(obj, writer),
+            Self::Postcard => PostcardCodec.encode_to_writer(obj, writer),
+            Self::MessagePack => MessagePackCodec.encode_to_writer(obj, writer),
+            Self::Cbor => CborCodec.encode_to_writer(obj, writer),
+            Self::Protobuf => ProtobufCodec.encode_to_writer(obj, writer),
+        }
+    }
+
+    fn decode<T: DeserializeOwned + ProtoMessage>(&self, bytes: &[u8]) -> Result<T> {
+        match self {
+            Self::Json => JsonCodec.decode(bytes),
+            Self::Bincode => BincodeCodec.decode(bytes),
+            Self::Postcard => PostcardCodec.decode(bytes),
+            Self::MessagePack => MessagePackCodec.decode(bytes),
+            Self::Cbor => CborCodec.decode(bytes),
+            Self::Protobuf => ProtobufCodec.decode(bytes),
+        }
+    }
+
+    fn encode_into<T: Serialize + ProtoMessage>(&self, obj: &T, buf: &mut Vec<u8>) -> Result<()> {
+        match self {
+            // Protobuf messages can be encoded directly into the buffer
+            Self::Protobuf => ProtobufCodec.encode_into(obj, buf),
+            _ => self.encode_to_writer(obj, buf),
+        }
+    }
+}
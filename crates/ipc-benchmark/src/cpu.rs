@@ -0,0 +1,298 @@
+/*!
+CPU cost of a run, for both the parent & child processes.
+
+Throughput alone flatters transports that busy-wait (ex. `shmem::raw_sync`), since they burn a full core
+on each side to get there. Every parent binary therefore accounts for the CPU time (user & system),
+context switches (voluntary & involuntary) and max RSS of both processes over a run,
+and reports round-trips per CPU-second alongside round-trips per wall-second.
+
+The parent's usage is read with `getrusage(RUSAGE_SELF)`, and covers all of its threads.
+For `baseline`, where the child is a thread of the parent (and there is no child process),
+a single in-process figure is reported instead (see [`CpuAccounting::start_in_process`]).
+
+The child's usage is read with `getrusage(RUSAGE_CHILDREN)` once it has been killed & waited for,
+minus the usage it had already accrued when the run started (read from `/proc/<pid>/stat` & `/proc/<pid>/status`),
+so that its start-up is not counted. Where `/proc` is not available, the child's start-up is included.
+**/
+
+use std::process::Child;
+use std::time::Duration;
+
+use anyhow::{anyhow, Context as _, Result};
+use conv::ValueFrom as _;
+use nix::sys::resource::{getrusage, UsageWho};
+use nix::sys::time::{TimeVal, TimeValLike as _};
+use nix::unistd::{sysconf, SysconfVar};
+use tracing::{info, warn};
+
+use crate::trials::format_cpu_cost_line;
+
+/// CPU usage of a process
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct CpuUsage {
+    /// CPU time spent in user mode
+    pub user: Duration,
+
+    /// CPU time spent in kernel mode
+    pub system: Duration,
+
+    /// Number of context switches where the process gave up the CPU (ex. to block on I/O)
+    pub voluntary_context_switches: u64,
+
+    /// Number of context switches where the process was preempted
+    pub involuntary_context_switches: u64,
+
+    /// Maximum resident set size (in KiB)
+    pub max_rss_kib: u64,
+}
+
+impl CpuUsage {
+    /// Read the CPU usage of the current process (all threads included)
+    pub fn of_self() -> Result<Self> {
+        Self::from_rusage(UsageWho::RUSAGE_SELF)
+    }
+
+    /// Read the CPU usage of all children of the current process that have terminated & been waited for
+    ///
+    /// Max RSS is that of the largest child.
+    pub fn of_children() -> Result<Self> {
+        Self::from_rusage(UsageWho::RUSAGE_CHILDREN)
+    }
+
+    /// Read the CPU usage of a running process, from `/proc/<pid>/stat` & `/proc/<pid>/status`
+    pub fn of_process(pid: u32) -> Result<Self> {
+        let stat_path = format!("/proc/{pid}/stat");
+        let stat = std::fs::read_to_string(&stat_path)
+            .with_context(|| format!("failed to read [{stat_path}]"))?;
+        // NOTE: the command name (2nd field) is in parentheses and may contain spaces,
+        // so fields are counted from the closing parenthesis (starting at the 3rd field)
+        let fields = stat
+            .rsplit_once(')')
+            .map(|(_, fields)| fields.split_whitespace().collect::<Vec<_>>())
+            .with_context(|| format!("invalid contents of [{stat_path}]"))?;
+        let ticks_per_second = sysconf(SysconfVar::CLK_TCK)
+            .context("failed to get clock ticks per second")?
+            .and_then(|ticks| u64::try_from(ticks).ok())
+            .filter(|ticks| *ticks > 0)
+            .context("missing clock ticks per second")?;
+        let cpu_time = |field: usize, name: &str| -> Result<Duration> {
+            let ticks = fields
+                .get(field - 3)
+                .with_context(|| format!("missing [{name}] in [{stat_path}]"))?
+                .parse::<u64>()
+                .with_context(|| format!("failed to parse [{name}] in [{stat_path}]"))?;
+            Ok(Duration::from_nanos(
+                ticks.saturating_mul(1_000_000_000) / ticks_per_second,
+            ))
+        };
+
+        let status_path = format!("/proc/{pid}/status");
+        let status = std::fs::read_to_string(&status_path)
+            .with_context(|| format!("failed to read [{status_path}]"))?;
+        let status_value = |name: &str| -> Result<u64> {
+            status
+                .lines()
+                .find_map(|line| line.strip_prefix(name)?.strip_prefix(':'))
+                .and_then(|value| value.split_whitespace().next())
+                .with_context(|| format!("missing [{name}] in [{status_path}]"))?
+                .parse::<u64>()
+                .with_context(|| format!("failed to parse [{name}] in [{status_path}]"))
+        };
+
+        Ok(Self {
+            user: cpu_time(14, "utime")?,
+            system: cpu_time(15, "stime")?,
+            voluntary_context_switches: status_value("voluntary_ctxt_switches")?,
+            involuntary_context_switches: status_value("nonvoluntary_ctxt_switches")?,
+            max_rss_kib: status_value("VmHWM")?,
+        })
+    }
+
+    /// Read CPU usage with `getrusage`
+    fn from_rusage(who: UsageWho) -> Result<Self> {
+        let usage =
+            getrusage(who).with_context(|| format!("failed to get resource usage of [{who:?}]"))?;
+        Ok(Self {
+            user: duration_from_timeval(usage.user_time())?,
+            system: duration_from_timeval(usage.system_time())?,
+            voluntary_context_switches: u64::try_from(usage.voluntary_context_switches())
+                .context("invalid voluntary context switches")?,
+            involuntary_context_switches: u64::try_from(usage.involuntary_context_switches())
+                .context("invalid involuntary context switches")?,
+            max_rss_kib: u64::try_from(usage.max_rss()).context("invalid max RSS")?,
+        })
+    }
+
+    /// Total CPU time (user & system)
+    #[must_use]
+    pub fn cpu_time(&self) -> Duration {
+        self.user + self.system
+    }
+
+    /// CPU usage accrued since an earlier reading (max RSS is kept as-is, since it is not cumulative)
+    #[must_use]
+    pub fn since(&self, earlier: &Self) -> Self {
+        Self {
+            user: self.user.saturating_sub(earlier.user),
+            system: self.system.saturating_sub(earlier.system),
+            voluntary_context_switches: self
+                .voluntary_context_switches
+                .saturating_sub(earlier.voluntary_context_switches),
+            involuntary_context_switches: self
+                .involuntary_context_switches
+                .saturating_sub(earlier.involuntary_context_switches),
+            max_rss_kib: self.max_rss_kib,
+        }
+    }
+
+    /// Report CPU usage (via tracing, and on STDERR like the rest of the results), under a given name (ex. `parent`)
+    fn report(&self, name: &str) {
+        let user_seconds = self.user.as_secs_f64();
+        let system_seconds = self.system.as_secs_f64();
+        let Self {
+            voluntary_context_switches,
+            involuntary_context_switches,
+            max_rss_kib,
+            ..
+        } = *self;
+        info!(
+            name,
+            user_seconds,
+            system_seconds,
+            voluntary_context_switches,
+            involuntary_context_switches,
+            max_rss_kib,
+            "computed CPU usage"
+        );
+        eprintln!("{name} cpu: user [{user_seconds:.3}s] system [{system_seconds:.3}s] context switches: voluntary [{voluntary_context_switches}] involuntary [{involuntary_context_switches}] max rss [{max_rss_kib} KiB]");
+    }
+}
+
+/// CPU usage of the parent & child processes over a run
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CpuCost {
+    /// CPU usage of the parent process
+    pub parent: CpuUsage,
+
+    /// CPU usage of the child process (`None` if the child ran in-process, as part of the parent)
+    pub child: Option<CpuUsage>,
+}
+
+impl CpuCost {
+    /// Total CPU time of both processes
+    #[must_use]
+    pub fn cpu_time(&self) -> Duration {
+        self.parent.cpu_time()
+            + self
+                .child
+                .as_ref()
+                .map_or(Duration::ZERO, CpuUsage::cpu_time)
+    }
+
+    /// Report CPU usage of both processes, and round-trips per CPU-second next to round-trips per wall-second
+    /// (via tracing, and on STDERR like the rest of the results)
+    pub fn report(&self, roundtrips: u64, roundtrips_per_second: f64) -> Result<()> {
+        match &self.child {
+            Some(child) => {
+                self.parent.report("parent");
+                child.report("child");
+            }
+            None => self.parent.report("in-process"),
+        }
+
+        let cpu_seconds = self.cpu_time().as_secs_f64();
+        // NOTE: very short runs may not accrue any (measurable) CPU time
+        let roundtrips_per_cpu_second = if cpu_seconds > 0.0 {
+            Some(
+                f64::value_from(roundtrips).context("failed to convert roundtrips to f64")?
+                    / cpu_seconds,
+            )
+        } else {
+            None
+        };
+        info!(
+            cpu_seconds,
+            roundtrips_per_cpu_second, roundtrips_per_second, "computed CPU cost"
+        );
+        eprintln!(
+            "{}",
+            format_cpu_cost_line(
+                cpu_seconds,
+                roundtrips_per_cpu_second,
+                roundtrips_per_second
+            )
+        );
+        Ok(())
+    }
+}
+
+/// Accounting of the CPU usage of a parent & its child, from the start of a run
+#[derive(Debug, Clone)]
+pub struct CpuAccounting {
+    /// CPU usage of the parent when the run started
+    parent_at_start: CpuUsage,
+
+    /// CPU usage of terminated children when the run started
+    children_at_start: CpuUsage,
+
+    /// CPU usage of the child when the run started (if it could be read)
+    child_at_start: Option<CpuUsage>,
+}
+
+impl CpuAccounting {
+    /// Start accounting for CPU usage, once the child has been spawned and before pings are sent
+    pub fn start(child: &Child) -> Result<Self> {
+        let child_at_start = CpuUsage::of_process(child.id())
+            .inspect_err(|e| {
+                warn!(error = %e, "failed to read child CPU usage, its start-up will be counted");
+            })
+            .ok();
+        Ok(Self {
+            parent_at_start: CpuUsage::of_self()?,
+            children_at_start: CpuUsage::of_children()?,
+            child_at_start,
+        })
+    }
+
+    /// Start accounting for CPU usage of a run where the child is a thread of the parent, before pings are sent
+    pub fn start_in_process() -> Result<Self> {
+        Ok(Self {
+            parent_at_start: CpuUsage::of_self()?,
+            children_at_start: CpuUsage::default(),
+            child_at_start: None,
+        })
+    }
+
+    /// Finish accounting for CPU usage, once the child has been killed
+    ///
+    /// The child is waited for here, since `RUSAGE_CHILDREN` only covers children that have been waited for.
+    pub fn finish(self, child: &mut Child) -> Result<CpuCost> {
+        let parent = CpuUsage::of_self()?.since(&self.parent_at_start);
+        child.wait().context("failed to wait for child process")?;
+        let mut child_usage = CpuUsage::of_children()?.since(&self.children_at_start);
+        if let Some(child_at_start) = &self.child_at_start {
+            child_usage = child_usage.since(child_at_start);
+        }
+        Ok(CpuCost {
+            parent,
+            child: Some(child_usage),
+        })
+    }
+
+    /// Finish accounting for CPU usage of a run where the child is a thread of the parent
+    /// (see [`CpuAccounting::start_in_process`])
+    pub fn finish_in_process(self) -> Result<CpuCost> {
+        let parent = CpuUsage::of_self()?.since(&self.parent_at_start);
+        Ok(CpuCost {
+            parent,
+            child: None,
+        })
+    }
+}
+
+/// Convert a [`TimeVal`] (as returned by `getrusage`) to a [`Duration`]
+fn duration_from_timeval(tv: TimeVal) -> Result<Duration> {
+    u64::try_from(tv.num_microseconds())
+        .map(Duration::from_micros)
+        .map_err(|_| anyhow!("invalid negative CPU time [{tv}]"))
+}
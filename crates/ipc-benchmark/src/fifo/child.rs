@@ -0,0 +1,110 @@
+//! Child-specific IPC implementation over named pipes (FIFOs)
+
+use std::fs::{File, OpenOptions};
+use std::io::{stdin, BufRead};
+
+use anyhow::{ensure, Context as _, Result};
+use tracing::debug;
+use uuid::Uuid;
+
+use crate::fifo::{FifoInit, FifoInitResponse};
+use crate::stream::{is_eof, StreamHandle};
+use crate::{
+    get_monotonic_time_nanos, monotonic_nanos_since, ChildProcess, PingMessage, PongMessage,
+};
+
+/// A child process that performs IPC via a pair of named pipes (FIFOs)
+#[derive(Debug)]
+pub struct FifoChild {
+    /// UUID that should uniquely identify this process
+    uuid: Uuid,
+}
+
+impl Default for FifoChild {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl FifoChild {
+    /// Build a new [`FifoChild`] with a random UUID
+    #[must_use]
+    pub fn new() -> Self {
+        Self {
+            uuid: Uuid::now_v7(),
+        }
+    }
+}
+
+impl ChildProcess for FifoChild {
+    fn id(&self) -> String {
+        self.uuid.to_string()
+    }
+
+    fn run(self) -> Result<()> {
+        debug!("child process running");
+
+        debug!("reading fifo init from STDIN");
+        let mut s = String::new();
+        stdin().lock().read_line(&mut s)?;
+
+        // We expect to receive an init message on STDIN
+        let FifoInit {
+            parent_id,
+            codec,
+            to_child_path,
+            from_child_path,
+        } = serde_json::from_slice(s.as_bytes())
+            .context("failed to read init message from STDIN")?;
+
+        // Open the FIFOs (in the same order as the parent)
+        debug!("opening FIFOs");
+        let from_parent = File::open(&to_child_path)
+            .with_context(|| format!("failed to open FIFO @ [{}]", to_child_path.display()))?;
+        let to_parent = OpenOptions::new()
+            .write(true)
+            .open(&from_child_path)
+            .with_context(|| format!("failed to open FIFO @ [{}]", from_child_path.display()))?;
+        let mut stream = StreamHandle::new(from_parent, to_parent, codec);
+
+        stream
+            .write_message(&FifoInitResponse {
+                parent_id: parent_id.clone(),
+                child_id: self.id(),
+            })
+            .context("failed to write init response to parent")?;
+        debug!("successfully wrote init response to parent");
+
+        // Enter reading/writing loop
+        debug!("entering read loop...");
+        loop {
+            let PingMessage {
+                sender_id,
+                receiver_id,
+                sent_at_ns,
+                payload,
+                ..
+            } = match stream.read_message() {
+                Ok(msg) => msg,
+                Err(e) if is_eof(&e) => {
+                    debug!("parent closed the FIFO, exiting");
+                    return Ok(());
+                }
+                Err(e) => return Err(e.context("failed to read ping message")),
+            };
+            let ping_latency_ns = monotonic_nanos_since(sent_at_ns)?;
+            ensure!(sender_id == parent_id, "sender should be parent");
+            ensure!(receiver_id == self.id(), "receiver should be child");
+
+            stream
+                .write_message(&PongMessage {
+                    sender_id: self.id(),
+                    receiver_id: sender_id,
+                    sent_at_ns: get_monotonic_time_nanos()?,
+                    ping_latency_ns,
+                    payload,
+                })
+                .context("failed to send pong to parent")?;
+        }
+    }
+}
@@ -0,0 +1,57 @@
+/*!
+Process IPC over a pair of [named pipes][wiki-named-pipes] (FIFOs, created with `mkfifo`).
+
+The parent creates two FIFOs in a private temporary directory (one per direction) and sends their
+paths to the child over STDIN. Both sides then open the FIFOs by path, and pings and pongs travel
+as length-prefixed messages.
+
+Unlike [`crate::pipe`], the child does not need to inherit any file descriptors from the parent,
+the FIFOs are found purely by path.
+
+[wiki-named-pipes]: <https://en.wikipedia.org/wiki/Named_pipe>
+**/
+
+use std::path::PathBuf;
+
+use serde::{Deserialize, Serialize};
+
+use crate::codec::RpcCodec;
+use crate::proto::impl_init_response_proto;
+
+pub mod child;
+pub mod parent;
+
+pub use child::FifoChild;
+pub use parent::FifoParent;
+
+/// Message sent to child processes over STDIN that contains
+/// information necessary for the child to connect to the parent
+///
+/// NOTE: opening a FIFO blocks until the other end is opened as well, so both sides
+/// *must* open `to_child_path` first, then `from_child_path`.
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
+struct FifoInit {
+    /// UUID of the parent
+    parent_id: String,
+
+    /// Codec used to encode messages (after this init message)
+    codec: RpcCodec,
+
+    /// Path to the FIFO that the parent writes to (and the child reads from)
+    to_child_path: PathBuf,
+
+    /// Path to the FIFO that the child writes to (and the parent reads from)
+    from_child_path: PathBuf,
+}
+
+/// Message sent from the child process (over the FIFO) upon successful connection
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
+struct FifoInitResponse {
+    /// UUID of the parent
+    parent_id: String,
+
+    /// UUID of the child
+    child_id: String,
+}
+
+impl_init_response_proto!(FifoInitResponse);
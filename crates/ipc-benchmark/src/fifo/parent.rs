@@ -0,0 +1,199 @@
+//! Parent-specific IPC implementation over named pipes (FIFOs)
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::process::{Child, Command, Stdio};
+
+use anyhow::{ensure, Context as _, Result};
+use nix::sys::stat::Mode;
+use tempfile::TempDir;
+use tracing::debug;
+use uuid::Uuid;
+
+use crate::codec::RpcCodec;
+use crate::fifo::{FifoInit, FifoInitResponse};
+use crate::latency::OneWayLatencies;
+use crate::stream::StreamHandle;
+use crate::{
+    get_monotonic_time_nanos, payload_size_bytes_from_env_or_default, synthetic_payload, ChildId,
+    ChildName, ParentProcess, PingMessage, Pinger, PongMessage, RpcPong,
+};
+
+/// Bi-directional channel for communication with a single child
+struct FifoChannel {
+    /// Self-reported ID of the child
+    child_id: ChildId,
+
+    /// FIFOs connected to the child (reading from the child FIFO, writing to the parent FIFO)
+    stream: StreamHandle<File, File>,
+
+    /// Temporary directory that contains the FIFOs
+    ///
+    /// NOTE: this must be held so that the directory is not removed while the child is running
+    _fifo_dir: TempDir,
+}
+
+/// A parent process that performs IPC via a pair of named pipes (FIFOs)
+pub struct FifoParent {
+    /// UUID of the parent process
+    uuid: Uuid,
+
+    /// Synthetic payload sent with every ping (see `RPC_PAYLOAD_SIZE_BYTES`)
+    payload: Vec<u8>,
+
+    /// Codec used to encode messages (sent to children in the init message)
+    codec: RpcCodec,
+
+    /// Channels for communicating with children, by child name
+    ///
+    /// SAFETY: We're safe using a `RefCell` here because this structure
+    /// is very much *not* multi-threaded.
+    channels: HashMap<ChildName, RefCell<FifoChannel>>,
+}
+
+impl std::fmt::Debug for FifoParent {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("FifoParent")
+            .field("uuid", &self.uuid)
+            .finish()
+    }
+}
+
+impl Default for FifoParent {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl FifoParent {
+    /// Create a new [`FifoParent`]
+    #[must_use]
+    pub fn new() -> Self {
+        Self {
+            uuid: Uuid::now_v7(),
+            payload: synthetic_payload(payload_size_bytes_from_env_or_default(std::env::vars())),
+            codec: RpcCodec::from_env_or_default(std::env::vars()),
+            channels: HashMap::new(),
+        }
+    }
+}
+
+impl ParentProcess for FifoParent {
+    fn id(&self) -> String {
+        self.uuid.to_string()
+    }
+
+    fn spawn_child(&mut self, name: impl AsRef<str>, mut cmd: Command) -> Result<Child> {
+        let name = name.as_ref();
+
+        // Create both FIFOs in a private temp dir, before the child is started
+        let fifo_dir = tempfile::Builder::new()
+            .prefix("ipc-benchmark-fifo-")
+            .tempdir()
+            .context("failed to create temp dir for FIFOs")?;
+        let to_child_path = fifo_dir.path().join("to-child.fifo");
+        let from_child_path = fifo_dir.path().join("from-child.fifo");
+        for path in [&to_child_path, &from_child_path] {
+            debug!(path = %path.display(), "creating FIFO");
+            nix::unistd::mkfifo(path, Mode::S_IRUSR | Mode::S_IWUSR)
+                .with_context(|| format!("failed to create FIFO @ [{}]", path.display()))?;
+        }
+
+        // Spawn the child
+        debug!("spawning child");
+        let mut child = cmd
+            .stdin(Stdio::piped())
+            .spawn()
+            .context("failed to spawn child process")?;
+
+        // Send the init message over STDIN
+        let init_msg = FifoInit {
+            parent_id: self.id(),
+            codec: self.codec,
+            to_child_path: to_child_path.clone(),
+            from_child_path: from_child_path.clone(),
+        };
+        debug!(?init_msg, "writing init to child STDIN");
+        let mut child_stdin = child.stdin.take().context("failed to get child STDIN")?;
+        child_stdin
+            .write_all(&serde_json::to_vec(&init_msg).context("failed to serialize init msg")?)
+            .context("failed to write init msg")?;
+        child_stdin
+            .write_all(b"\r\n")
+            .context("failed to write new line")?;
+        child_stdin.flush().context("failed to flush child STDIN")?;
+
+        // Open the FIFOs (in the same order as the child), each of which blocks
+        // until the child has opened the other end
+        debug!("opening FIFOs");
+        let to_child = OpenOptions::new()
+            .write(true)
+            .open(&to_child_path)
+            .with_context(|| format!("failed to open FIFO @ [{}]", to_child_path.display()))?;
+        let from_child = File::open(&from_child_path)
+            .with_context(|| format!("failed to open FIFO @ [{}]", from_child_path.display()))?;
+        let mut stream = StreamHandle::new(from_child, to_child, self.codec);
+
+        debug!("waiting for init response from child");
+        let init_resp: FifoInitResponse = stream
+            .read_message()
+            .context("failed to read init response from child")?;
+        ensure!(
+            init_resp.parent_id == self.id(),
+            "parent ID reported by child did not match"
+        );
+        debug!(child_id = init_resp.child_id, "received init response");
+
+        // Save information to local registry
+        self.channels.insert(
+            name.into(),
+            RefCell::new(FifoChannel {
+                child_id: init_resp.child_id,
+                stream,
+                _fifo_dir: fifo_dir,
+            }),
+        );
+
+        Ok(child)
+    }
+}
+
+impl Pinger for FifoParent {
+    fn roundtrip_ping(&self, child_name: impl AsRef<str>) -> Result<Option<OneWayLatencies>> {
+        let child = child_name.as_ref();
+        let mut chan = self
+            .channels
+            .get(child)
+            .with_context(|| format!("failed to find child [{child}]"))?
+            .borrow_mut();
+        let FifoChannel {
+            child_id, stream, ..
+        } = &mut *chan;
+
+        debug!(child, "sending ping message to child");
+        stream
+            .write_message(&PingMessage {
+                sender_id: self.id(),
+                receiver_id: child_id.clone(),
+                sent_at_ns: get_monotonic_time_nanos()?,
+                payload: self.payload.clone(),
+            })
+            .context("failed to send ping message to child")?;
+
+        debug!(child, "reading pong message from child");
+        let pong_msg: PongMessage = stream
+            .read_message()
+            .context("failed to read pong message")?;
+        let latencies = pong_msg.one_way_latencies()?;
+        ensure!(pong_msg.sender_id() == child_id, "child ID matches");
+        ensure!(pong_msg.receiver_id() == self.id(), "parent ID matches");
+        ensure!(
+            pong_msg.payload() == self.payload.as_slice(),
+            "payload matches"
+        );
+
+        Ok(latencies)
+    }
+}
@@ -0,0 +1,164 @@
+//! Child-specific IPC implementation via gRPC
+
+use std::io::{stdin, BufRead};
+
+use anyhow::{Context as _, Result};
+use tokio::net::UnixListener;
+use tokio_stream::wrappers::UnixListenerStream;
+use tonic::transport::Server;
+use tonic::{Request, Response, Status};
+use tracing::debug;
+use uuid::Uuid;
+
+use crate::grpc::GrpcInit;
+use crate::proto::schema::ping_service_server::{PingService, PingServiceServer};
+use crate::proto::schema::{self, GrpcInitRequest};
+use crate::proto::ProtoMessage as _;
+use crate::{
+    get_monotonic_time_nanos, monotonic_nanos_since, ChildProcess, PingMessage, PongMessage,
+};
+
+/// A child process that performs IPC via gRPC over a Unix domain socket
+#[derive(Debug)]
+pub struct GrpcChild {
+    /// UUID that should uniquely identify this process
+    uuid: Uuid,
+}
+
+impl Default for GrpcChild {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl GrpcChild {
+    /// Build a new [`GrpcChild`] with a random UUID
+    #[must_use]
+    pub fn new() -> Self {
+        Self {
+            uuid: Uuid::now_v7(),
+        }
+    }
+}
+
+/// Implementation of the `PingService`, served to the parent
+#[derive(Debug)]
+struct PingServer {
+    /// UUID of the parent
+    parent_id: String,
+
+    /// UUID of the child
+    child_id: String,
+}
+
+#[tonic::async_trait]
+impl PingService for PingServer {
+    async fn init(
+        &self,
+        request: Request<GrpcInitRequest>,
+    ) -> Result<Response<schema::InitResponse>, Status> {
+        let GrpcInitRequest { parent_id } = request.into_inner();
+        if parent_id != self.parent_id {
+            return Err(Status::invalid_argument(format!(
+                "parent ID [{parent_id}] did not match"
+            )));
+        }
+        Ok(Response::new(schema::InitResponse {
+            parent_id,
+            child_id: self.child_id.clone(),
+        }))
+    }
+
+    async fn ping(
+        &self,
+        request: Request<schema::PingMessage>,
+    ) -> Result<Response<schema::PongMessage>, Status> {
+        let PingMessage {
+            sender_id,
+            receiver_id,
+            sent_at_ns,
+            payload,
+            ..
+        } = PingMessage::from_proto(request.into_inner())
+            .map_err(|e| Status::invalid_argument(format!("invalid ping message: {e:#}")))?;
+        let ping_latency_ns =
+            monotonic_nanos_since(sent_at_ns).map_err(|e| Status::internal(format!("{e:#}")))?;
+        if sender_id != self.parent_id {
+            return Err(Status::invalid_argument("sender should be parent"));
+        }
+        if receiver_id != self.child_id {
+            return Err(Status::invalid_argument("receiver should be child"));
+        }
+
+        let pong = PongMessage {
+            sender_id: self.child_id.clone(),
+            receiver_id: sender_id,
+            sent_at_ns: get_monotonic_time_nanos()
+                .map_err(|e| Status::internal(format!("{e:#}")))?,
+            ping_latency_ns,
+            payload,
+        };
+        Ok(Response::new(
+            pong.to_proto()
+                .map_err(|e| Status::internal(format!("{e:#}")))?,
+        ))
+    }
+}
+
+impl ChildProcess for GrpcChild {
+    fn id(&self) -> String {
+        self.uuid.to_string()
+    }
+
+    fn run(self) -> Result<()> {
+        debug!("child process running");
+
+        debug!("reading grpc init from STDIN");
+        let mut s = String::new();
+        stdin().lock().read_line(&mut s)?;
+
+        // We expect to receive an init message on STDIN
+        let GrpcInit {
+            parent_id,
+            socket_path,
+        } = serde_json::from_slice(s.as_bytes())
+            .context("failed to read init message from STDIN")?;
+
+        // Shut down once STDIN is closed (i.e. the parent has gone away)
+        let (shutdown_tx, shutdown_rx) = tokio::sync::oneshot::channel::<()>();
+        std::thread::spawn(move || {
+            let _ = std::io::copy(&mut stdin(), &mut std::io::sink());
+            let _ = shutdown_tx.send(());
+        });
+
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_io()
+            .enable_time()
+            .build()
+            .context("failed to build tokio runtime")?;
+        let server = PingServer {
+            parent_id,
+            child_id: self.id(),
+        };
+
+        runtime.block_on(async move {
+            debug!(socket_path = %socket_path.display(), "binding unix socket");
+            let listener = UnixListener::bind(&socket_path).with_context(|| {
+                format!("failed to bind unix socket @ [{}]", socket_path.display())
+            })?;
+
+            debug!("serving ping service...");
+            Server::builder()
+                .add_service(
+                    // NOTE: tonic limits decoded messages to 4MiB by default, which large payloads exceed
+                    PingServiceServer::new(server).max_decoding_message_size(usize::MAX),
+                )
+                .serve_with_incoming_shutdown(UnixListenerStream::new(listener), async {
+                    let _ = shutdown_rx.await;
+                    debug!("parent closed STDIN, exiting");
+                })
+                .await
+                .context("gRPC server failed")
+        })
+    }
+}
@@ -0,0 +1,40 @@
+/*!
+Process IPC via [gRPC][grpc] (using [`tonic`][crate-tonic]), over a [Unix domain socket][wiki-uds].
+
+This is what many teams deploy for sidecar communication, so it's a useful measure of how much
+a complete HTTP/2-based RPC stack costs, compared to the hand-rolled transports.
+
+The parent picks a socket path in a private temporary directory, then sends it to the child over
+STDIN. The child binds the socket and serves the `PingService` (see `proto/ipc_benchmark.proto`),
+which the parent connects to and calls once per round-trip. The handshake is completed with a call
+to `Init`.
+
+The child keeps serving until its STDIN is closed, which happens when the parent goes away.
+
+Messages are always encoded as protobuf, so [`crate::codec`] is not used here.
+
+[grpc]: <https://grpc.io>
+[crate-tonic]: <https://crates.io/crates/tonic>
+[wiki-uds]: <https://en.wikipedia.org/wiki/Unix_domain_socket>
+**/
+
+use std::path::PathBuf;
+
+use serde::{Deserialize, Serialize};
+
+pub mod child;
+pub mod parent;
+
+pub use child::GrpcChild;
+pub use parent::GrpcParent;
+
+/// Message sent to child processes over STDIN that contains
+/// information necessary for the child to serve the parent
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
+struct GrpcInit {
+    /// UUID of the parent
+    parent_id: String,
+
+    /// Path at which the child should bind the Unix domain socket it serves on
+    socket_path: PathBuf,
+}
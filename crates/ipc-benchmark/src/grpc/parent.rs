@@ -0,0 +1,232 @@
+//! Parent-specific IPC implementation via gRPC
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::io::Write;
+use std::path::PathBuf;
+use std::process::{Child, ChildStdin, Command, Stdio};
+use std::time::{Duration, Instant};
+
+use anyhow::{bail, ensure, Context as _, Result};
+use hyper_util::rt::TokioIo;
+use tempfile::TempDir;
+use tokio::net::UnixStream;
+use tokio::runtime::Runtime;
+use tonic::transport::{Channel, Endpoint, Uri};
+use tracing::debug;
+use uuid::Uuid;
+
+use crate::grpc::GrpcInit;
+use crate::latency::OneWayLatencies;
+use crate::proto::schema::ping_service_client::PingServiceClient;
+use crate::proto::schema::GrpcInitRequest;
+use crate::proto::ProtoMessage as _;
+use crate::{
+    get_monotonic_time_nanos, payload_size_bytes_from_env_or_default, synthetic_payload, ChildId,
+    ChildName, ParentProcess, PingMessage, Pinger, PongMessage, RpcPong,
+};
+
+/// How long to wait for a child to start serving, before giving up
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// How long to wait between attempts to connect to a child that is not serving yet
+const CONNECT_RETRY_INTERVAL: Duration = Duration::from_millis(5);
+
+/// Connection to a single child
+struct GrpcChannel {
+    /// Self-reported ID of the child
+    child_id: ChildId,
+
+    /// Client for the `PingService` served by the child
+    ///
+    /// SAFETY: We're safe using a `RefCell` here because this structure
+    /// is very much *not* multi-threaded.
+    client: RefCell<PingServiceClient<Channel>>,
+
+    /// STDIN of the child, which tells the child to shut down when closed
+    ///
+    /// NOTE: this must be held so that the child keeps serving
+    _child_stdin: ChildStdin,
+
+    /// Temporary directory that contains the socket file
+    ///
+    /// NOTE: this must be held so that the directory is not removed while the child is running
+    _socket_dir: TempDir,
+}
+
+/// A parent process that performs IPC via gRPC over a Unix domain socket
+pub struct GrpcParent {
+    /// UUID of the parent process
+    uuid: Uuid,
+
+    /// Synthetic payload sent with every ping (see `RPC_PAYLOAD_SIZE_BYTES`)
+    payload: Vec<u8>,
+
+    /// Runtime that drives the connections to children (single threaded)
+    runtime: Runtime,
+
+    /// Connections to children, by child name
+    channels: HashMap<ChildName, GrpcChannel>,
+}
+
+impl std::fmt::Debug for GrpcParent {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("GrpcParent")
+            .field("uuid", &self.uuid)
+            .finish()
+    }
+}
+
+impl GrpcParent {
+    /// Create a new [`GrpcParent`]
+    pub fn new() -> Result<Self> {
+        Ok(Self {
+            uuid: Uuid::now_v7(),
+            payload: synthetic_payload(payload_size_bytes_from_env_or_default(std::env::vars())),
+            runtime: tokio::runtime::Builder::new_current_thread()
+                .enable_io()
+                .enable_time()
+                .build()
+                .context("failed to build tokio runtime")?,
+            channels: HashMap::new(),
+        })
+    }
+}
+
+/// Connect to the `PingService` served by a child at a given socket path
+///
+/// Since the child binds the socket itself, connecting is retried until it is ready
+/// (or [`CONNECT_TIMEOUT`] has passed).
+async fn connect(socket_path: PathBuf) -> Result<PingServiceClient<Channel>> {
+    let started_at = Instant::now();
+    loop {
+        let socket_path = socket_path.clone();
+        // NOTE: the URI is required by the HTTP/2 client, but is ignored by the connector
+        let result = Endpoint::from_static("http://[::]:50051")
+            .connect_with_connector(tower::service_fn(move |_: Uri| {
+                let socket_path = socket_path.clone();
+                async move {
+                    Ok::<_, std::io::Error>(TokioIo::new(UnixStream::connect(socket_path).await?))
+                }
+            }))
+            .await;
+        match result {
+            Ok(channel) => {
+                // NOTE: tonic limits decoded messages to 4MiB by default, which large payloads exceed
+                return Ok(PingServiceClient::new(channel).max_decoding_message_size(usize::MAX));
+            }
+            Err(e) if started_at.elapsed() < CONNECT_TIMEOUT => {
+                debug!(?e, "child not serving yet, retrying connect");
+                tokio::time::sleep(CONNECT_RETRY_INTERVAL).await;
+            }
+            Err(e) => bail!("failed to connect to child after {CONNECT_TIMEOUT:?}: {e}"),
+        }
+    }
+}
+
+impl ParentProcess for GrpcParent {
+    fn id(&self) -> String {
+        self.uuid.to_string()
+    }
+
+    fn spawn_child(&mut self, name: impl AsRef<str>, mut cmd: Command) -> Result<Child> {
+        let name = name.as_ref();
+
+        // Pick a socket path in a private temp dir (the child binds it)
+        let socket_dir = tempfile::Builder::new()
+            .prefix("ipc-benchmark-grpc-")
+            .tempdir()
+            .context("failed to create temp dir for socket")?;
+        let socket_path = socket_dir.path().join("child.sock");
+
+        // Spawn the child
+        debug!("spawning child");
+        let mut child = cmd
+            .stdin(Stdio::piped())
+            .spawn()
+            .context("failed to spawn child process")?;
+
+        // Send the init message over STDIN
+        let init_msg = GrpcInit {
+            parent_id: self.id(),
+            socket_path: socket_path.clone(),
+        };
+        debug!(?init_msg, "writing init to child STDIN");
+        let mut child_stdin = child.stdin.take().context("failed to get child STDIN")?;
+        child_stdin
+            .write_all(&serde_json::to_vec(&init_msg).context("failed to serialize init msg")?)
+            .context("failed to write init msg")?;
+        child_stdin
+            .write_all(b"\r\n")
+            .context("failed to write new line")?;
+        child_stdin.flush().context("failed to flush child STDIN")?;
+
+        // Wait for the child to start serving, and complete the handshake
+        debug!(socket_path = %socket_path.display(), "connecting to child");
+        let mut client = self
+            .runtime
+            .block_on(connect(socket_path))
+            .context("failed to connect to child")?;
+        let init_resp = self
+            .runtime
+            .block_on(client.init(GrpcInitRequest {
+                parent_id: self.id(),
+            }))
+            .context("failed to call init on child")?
+            .into_inner();
+        ensure!(
+            init_resp.parent_id == self.id(),
+            "parent ID reported by child did not match"
+        );
+        debug!(child_id = init_resp.child_id, "received init response");
+
+        // Save information to local registry
+        self.channels.insert(
+            name.into(),
+            GrpcChannel {
+                child_id: init_resp.child_id,
+                client: RefCell::new(client),
+                _child_stdin: child_stdin,
+                _socket_dir: socket_dir,
+            },
+        );
+
+        Ok(child)
+    }
+}
+
+impl Pinger for GrpcParent {
+    fn roundtrip_ping(&self, child_name: impl AsRef<str>) -> Result<Option<OneWayLatencies>> {
+        let child = child_name.as_ref();
+        let GrpcChannel {
+            child_id, client, ..
+        } = self
+            .channels
+            .get(child)
+            .with_context(|| format!("failed to find child [{child}]"))?;
+
+        debug!(child, "calling ping on child");
+        let ping = PingMessage {
+            sender_id: self.id(),
+            receiver_id: child_id.clone(),
+            sent_at_ns: get_monotonic_time_nanos()?,
+            payload: self.payload.clone(),
+        }
+        .to_proto()?;
+        let response = self
+            .runtime
+            .block_on(client.borrow_mut().ping(ping))
+            .context("failed to call ping on child")?;
+
+        let pong_msg = PongMessage::from_proto(response.into_inner())?;
+        let latencies = pong_msg.one_way_latencies()?;
+        ensure!(pong_msg.sender_id() == child_id, "child ID matches");
+        ensure!(pong_msg.receiver_id() == self.id(), "parent ID matches");
+        ensure!(
+            pong_msg.payload() == self.payload.as_slice(),
+            "payload matches"
+        );
+
+        Ok(latencies)
+    }
+}
@@ -6,14 +6,19 @@ use std::{
 };
 
 use anyhow::{ensure, Context as _, Result};
-use ipc_channel::ipc::{IpcOneShotServer, IpcSender};
+use ipc_channel::ipc::{self, IpcError, IpcOneShotServer, IpcReceiver, IpcSender};
 use tracing::debug;
 use uuid::{NoContext, Timestamp, Uuid};
 
+use crate::codec::{Codec as _, RpcCodec};
+use crate::ipcc::{
+    BulkChannels, BulkPingMessage, BytesChannels, IpcChannelMode, IpcChannelModeSetup,
+    TypedChannels, BULK_PAYLOAD_FILL_BYTE,
+};
 use crate::{
-    get_system_time_millis, ChildProcess, IpcChannelChildInit, IpcChannelChildInitResponse,
-    IpcChannelInitComplete, PingMessage, PongMessage, RawStringPingMessage, RpcMessageComplexity,
-    RpcPing,
+    get_monotonic_time_nanos, monotonic_nanos_since, ChildProcess, IpcChannelChildInit,
+    IpcChannelChildInitResponse, IpcChannelInitComplete, PingMessage, PongMessage,
+    RawStringPingMessage, RpcMessageComplexity, RpcPing,
 };
 
 /// Contains the implementation of the [`ChildProcess`] trait over IPC (via `ipc-channel`)
@@ -29,6 +34,16 @@ pub struct IpcChannelChild {
     ///
     /// NOTE: the parent that is sending should have an identical value set
     rpc_message_complexity: RpcMessageComplexity,
+
+    /// Codec used to encode JSON-complexity messages in the `vec` and `bytes` modes
+    ///
+    /// NOTE: the parent that is sending should have an identical value set
+    codec: RpcCodec,
+
+    /// Kind of channels to use for ping/pong messages
+    ///
+    /// NOTE: the parent that is sending should have an identical value set
+    ipc_channel_mode: IpcChannelMode,
 }
 
 impl IpcChannelChild {
@@ -37,8 +52,73 @@ impl IpcChannelChild {
         Self {
             uuid: Uuid::new_v7(Timestamp::now(NoContext)),
             rpc_message_complexity: RpcMessageComplexity::from_env_or_default(std::env::vars()),
+            codec: RpcCodec::from_env_or_default(std::env::vars()),
+            ipc_channel_mode: IpcChannelMode::from_env_or_default(std::env::vars()),
         }
     }
+
+    /// Send mode-specific channels to the parent, once it has sent a [`IpcChannelModeSetup`]
+    fn send_mode_channels<T: serde::Serialize + serde::de::DeserializeOwned>(
+        from_parent_receiver: &IpcReceiver<Vec<u8>>,
+        channels: T,
+    ) -> Result<()> {
+        let setup = serde_json::from_slice::<IpcChannelModeSetup>(
+            &from_parent_receiver
+                .recv()
+                .context("failed to receive mode setup from parent")?,
+        )
+        .context("failed to parse mode setup message")?;
+        IpcSender::<T>::connect(setup.ipc_server_name)
+            .context("failed to connect to parent IPC server for mode setup")?
+            .send(channels)
+            .context("failed to send mode channels to parent")
+    }
+
+    /// Handle the bytes of a ping, returning the bytes of the pong to send back, depending on message complexity
+    fn handle_ping_payload(&self, msg_bytes: &[u8]) -> Result<Vec<u8>> {
+        // Handle the ping message
+        let (sender_id, ping_latency_ns, payload) = match self.rpc_message_complexity {
+            RpcMessageComplexity::RawString => {
+                let ping_msg = RawStringPingMessage::from_str(
+                    std::str::from_utf8(msg_bytes)
+                        .context("failed to convert incoming bytes to str")?,
+                )?;
+                ensure!(ping_msg.receiver_id() == self.id(), "invalid receiver ID");
+                // NOTE: raw string pings carry no timestamp
+                (ping_msg.sender_id().to_string(), 0, Vec::new())
+            }
+            RpcMessageComplexity::Json => {
+                let ping_msg = self
+                    .codec
+                    .decode::<PingMessage>(msg_bytes)
+                    .context("failed to parse ping msg in child")?;
+                let ping_latency_ns = monotonic_nanos_since(ping_msg.sent_at_ns())?;
+                ensure!(ping_msg.receiver_id() == self.id(), "invalid receiver ID");
+                (
+                    ping_msg.sender_id().to_string(),
+                    ping_latency_ns,
+                    ping_msg.payload,
+                )
+            }
+        };
+
+        // Build pong
+        Ok(match self.rpc_message_complexity {
+            RpcMessageComplexity::RawString => format!("{}|{}|pong", self.id(), sender_id).into(),
+            RpcMessageComplexity::Json => {
+                let pong_msg = PongMessage::new(
+                    self.id(),
+                    sender_id,
+                    get_monotonic_time_nanos()?,
+                    ping_latency_ns,
+                )
+                .with_payload(payload);
+                self.codec
+                    .encode(&pong_msg)
+                    .context("failed to serialize pong message")?
+            }
+        })
+    }
 }
 
 impl Default for IpcChannelChild {
@@ -47,6 +127,20 @@ impl Default for IpcChannelChild {
     }
 }
 
+/// Handle the result of receiving from the parent, where `None` means the parent has disconnected
+///
+/// Children use this to exit cleanly when the parent goes away, rather than reporting an error.
+fn recv_until_disconnected<T>(result: Result<T, IpcError>) -> Result<Option<T>> {
+    match result {
+        Ok(msg) => Ok(Some(msg)),
+        Err(IpcError::Disconnected) => {
+            debug!("parent disconnected, exiting");
+            Ok(None)
+        }
+        Err(e) => Err(e).context("failed to receive message from parent"),
+    }
+}
+
 impl ChildProcess for IpcChannelChild {
     /// Return a unique identifier for the process (UUID)
     fn id(&self) -> String {
@@ -79,12 +173,13 @@ impl ChildProcess for IpcChannelChild {
             .context("failed to connect to parent [{parent_id}] IPC server from child")?;
         sender
             .send(
-                serde_json::to_vec(&IpcChannelChildInitResponse::new(
-                    &self.id(),
-                    &parent_id,
-                    &server_name,
-                ))
-                .context("failed to serialize child init response")?,
+                self.codec
+                    .encode(&IpcChannelChildInitResponse::new(
+                        &self.id(),
+                        &parent_id,
+                        &server_name,
+                    ))
+                    .context("failed to serialize child init response")?,
             )
             .context("failed to send child init response")?;
 
@@ -93,49 +188,113 @@ impl ChildProcess for IpcChannelChild {
         let (from_parent_receiver, first_msg) = server
             .accept()
             .context("parent process server failed to accept bytes from child")?;
-        let init_complete = serde_json::from_slice::<IpcChannelInitComplete>(&first_msg)
+        let init_complete = self
+            .codec
+            .decode::<IpcChannelInitComplete>(&first_msg)
             .context("failed to convert")?;
 
         ensure!(init_complete.parent_id() == parent_id, "parent ID matchees");
         ensure!(init_complete.child_id() == self.id(), "child ID matchees");
 
         // Now that we're initialized, Run forever listening for messages and handling them
-        debug!("starting forever listen loop...");
-        loop {
-            if let Ok(msg_bytes) = from_parent_receiver.recv() {
-                // Handle the ping message
-                let sender_id = match self.rpc_message_complexity {
-                    RpcMessageComplexity::RawString => {
-                        let ping_msg = RawStringPingMessage::from_str(
-                            std::str::from_utf8(&msg_bytes)
-                                .context("failed to convert incoming bytes to str")?,
-                        )?;
-                        ensure!(ping_msg.receiver_id() == self.id(), "invalid receiver ID");
-                        ping_msg.sender_id().to_string()
-                    }
-                    RpcMessageComplexity::Json => {
-                        let ping_msg = serde_json::from_slice::<PingMessage>(&msg_bytes)
-                            .context("failed to parse ping msg in child")?;
-                        ensure!(ping_msg.receiver_id() == self.id(), "invalid receiver ID");
-                        ping_msg.sender_id().to_string()
-                    }
-                };
-
-                // Send pong
-                let pong_bytes: Vec<u8> = match self.rpc_message_complexity {
-                    RpcMessageComplexity::RawString => {
-                        format!("{}|{}|pong", self.id(), sender_id).into()
-                    }
-                    RpcMessageComplexity::Json => {
-                        let pong_msg =
-                            PongMessage::new(self.id(), sender_id, get_system_time_millis()?);
-                        serde_json::to_vec(&pong_msg).context("failed to serialize pong message")?
-                    }
-                };
-
-                sender
-                    .send(pong_bytes)
-                    .context("failed to send pong message")?;
+        debug!(mode = ?self.ipc_channel_mode, "starting forever listen loop...");
+        match self.ipc_channel_mode {
+            IpcChannelMode::Vec => loop {
+                if let Ok(msg_bytes) = from_parent_receiver.recv() {
+                    sender
+                        .send(self.handle_ping_payload(&msg_bytes)?)
+                        .context("failed to send pong message")?;
+                }
+            },
+            IpcChannelMode::Typed => {
+                let (ping_sender, ping_receiver) =
+                    ipc::channel::<PingMessage>().context("failed to create ping channel")?;
+                let (pong_sender, pong_receiver) =
+                    ipc::channel::<PongMessage>().context("failed to create pong channel")?;
+                Self::send_mode_channels(
+                    &from_parent_receiver,
+                    TypedChannels {
+                        ping_sender,
+                        pong_receiver,
+                    },
+                )?;
+                loop {
+                    let Some(ping_msg) = recv_until_disconnected(ping_receiver.recv())? else {
+                        return Ok(());
+                    };
+                    let ping_latency_ns = monotonic_nanos_since(ping_msg.sent_at_ns())?;
+                    ensure!(ping_msg.receiver_id() == self.id(), "invalid receiver ID");
+                    pong_sender
+                        .send(
+                            PongMessage::new(
+                                self.id(),
+                                ping_msg.sender_id().into(),
+                                get_monotonic_time_nanos()?,
+                                ping_latency_ns,
+                            )
+                            .with_payload(ping_msg.payload),
+                        )
+                        .context("failed to send pong message")?;
+                }
+            }
+            IpcChannelMode::Bytes => {
+                let (ping_sender, ping_receiver) =
+                    ipc::bytes_channel().context("failed to create ping channel")?;
+                let (pong_sender, pong_receiver) =
+                    ipc::bytes_channel().context("failed to create pong channel")?;
+                Self::send_mode_channels(
+                    &from_parent_receiver,
+                    BytesChannels {
+                        ping_sender: ping_sender.into(),
+                        pong_receiver,
+                    },
+                )?;
+                loop {
+                    let Some(msg_bytes) = recv_until_disconnected(ping_receiver.recv())? else {
+                        return Ok(());
+                    };
+                    pong_sender
+                        .send(&self.handle_ping_payload(&msg_bytes)?)
+                        .context("failed to send pong message")?;
+                }
+            }
+            IpcChannelMode::BulkInline | IpcChannelMode::BulkShared => {
+                let (ping_sender, ping_receiver) = ipc::channel::<BulkPingMessage>()
+                    .context("failed to create bulk ping channel")?;
+                let (pong_sender, pong_receiver) =
+                    ipc::channel::<PongMessage>().context("failed to create pong channel")?;
+                Self::send_mode_channels(
+                    &from_parent_receiver,
+                    BulkChannels {
+                        ping_sender,
+                        pong_receiver,
+                    },
+                )?;
+                loop {
+                    let Some(BulkPingMessage { ping, payload }) =
+                        recv_until_disconnected(ping_receiver.recv())?
+                    else {
+                        return Ok(());
+                    };
+                    let ping_latency_ns = monotonic_nanos_since(ping.sent_at_ns())?;
+                    ensure!(ping.receiver_id() == self.id(), "invalid receiver ID");
+
+                    // Touch the payload (at both ends), so that it is actually accessed
+                    ensure!(
+                        payload.first() == Some(&BULK_PAYLOAD_FILL_BYTE)
+                            && payload.last() == Some(&BULK_PAYLOAD_FILL_BYTE),
+                        "invalid bulk payload ({payload:?})"
+                    );
+
+                    pong_sender
+                        .send(PongMessage::new(
+                            self.id(),
+                            ping.sender_id().into(),
+                            get_monotonic_time_nanos()?,
+                            ping_latency_ns,
+                        ))
+                        .context("failed to send pong message")?;
+                }
             }
         }
     }
@@ -2,7 +2,174 @@
 Implementation of IPC between parent and child processes via [`ipc-channel`].
 
 `ipc-channel` seems to be a robust IPC mechanism, with support for Linux, Mac, and Windows environments.
+
+Channels can be used in one of a few modes (see [`IpcChannelMode`]), to measure how much of the cost
+of a round-trip is due to serialization:
+
+- `vec` (default), `IpcSender<Vec<u8>>`/`IpcReceiver<Vec<u8>>`, with payloads encoded by us (see [`crate::RpcMessageComplexity`] and [`crate::codec`])
+- `typed`, `IpcSender<PingMessage>`/`IpcReceiver<PongMessage>`, with payloads encoded by `ipc-channel` (`bincode`)
+- `bytes`, `IpcBytesSender`/`IpcBytesReceiver`, with payloads encoded by us, and no serialization done by `ipc-channel`
+- `bulk-inline`, like `typed`, but every ping carries a large payload, which is copied through the socket
+- `bulk-shared`, like `typed`, but every ping carries a large payload in an [`IpcSharedMemory`] region,
+  attached to the (small) control message
 */
 
+use anyhow::{bail, Result};
+use ipc_channel::ipc::{IpcBytesReceiver, IpcReceiver, IpcSender, IpcSharedMemory};
+use serde::{Deserialize, Serialize};
+
+use crate::{IpcBytesSenderWrapper, PingMessage, PongMessage};
+
 pub mod child;
 pub mod parent;
+
+/// ENV variable for setting the `ipc-channel` mode
+const ENV_VAR_IPC_CHANNEL_MODE: &str = "IPC_CHANNEL_MODE";
+
+/// ENV variable for setting the size of payloads sent in bulk modes
+const ENV_VAR_IPC_CHANNEL_BULK_PAYLOAD_SIZE_BYTES: &str = "IPC_CHANNEL_BULK_PAYLOAD_SIZE_BYTES";
+
+/// Default size of payloads sent in bulk modes
+const DEFAULT_IPC_CHANNEL_BULK_PAYLOAD_SIZE_BYTES: usize = 1024 * 1024;
+
+/// Byte that bulk payloads are filled with (checked by the child)
+const BULK_PAYLOAD_FILL_BYTE: u8 = 0xAB;
+
+/// Kind of channels used for ping/pong messages after the initial parent/child handshake
+///
+/// This is normally configured via ENV ("IPC_CHANNEL_MODE"), and parsed
+/// into this structure for easy usage from code.
+#[derive(Debug, Default, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum IpcChannelMode {
+    /// `IpcSender<Vec<u8>>`/`IpcReceiver<Vec<u8>>` with payloads encoded by us
+    #[default]
+    Vec,
+    /// `IpcSender<PingMessage>`/`IpcReceiver<PongMessage>`, with payloads encoded by `ipc-channel`
+    ///
+    /// NOTE: as messages are always structs, [`crate::RpcMessageComplexity`] has no effect in this mode
+    Typed,
+    /// `IpcBytesSender`/`IpcBytesReceiver` with payloads encoded by us
+    Bytes,
+    /// `IpcSender<BulkPingMessage>`/`IpcReceiver<PongMessage>`, with a large payload copied through the socket
+    BulkInline,
+    /// `IpcSender<BulkPingMessage>`/`IpcReceiver<PongMessage>`, with a large payload in shared memory
+    BulkShared,
+}
+
+impl std::str::FromStr for IpcChannelMode {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "vec" => Ok(Self::Vec),
+            "typed" => Ok(Self::Typed),
+            "bytes" => Ok(Self::Bytes),
+            "bulk-inline" => Ok(Self::BulkInline),
+            "bulk-shared" => Ok(Self::BulkShared),
+            _ => bail!("invalid IpcChannelMode value [{s}]"),
+        }
+    }
+}
+
+impl IpcChannelMode {
+    /// Retreive from env or use the default (Vec)
+    pub fn from_env_or_default(values: impl Iterator<Item = (String, String)>) -> Self {
+        for (k, v) in values {
+            if k == ENV_VAR_IPC_CHANNEL_MODE {
+                return <Self as std::str::FromStr>::from_str(&v).unwrap_or(Self::default());
+            }
+        }
+        Self::default()
+    }
+
+    /// Whether this mode sends bulk payloads
+    #[must_use]
+    pub fn is_bulk(&self) -> bool {
+        matches!(self, Self::BulkInline | Self::BulkShared)
+    }
+}
+
+/// Retrieve the size of payloads sent in bulk modes from env, or use the default
+fn bulk_payload_size_bytes_from_env_or_default(
+    mut values: impl Iterator<Item = (String, String)>,
+) -> usize {
+    values
+        .find(|(k, _)| k == ENV_VAR_IPC_CHANNEL_BULK_PAYLOAD_SIZE_BYTES)
+        .and_then(|(_, v)| v.parse().ok())
+        .unwrap_or(DEFAULT_IPC_CHANNEL_BULK_PAYLOAD_SIZE_BYTES)
+}
+
+/// Message sent by the parent (as JSON, over the handshake channel) to start setup of
+/// mode-specific channels, for modes other than [`IpcChannelMode::Vec`]
+#[derive(Debug, Serialize, Deserialize)]
+struct IpcChannelModeSetup {
+    /// Name of the [`ipc_channel::ipc::IpcOneShotServer`] the child should send its channels to
+    ipc_server_name: String,
+}
+
+/// Channels created by the child and sent to the parent for [`IpcChannelMode::Typed`]
+#[derive(Debug, Serialize, Deserialize)]
+struct TypedChannels {
+    /// Sender the parent uses to send pings
+    ping_sender: IpcSender<PingMessage>,
+    /// Receiver the parent uses to receive pongs
+    pong_receiver: IpcReceiver<PongMessage>,
+}
+
+/// Channels created by the child and sent to the parent for [`IpcChannelMode::Bytes`]
+#[derive(Debug, Serialize, Deserialize)]
+struct BytesChannels {
+    /// Sender the parent uses to send pings
+    ping_sender: IpcBytesSenderWrapper,
+    /// Receiver the parent uses to receive pongs
+    pong_receiver: IpcBytesReceiver,
+}
+
+/// Channels created by the child and sent to the parent for bulk modes
+/// ([`IpcChannelMode::BulkInline`], [`IpcChannelMode::BulkShared`])
+#[derive(Debug, Serialize, Deserialize)]
+struct BulkChannels {
+    /// Sender the parent uses to send pings (with payloads)
+    ping_sender: IpcSender<BulkPingMessage>,
+    /// Receiver the parent uses to receive pongs
+    pong_receiver: IpcReceiver<PongMessage>,
+}
+
+/// A ping that carries a (large) payload
+#[derive(Debug, Serialize, Deserialize)]
+struct BulkPingMessage {
+    /// Control message
+    ping: PingMessage,
+    /// Payload that accompanies the ping
+    payload: BulkPayload,
+}
+
+/// Payload of a [`BulkPingMessage`]
+#[derive(Serialize, Deserialize)]
+enum BulkPayload {
+    /// Bytes that are serialized into (and copied through) the channel
+    Inline(Vec<u8>),
+    /// Bytes in a shared memory region, of which only a handle goes through the channel
+    Shared(IpcSharedMemory),
+}
+
+impl std::fmt::Debug for BulkPayload {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Inline(bytes) => f.debug_tuple("Inline").field(&bytes.len()).finish(),
+            Self::Shared(shmem) => f.debug_tuple("Shared").field(&shmem.len()).finish(),
+        }
+    }
+}
+
+impl std::ops::Deref for BulkPayload {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        match self {
+            Self::Inline(bytes) => bytes,
+            Self::Shared(shmem) => shmem,
+        }
+    }
+}
@@ -6,18 +6,41 @@ use std::process::{Command, Stdio};
 use std::str::FromStr;
 
 use anyhow::{ensure, Context as _, Result};
-use ipc_channel::ipc::{IpcOneShotServer, IpcReceiver, IpcSender};
+use ipc_channel::ipc::{
+    IpcBytesReceiver, IpcBytesSender, IpcOneShotServer, IpcReceiver, IpcSender, IpcSharedMemory,
+};
+use serde::{de::DeserializeOwned, Serialize};
 use tracing::debug;
 use uuid::{NoContext, Timestamp, Uuid};
 
+use crate::codec::{Codec as _, RpcCodec};
+use crate::ipcc::{
+    bulk_payload_size_bytes_from_env_or_default, BulkChannels, BulkPayload, BulkPingMessage,
+    BytesChannels, IpcChannelMode, IpcChannelModeSetup, TypedChannels, BULK_PAYLOAD_FILL_BYTE,
+};
+use crate::latency::OneWayLatencies;
 use crate::{
-    get_system_time_millis, ChildId, ChildName, IpcChannelChildInit, IpcChannelChildInitResponse,
-    IpcChannelInitComplete, ParentProcess, PingMessage, Pinger, PongMessage, RawStringPongMessage,
-    RpcMessageComplexity, RpcPong,
+    get_monotonic_time_nanos, payload_size_bytes_from_env_or_default, synthetic_payload, ChildId,
+    ChildName, IpcChannelChildInit, IpcChannelChildInitResponse, IpcChannelInitComplete,
+    ParentProcess, PingMessage, Pinger, PongMessage, RawStringPongMessage, RpcMessageComplexity,
+    RpcPong,
 };
 
+/// IPC sender/receiver pair (i.e. a usable channel) for a single child, depending on [`IpcChannelMode`]
+#[derive(Debug)]
+enum IpcChannelPair {
+    /// Channels that carry payloads encoded by us (see [`IpcChannelMode::Vec`])
+    Vec(IpcSender<Vec<u8>>, IpcReceiver<Vec<u8>>),
+    /// Channels that carry messages encoded by `ipc-channel` (see [`IpcChannelMode::Typed`])
+    Typed(IpcSender<PingMessage>, IpcReceiver<PongMessage>),
+    /// Channels that carry raw bytes (see [`IpcChannelMode::Bytes`])
+    Bytes(IpcBytesSender, IpcBytesReceiver),
+    /// Channels that carry pings with large payloads (see [`IpcChannelMode::BulkInline`], [`IpcChannelMode::BulkShared`])
+    Bulk(IpcSender<BulkPingMessage>, IpcReceiver<PongMessage>),
+}
+
 /// Map of child process IDs to IPC senders/receivers (i.e. a usable channel)
-type ChildChannelMap = HashMap<ChildId, (IpcSender<Vec<u8>>, IpcReceiver<Vec<u8>>)>;
+type ChildChannelMap = HashMap<ChildId, IpcChannelPair>;
 
 /// Contains the implementation of the [`ParentProcess`] trait over IPC (via `ipc-channel`)
 ///
@@ -38,18 +61,166 @@ pub struct IpcChannelParent {
 
     /// Complexity of RPC messages to send
     rpc_message_complexity: RpcMessageComplexity,
+
+    /// Codec used to encode JSON-complexity messages in the `vec` and `bytes` modes
+    codec: RpcCodec,
+
+    /// Kind of channels to use for ping/pong messages
+    ipc_channel_mode: IpcChannelMode,
+
+    /// Payload sent with every ping in bulk modes (empty otherwise)
+    bulk_payload: Vec<u8>,
+
+    /// Synthetic payload carried by every ping in non-bulk modes (see `RPC_PAYLOAD_SIZE_BYTES`)
+    ///
+    /// NOTE: this is empty in bulk modes (which send [`Self::bulk_payload`] instead), and
+    /// is not sent when using [`RpcMessageComplexity::RawString`]
+    payload: Vec<u8>,
 }
 
 impl IpcChannelParent {
     /// Create a new [`IpcChannelParent`]
     pub fn new() -> Self {
+        let ipc_channel_mode = IpcChannelMode::from_env_or_default(std::env::vars());
+        let (bulk_payload, payload) = if ipc_channel_mode.is_bulk() {
+            (
+                vec![
+                    BULK_PAYLOAD_FILL_BYTE;
+                    bulk_payload_size_bytes_from_env_or_default(std::env::vars())
+                ],
+                Vec::new(),
+            )
+        } else {
+            (
+                Vec::new(),
+                synthetic_payload(payload_size_bytes_from_env_or_default(std::env::vars())),
+            )
+        };
         Self {
             uuid: Uuid::new_v7(Timestamp::now(NoContext)),
             children_names: HashMap::new(),
             children: HashMap::new(),
             rpc_message_complexity: RpcMessageComplexity::from_env_or_default(std::env::vars()),
+            codec: RpcCodec::from_env_or_default(std::env::vars()),
+            ipc_channel_mode,
+            bulk_payload,
+            payload,
         }
     }
+
+    /// Retrieve the size of the payload sent with every ping, if a bulk mode is in use
+    #[must_use]
+    pub fn bulk_payload_size_bytes(&self) -> Option<usize> {
+        self.ipc_channel_mode
+            .is_bulk()
+            .then_some(self.bulk_payload.len())
+    }
+
+    /// Retrieve the size of the synthetic payload carried by every ping, if one is sent
+    ///
+    /// Pings carry no synthetic payload in bulk modes, or when raw strings are sent.
+    #[must_use]
+    pub fn payload_size_bytes(&self) -> Option<usize> {
+        let carries_payload = matches!(self.ipc_channel_mode, IpcChannelMode::Typed)
+            || matches!(self.rpc_message_complexity, RpcMessageComplexity::Json);
+        (carries_payload && !self.payload.is_empty()).then_some(self.payload.len())
+    }
+
+    /// Receive mode-specific channels from the child, over a new [`IpcOneShotServer`]
+    ///
+    /// The name of the server is sent to the child over the (already established) handshake channel.
+    fn receive_mode_channels<T: Serialize + DeserializeOwned>(
+        sender: &IpcSender<Vec<u8>>,
+    ) -> Result<T> {
+        let (server, server_name) =
+            IpcOneShotServer::<T>::new().context("failed to build IPC server for mode setup")?;
+        sender
+            .send(
+                serde_json::to_vec(&IpcChannelModeSetup {
+                    ipc_server_name: server_name,
+                })
+                .context("failed to serialize mode setup message")?,
+            )
+            .context("failed to send mode setup to child from parent")?;
+        let (_, channels) = server
+            .accept()
+            .context("parent process server failed to accept mode channels from child")?;
+        Ok(channels)
+    }
+
+    /// Build the payload for a ping, depending on message complexity
+    fn build_ping_payload(&self, child_id: &str) -> Result<Vec<u8>> {
+        Ok(match self.rpc_message_complexity {
+            RpcMessageComplexity::RawString => format!("{}|{}|ping", self.id(), child_id).into(),
+            RpcMessageComplexity::Json => self
+                .codec
+                .encode(
+                    &PingMessage::new(self.id(), child_id.into(), get_monotonic_time_nanos()?)
+                        .with_payload(self.payload.clone()),
+                )
+                .context("failed to serialize ping")?,
+        })
+    }
+
+    /// Check the bytes of a pong (this is essentially "processing"), depending on message complexity,
+    /// returning the one-way latencies of the round-trip (if the pong carries timestamps)
+    fn check_pong_payload(
+        &self,
+        child_id: &str,
+        pong_bytes: &[u8],
+    ) -> Result<Option<OneWayLatencies>> {
+        match self.rpc_message_complexity {
+            // If we were dealing with raw strings, then we can just check
+            RpcMessageComplexity::RawString => {
+                let pong_msg = RawStringPongMessage::from_str(
+                    std::str::from_utf8(pong_bytes)
+                        .context("failed to parse pong message from pong bytes")?,
+                )?;
+                ensure!(
+                    pong_msg.receiver_id() == self.id(),
+                    "receiver_id is parent process"
+                );
+                ensure!(
+                    pong_msg.sender_id() == child_id,
+                    "sender_id is child process"
+                );
+                pong_msg.one_way_latencies()
+            }
+            RpcMessageComplexity::Json => {
+                let pong_msg = self
+                    .codec
+                    .decode::<PongMessage>(pong_bytes)
+                    .context("failed to decode pong message")?;
+                self.check_pong(child_id, &pong_msg)
+            }
+        }
+    }
+
+    /// Check that a pong was sent by the given child, to this parent,
+    /// returning the one-way latencies of the round-trip
+    fn check_pong(
+        &self,
+        child_id: &str,
+        pong_msg: &PongMessage,
+    ) -> Result<Option<OneWayLatencies>> {
+        let latencies = pong_msg.one_way_latencies()?;
+        let pong_sender_id = pong_msg.sender_id();
+        let pong_receiver_id = pong_msg.receiver_id();
+        let parent_id = self.id();
+        ensure!(
+            pong_sender_id == child_id,
+            "pong message sender_id [{pong_sender_id}] does not match child id [{child_id}]",
+        );
+        ensure!(
+            pong_receiver_id == parent_id,
+            "pong receiver_id [{pong_receiver_id}] should be parent ID [{parent_id}]"
+        );
+        ensure!(
+            pong_msg.payload() == self.payload.as_slice(),
+            "pong payload should match ping payload"
+        );
+        Ok(latencies)
+    }
 }
 
 impl ParentProcess for IpcChannelParent {
@@ -97,7 +268,9 @@ impl ParentProcess for IpcChannelParent {
             .context("parent process server failed to accept bytes from child")?;
 
         debug!("receiving init response from child...");
-        let init_resp = serde_json::from_slice::<IpcChannelChildInitResponse>(&first_msg)
+        let init_resp = self
+            .codec
+            .decode::<IpcChannelChildInitResponse>(&first_msg)
             .context("failed to InitResponse from first child message")?;
 
         // Connect to the IPC channel created by the child, and send the init complete message
@@ -107,20 +280,47 @@ impl ParentProcess for IpcChannelParent {
 
         sender
             .send(
-                serde_json::to_vec(&IpcChannelInitComplete::new(
-                    &self.id(),
-                    &init_resp.child_id,
-                ))
-                .context("failed to serialize init complete message")?,
+                self.codec
+                    .encode(&IpcChannelInitComplete::new(
+                        &self.id(),
+                        &init_resp.child_id,
+                    ))
+                    .context("failed to serialize init complete message")?,
             )
             .context("failed to send init complete to child from parent")?;
 
+        // Set up the channels that will be used for ping/pong messages, depending on mode
+        debug!(mode = ?self.ipc_channel_mode, "setting up channels for mode...");
+        let channels = match self.ipc_channel_mode {
+            IpcChannelMode::Vec => IpcChannelPair::Vec(sender, from_child_receiver),
+            IpcChannelMode::Typed => {
+                let TypedChannels {
+                    ping_sender,
+                    pong_receiver,
+                } = Self::receive_mode_channels(&sender)?;
+                IpcChannelPair::Typed(ping_sender, pong_receiver)
+            }
+            IpcChannelMode::Bytes => {
+                let BytesChannels {
+                    ping_sender,
+                    pong_receiver,
+                } = Self::receive_mode_channels(&sender)?;
+                IpcChannelPair::Bytes(ping_sender.0, pong_receiver)
+            }
+            IpcChannelMode::BulkInline | IpcChannelMode::BulkShared => {
+                let BulkChannels {
+                    ping_sender,
+                    pong_receiver,
+                } = Self::receive_mode_channels(&sender)?;
+                IpcChannelPair::Bulk(ping_sender, pong_receiver)
+            }
+        };
+
         // Save all the information for this duplex connection
         let child_id = init_resp.child_id();
         self.children_names
             .insert(name.as_ref().into(), child_id.into());
-        self.children
-            .insert(child_id.into(), (sender, from_child_receiver));
+        self.children.insert(child_id.into(), channels);
         debug!("successfully set spawned & saved child");
 
         Ok(child)
@@ -128,7 +328,7 @@ impl ParentProcess for IpcChannelParent {
 }
 
 impl Pinger for IpcChannelParent {
-    fn roundtrip_ping(&self, name: impl AsRef<str>) -> Result<()> {
+    fn roundtrip_ping(&self, name: impl AsRef<str>) -> Result<Option<OneWayLatencies>> {
         let name = name.as_ref();
 
         // Retrieve the child
@@ -136,65 +336,68 @@ impl Pinger for IpcChannelParent {
             .children_names
             .get(name)
             .with_context(|| format!("failed to find child with name [{name}]"))?;
-        let (sender, receiver) = self
+        let channels = self
             .children
             .get(child_id)
             .with_context(|| format!("failed to find sender for child w/ id [{child_id}]"))?;
 
-        // Build payload, depending on message complexity
-        let payload: Vec<u8> = match self.rpc_message_complexity {
-            RpcMessageComplexity::RawString => format!("{}|{}|ping", self.id(), child_id).into(),
-            RpcMessageComplexity::Json => serde_json::to_vec(&PingMessage::new(
-                self.id(),
-                child_id.into(),
-                get_system_time_millis()?,
-            ))
-            .context("failed to serialize ping")?,
-        };
+        match channels {
+            IpcChannelPair::Vec(sender, receiver) => {
+                // Send ping payload
+                sender
+                    .send(self.build_ping_payload(child_id)?)
+                    .context("failed to send ping from parent")?;
 
-        // Send ping payload
-        sender
-            .send(payload)
-            .context("failed to send ping from parent")?;
-
-        // Receive pong bytes
-        let pong_bytes = receiver.recv().context("failed to receive ping")?;
+                // Receive & check pong bytes
+                let pong_bytes = receiver.recv().context("failed to receive ping")?;
+                self.check_pong_payload(child_id, &pong_bytes)
+            }
+            IpcChannelPair::Typed(sender, receiver) => {
+                // Send ping (serialized by ipc-channel)
+                sender
+                    .send(
+                        PingMessage::new(self.id(), child_id.into(), get_monotonic_time_nanos()?)
+                            .with_payload(self.payload.clone()),
+                    )
+                    .context("failed to send ping from parent")?;
 
-        // Check the returned bytes (this is essentially "processing")
-        // depending on complexity required
-        match self.rpc_message_complexity {
-            // If we were dealing with raw strings, then we can just check
-            RpcMessageComplexity::RawString => {
-                let pong_msg = RawStringPongMessage::from_str(
-                    std::str::from_utf8(&pong_bytes)
-                        .context("failed to parse pong message from pong bytes")?,
-                )?;
-                ensure!(
-                    pong_msg.receiver_id() == self.id(),
-                    "receiver_id is parent process"
-                );
-                ensure!(
-                    pong_msg.sender_id() == child_id,
-                    "sender_id is child process"
-                );
+                // Receive & check pong (deserialized by ipc-channel)
+                let pong_msg = receiver.recv().context("failed to receive pong")?;
+                self.check_pong(child_id, &pong_msg)
             }
-            RpcMessageComplexity::Json => {
-                let pong_msg = serde_json::from_slice::<PongMessage>(&pong_bytes)
-                    .context("failed to decode pong message")?;
-                let pong_sender_id = pong_msg.sender_id();
-                let pong_receiver_id = pong_msg.receiver_id();
-                let parent_id = self.id();
-                ensure!(
-                    pong_sender_id == child_id,
-                    "pong message sender_id [{pong_sender_id}] does not match child id [{child_id}]",
-                );
-                ensure!(
-                    pong_receiver_id == parent_id,
-                    "pong receiver_id [{pong_receiver_id}] should be parent ID [{parent_id}]"
-                );
+            IpcChannelPair::Bytes(sender, receiver) => {
+                // Send ping payload
+                sender
+                    .send(&self.build_ping_payload(child_id)?)
+                    .context("failed to send ping from parent")?;
+
+                // Receive & check pong bytes
+                let pong_bytes = receiver.recv().context("failed to receive pong")?;
+                self.check_pong_payload(child_id, &pong_bytes)
             }
-        };
+            IpcChannelPair::Bulk(sender, receiver) => {
+                // Copy the payload into a fresh buffer (as a producer would), which is either
+                // serialized into the message or placed in a new shared memory region
+                let payload = match self.ipc_channel_mode {
+                    IpcChannelMode::BulkShared => {
+                        BulkPayload::Shared(IpcSharedMemory::from_bytes(&self.bulk_payload))
+                    }
+                    _ => BulkPayload::Inline(self.bulk_payload.clone()),
+                };
+                sender
+                    .send(BulkPingMessage {
+                        ping: PingMessage::new(
+                            self.id(),
+                            child_id.into(),
+                            get_monotonic_time_nanos()?,
+                        ),
+                        payload,
+                    })
+                    .context("failed to send bulk ping from parent")?;
 
-        Ok(())
+                let pong_msg = receiver.recv().context("failed to receive pong")?;
+                self.check_pong(child_id, &pong_msg)
+            }
+        }
     }
 }
@@ -0,0 +1,289 @@
+/*!
+Latency of individual round-trips, recorded into an [HDR histogram][crate-hdrhistogram].
+
+Every parent binary times each call to [`crate::Pinger::roundtrip_ping`], and reports tail latency
+(p50/p90/p99/p99.9/max) alongside throughput, since averages alone hide the stalls that matter most.
+
+Pings & pongs also carry timestamps from the monotonic clock (see [`crate::get_monotonic_time_nanos`]),
+which is shared by processes on the same host, so each direction of a round-trip is reported separately
+as well (see [`OneWayLatencies`]): children measure how long pings took to reach them, and send that back
+in their pongs, while parents measure how long pongs took to reach them.
+
+In open-loop mode (see [`crate::open_loop`]), round-trips are timed from when their ping was scheduled to be sent,
+rather than from when it was actually sent, so that queueing delay is counted.
+
+The full distribution can be written out in the `.hgrm` text format used by [HdrHistogram][hdrhistogram]
+(which can be plotted with its [plotter][hdrhistogram-plotter]), by setting `LATENCY_HISTOGRAM_PATH`.
+All values in reports & dumps are in microseconds.
+
+[crate-hdrhistogram]: <https://crates.io/crates/hdrhistogram>
+[hdrhistogram]: <https://hdrhistogram.github.io/HdrHistogram/>
+[hdrhistogram-plotter]: <https://hdrhistogram.github.io/HdrHistogram/plotFiles.html>
+**/
+
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+
+use anyhow::{anyhow, Context as _, Result};
+use hdrhistogram::Histogram;
+use tracing::info;
+
+use crate::monotonic_nanos_since;
+use crate::open_loop::OpenLoopSchedule;
+use crate::trials::{format_latency_line, ROUNDTRIP_LATENCY_NAME};
+
+/// ENV variable for setting the path the full latency histogram is written to
+const ENV_VAR_LATENCY_HISTOGRAM_PATH: &str = "LATENCY_HISTOGRAM_PATH";
+
+/// Number of significant digits kept for every recorded latency
+const HISTOGRAM_SIGNIFICANT_DIGITS: u8 = 3;
+
+/// Number of steps per halving of the remaining distribution, when writing out the full histogram
+const HISTOGRAM_TICKS_PER_HALF_DISTANCE: u32 = 5;
+
+/// One-way latencies of a single round-trip
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct OneWayLatencies {
+    /// Time taken by the ping to reach the child (as measured by the child)
+    pub to_child: Duration,
+
+    /// Time taken by the pong to reach the parent (as measured by the parent)
+    pub from_child: Duration,
+}
+
+impl OneWayLatencies {
+    /// Measure the one-way latencies of a round-trip, upon receiving its pong
+    ///
+    /// # Arguments
+    ///
+    /// * `ping_latency_ns` - latency of the ping, as reported by the child in its pong
+    /// * `pong_sent_at_ns` - time at which the child sent the pong (see [`crate::get_monotonic_time_nanos`])
+    ///
+    pub fn measure(ping_latency_ns: u64, pong_sent_at_ns: u64) -> Result<Self> {
+        Ok(Self {
+            to_child: Duration::from_nanos(ping_latency_ns),
+            from_child: Duration::from_nanos(monotonic_nanos_since(pong_sent_at_ns)?),
+        })
+    }
+}
+
+/// Latencies of round-trips (and of each of their directions), in nanoseconds
+#[derive(Debug, Clone)]
+pub struct RoundtripLatencies {
+    /// Histogram of round-trip latencies (in nanoseconds)
+    histogram: Histogram<u64>,
+
+    /// Histogram of parent to child (ping) latencies (in nanoseconds)
+    to_child: Histogram<u64>,
+
+    /// Histogram of child to parent (pong) latencies (in nanoseconds)
+    from_child: Histogram<u64>,
+
+    /// Schedule that round-trips follow in open-loop mode (round-trips are performed back-to-back otherwise)
+    schedule: Option<OpenLoopSchedule>,
+}
+
+impl RoundtripLatencies {
+    /// Create an empty set of [`RoundtripLatencies`]
+    pub fn new() -> Result<Self> {
+        Ok(Self {
+            histogram: new_histogram()?,
+            to_child: new_histogram()?,
+            from_child: new_histogram()?,
+            schedule: None,
+        })
+    }
+
+    /// Create an empty set of [`RoundtripLatencies`], following an open-loop schedule if one is configured in env
+    /// (see [`OpenLoopSchedule::from_env`])
+    pub fn from_env(values: impl Iterator<Item = (String, String)>) -> Result<Self> {
+        Ok(Self {
+            schedule: OpenLoopSchedule::from_env(values)?,
+            ..Self::new()?
+        })
+    }
+
+    /// Record the latency of a single round-trip
+    pub fn record(&mut self, latency: Duration) -> Result<()> {
+        record_into(&mut self.histogram, latency)
+    }
+
+    /// Record the one-way latencies of a single round-trip
+    pub fn record_one_way(&mut self, latencies: OneWayLatencies) -> Result<()> {
+        record_into(&mut self.to_child, latencies.to_child)?;
+        record_into(&mut self.from_child, latencies.from_child)
+    }
+
+    /// Perform (and time) a single round-trip, recording its latencies if it succeeds
+    ///
+    /// In open-loop mode, this first waits for the round-trip's scheduled time, which it is timed from.
+    pub fn time(
+        &mut self,
+        roundtrip: impl FnOnce() -> Result<Option<OneWayLatencies>>,
+    ) -> Result<()> {
+        let start = match self.schedule.as_mut() {
+            Some(schedule) => schedule.wait_for_next(),
+            None => Instant::now(),
+        };
+        let one_way = roundtrip()?;
+        let end = Instant::now();
+        self.record(end.duration_since(start))?;
+        if let Some(one_way) = one_way {
+            self.record_one_way(one_way)?;
+        }
+        if let Some(schedule) = self.schedule.as_mut() {
+            schedule.complete(end);
+        }
+        Ok(())
+    }
+
+    /// Number of round-trips recorded
+    #[must_use]
+    pub fn len(&self) -> u64 {
+        self.histogram.len()
+    }
+
+    /// Whether no round-trips have been recorded
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.histogram.is_empty()
+    }
+
+    /// Latency (in microseconds) at a given percentile (ex. `99.9`)
+    #[must_use]
+    pub fn percentile_micros(&self, percentile: f64) -> f64 {
+        micros(self.histogram.value_at_percentile(percentile))
+    }
+
+    /// Max latency (in microseconds)
+    #[must_use]
+    pub fn max_micros(&self) -> f64 {
+        micros(self.histogram.max())
+    }
+
+    /// Report tail latencies (via tracing, and on STDERR like the rest of the results)
+    ///
+    /// One-way latencies are only reported if any were recorded (i.e. if the transport's messages carry timestamps),
+    /// and the target & achieved rates in open-loop mode.
+    pub fn report(&self) -> Result<()> {
+        report_histogram(ROUNDTRIP_LATENCY_NAME, &self.histogram);
+        if !self.to_child.is_empty() {
+            report_histogram("parent->child", &self.to_child);
+            report_histogram("child->parent", &self.from_child);
+        }
+        if let Some(schedule) = &self.schedule {
+            schedule.report()?;
+        }
+        Ok(())
+    }
+
+    /// Write the full latency distribution (in microseconds) in the `.hgrm` text format
+    pub fn write_distribution(&self, mut w: impl Write) -> Result<()> {
+        writeln!(
+            w,
+            "{:>12} {:>14} {:>10} {:>14}\n",
+            "Value", "Percentile", "TotalCount", "1/(1-Percentile)"
+        )?;
+        let mut total_count = 0;
+        for v in self
+            .histogram
+            .iter_quantiles(HISTOGRAM_TICKS_PER_HALF_DISTANCE)
+        {
+            total_count += v.count_since_last_iteration();
+            let value_us = micros(v.value_iterated_to());
+            let quantile = v.quantile_iterated_to();
+            if quantile < 1.0 {
+                writeln!(
+                    w,
+                    "{value_us:12.3} {quantile:14.12} {total_count:10} {:14.2}",
+                    1.0 / (1.0 - quantile)
+                )?;
+            } else {
+                writeln!(w, "{value_us:12.3} {quantile:14.12} {total_count:10}")?;
+            }
+        }
+        writeln!(
+            w,
+            "#[Mean    = {:12.3}, StdDeviation   = {:12.3}]",
+            self.histogram.mean() / 1000.0,
+            self.histogram.stdev() / 1000.0
+        )?;
+        writeln!(
+            w,
+            "#[Max     = {:12.3}, Total count    = {:12}]",
+            self.max_micros(),
+            self.len()
+        )?;
+        Ok(())
+    }
+
+    /// Write the full latency distribution to the path in `LATENCY_HISTOGRAM_PATH`, if present in env
+    pub fn write_distribution_from_env(
+        &self,
+        mut values: impl Iterator<Item = (String, String)>,
+    ) -> Result<()> {
+        let Some(path) = values
+            .find(|(k, _)| k == ENV_VAR_LATENCY_HISTOGRAM_PATH)
+            .map(|(_, v)| PathBuf::from(v))
+        else {
+            return Ok(());
+        };
+
+        let file = File::create(&path).with_context(|| {
+            format!(
+                "failed to create latency histogram file [{}]",
+                path.display()
+            )
+        })?;
+        let mut w = BufWriter::new(file);
+        self.write_distribution(&mut w)
+            .and_then(|()| w.flush().map_err(Into::into))
+            .with_context(|| format!("failed to write latency histogram [{}]", path.display()))?;
+        info!(path = %path.display(), "wrote latency histogram");
+        eprintln!("wrote latency histogram to [{}]", path.display());
+        Ok(())
+    }
+}
+
+/// Create an empty histogram of latencies (in nanoseconds)
+fn new_histogram() -> Result<Histogram<u64>> {
+    Histogram::new(HISTOGRAM_SIGNIFICANT_DIGITS)
+        .map_err(|e| anyhow!("failed to create latency histogram: {e}"))
+}
+
+/// Record a latency into a histogram (in nanoseconds)
+fn record_into(histogram: &mut Histogram<u64>, latency: Duration) -> Result<()> {
+    histogram
+        .record(u64::try_from(latency.as_nanos()).unwrap_or(u64::MAX))
+        .map_err(|e| anyhow!("failed to record latency [{latency:?}]: {e}"))
+}
+
+/// Report the tail latencies in a histogram, under a given name (ex. `round-trip`)
+fn report_histogram(name: &str, histogram: &Histogram<u64>) {
+    let p50_us = micros(histogram.value_at_percentile(50.0));
+    let p90_us = micros(histogram.value_at_percentile(90.0));
+    let p99_us = micros(histogram.value_at_percentile(99.0));
+    let p999_us = micros(histogram.value_at_percentile(99.9));
+    let max_us = micros(histogram.max());
+    info!(
+        name,
+        p50_us,
+        p90_us,
+        p99_us,
+        p999_us,
+        max_us,
+        count = histogram.len(),
+        "computed latencies"
+    );
+    eprintln!(
+        "{}",
+        format_latency_line(name, p50_us, p90_us, p99_us, p999_us, max_us)
+    );
+}
+
+/// Convert a latency in nanoseconds to microseconds
+fn micros(nanos: u64) -> f64 {
+    Duration::from_nanos(nanos).as_secs_f64() * 1_000_000.0
+}
@@ -20,14 +20,52 @@
     rustdoc::redundant_explicit_links
 )]
 
-use std::{process::Command, time::SystemTime};
+use std::{process::Command, time::Duration};
 
 use anyhow::{bail, ensure, Context as _, Result};
+use conv::ValueFrom as _;
 use ipc_channel::ipc::IpcBytesSender;
+use nix::time::{clock_gettime, ClockId};
 use serde::{Deserialize, Serialize};
-
+use tracing::info;
+
+use crate::latency::OneWayLatencies;
+
+pub mod baseline;
+#[cfg(unix)]
+pub mod capnproto;
+pub mod codec;
+pub mod cpu;
+#[cfg(unix)]
+pub mod fifo;
+#[cfg(unix)]
+pub mod grpc;
 pub mod ipcc;
+pub mod latency;
+#[cfg(target_os = "linux")]
+pub mod mqueue;
+pub mod open_loop;
+#[cfg(unix)]
+pub mod pipe;
+pub mod proto;
 pub mod shmem;
+#[cfg(target_os = "linux")]
+pub mod sysv;
+#[cfg(unix)]
+pub mod tcp;
+pub mod trials;
+#[cfg(unix)]
+pub mod udp;
+#[cfg(unix)]
+pub mod uds;
+#[cfg(target_os = "linux")]
+pub mod uring;
+pub mod warmup;
+
+#[cfg(unix)]
+mod sockopt;
+#[cfg(unix)]
+mod stream;
 
 pub use raw_sync::*;
 pub use shared_memory::*;
@@ -35,6 +73,9 @@ pub use shared_memory::*;
 /// ENV variable for setting RPC message complexity
 const ENV_VAR_RPC_MESSAGE_COMPLEXITY: &str = "RPC_MESSAGE_COMPLEXITY";
 
+/// ENV variable for setting the size of the payload carried by every ping (and echoed back in every pong)
+const ENV_VAR_RPC_PAYLOAD_SIZE_BYTES: &str = "RPC_PAYLOAD_SIZE_BYTES";
+
 /// Human-friendly name of a child process
 type ChildName = String;
 
@@ -109,13 +150,22 @@ pub trait ParentProcess {
 }
 
 /// Enables ping-pong interaction between parent and child
-pub trait Pinger: ParentProcess {
+///
+/// Parents are usually also [`ParentProcess`]es, except for in-process baselines (see [`crate::baseline`]),
+/// whose children are threads.
+pub trait Pinger {
     /// Invoke a 'ping' from the parent, and receive a 'pong' from the child
     ///
     /// The actual details of what a ping/pong consist of depend on parent/child implementations,
     /// but usually means sending (i.e. serializing and transferring) a 'ping' message, and
     /// doing the same for a 'pong' message.
-    fn roundtrip_ping(&self, child_process_name: impl AsRef<str>) -> Result<()>;
+    ///
+    /// Returns the one-way latencies of the round-trip, when the ping & pong carry timestamps
+    /// (see [`PingMessage::sent_at_ns`]).
+    fn roundtrip_ping(
+        &self,
+        child_process_name: impl AsRef<str>,
+    ) -> Result<Option<OneWayLatencies>>;
 }
 
 /// Message sent in a ping
@@ -123,7 +173,7 @@ pub trait Pinger: ParentProcess {
 /// The fields in this message aren't important but in serialization/deserialization do
 /// offer some trivial work for parents and clients to perform, which is more in line with real
 /// use cases.
-#[derive(Debug, Serialize, Deserialize, Clone)]
+#[derive(Debug, Serialize, Deserialize, Clone, rkyv::Archive, rkyv::Serialize)]
 #[non_exhaustive]
 pub struct PingMessage {
     /// Sender of the ping message
@@ -132,20 +182,31 @@ pub struct PingMessage {
     receiver_id: String,
     /// When the message was sent
     ///
-    /// Time elapsed since the unix epoch in milliseconds
-    sent_at_ms: u128,
+    /// Time of the monotonic clock in nanoseconds (see [`get_monotonic_time_nanos`])
+    sent_at_ns: u64,
+    /// Synthetic binary payload (see [`synthetic_payload`]), empty by default
+    #[serde(with = "serde_bytes")]
+    payload: Vec<u8>,
 }
 
 impl PingMessage {
-    /// Create a new [`PingMessage`]
-    pub fn new(sender_id: String, receiver_id: String, sent_at_ms: u128) -> Self {
+    /// Create a new [`PingMessage`] (with an empty payload)
+    pub fn new(sender_id: String, receiver_id: String, sent_at_ns: u64) -> Self {
         Self {
             sender_id,
             receiver_id,
-            sent_at_ms,
+            sent_at_ns,
+            payload: Vec::new(),
         }
     }
 
+    /// Set the payload of the message
+    #[must_use]
+    pub fn with_payload(mut self, payload: Vec<u8>) -> Self {
+        self.payload = payload;
+        self
+    }
+
     /// Retrieve the sender (ID) of the message
     #[must_use]
     pub fn sender_id(&self) -> &str {
@@ -158,10 +219,16 @@ impl PingMessage {
         &self.receiver_id
     }
 
-    /// Retrieve when the message was sent
+    /// Retrieve when the message was sent (see [`get_monotonic_time_nanos`])
     #[must_use]
-    pub fn sent_at_ms(&self) -> u128 {
-        self.sent_at_ms
+    pub fn sent_at_ns(&self) -> u64 {
+        self.sent_at_ns
+    }
+
+    /// Retrieve the payload of the message
+    #[must_use]
+    pub fn payload(&self) -> &[u8] {
+        &self.payload
     }
 }
 
@@ -170,7 +237,7 @@ impl PingMessage {
 /// The fields in this message aren't important but in serialization/deserialization do
 /// offer some trivial work for parents and clients to perform, which is more in line with real
 /// use cases.
-#[derive(Debug, Serialize, Deserialize, Clone)]
+#[derive(Debug, Serialize, Deserialize, Clone, rkyv::Archive, rkyv::Serialize)]
 #[non_exhaustive]
 pub struct PongMessage {
     /// Sender of the pong message
@@ -179,24 +246,56 @@ pub struct PongMessage {
     receiver_id: String,
     /// When the message was sent
     ///
-    /// Time elapsed since the UNIX epoch in milliseconds
-    sent_at_ms: u128,
+    /// Time of the monotonic clock in nanoseconds (see [`get_monotonic_time_nanos`])
+    sent_at_ns: u64,
+    /// One-way latency of the ping this pong responds to (parent to child) in nanoseconds,
+    /// as measured by the child when it received the ping
+    ping_latency_ns: u64,
+    /// Payload of the ping this pong responds to, echoed back by the child
+    #[serde(with = "serde_bytes")]
+    payload: Vec<u8>,
 }
 
 impl PongMessage {
-    /// Create a new [`PongMessage`]
-    pub fn new(sender_id: String, receiver_id: String, sent_at_ms: u128) -> Self {
+    /// Create a new [`PongMessage`] (with an empty payload)
+    pub fn new(
+        sender_id: String,
+        receiver_id: String,
+        sent_at_ns: u64,
+        ping_latency_ns: u64,
+    ) -> Self {
         Self {
             sender_id,
             receiver_id,
-            sent_at_ms,
+            sent_at_ns,
+            ping_latency_ns,
+            payload: Vec::new(),
         }
     }
 
-    /// Retrieve when the message was sent
+    /// Set the payload of the message
+    #[must_use]
+    pub fn with_payload(mut self, payload: Vec<u8>) -> Self {
+        self.payload = payload;
+        self
+    }
+
+    /// Retrieve when the message was sent (see [`get_monotonic_time_nanos`])
     #[must_use]
-    pub fn sent_at_ms(&self) -> u128 {
-        self.sent_at_ms
+    pub fn sent_at_ns(&self) -> u64 {
+        self.sent_at_ns
+    }
+
+    /// Retrieve the one-way latency of the ping this pong responds to, in nanoseconds
+    #[must_use]
+    pub fn ping_latency_ns(&self) -> u64 {
+        self.ping_latency_ns
+    }
+
+    /// Retrieve the payload of the message
+    #[must_use]
+    pub fn payload(&self) -> &[u8] {
+        &self.payload
     }
 }
 
@@ -222,6 +321,12 @@ trait RpcPong {
 
     /// Retreive the receiver ID for the RPC message
     fn receiver_id(&self) -> &str;
+
+    /// Compute the one-way latencies of the round-trip this pong completes (upon receiving it),
+    /// if the RPC message carries timestamps
+    fn one_way_latencies(&self) -> Result<Option<OneWayLatencies>> {
+        Ok(None)
+    }
 }
 
 /// Simple version of a pong message, represented by a string
@@ -302,18 +407,54 @@ impl RpcPing for RawStringPingMessage {
 
 impl RpcPong for PongMessage {
     /// Retrieve the sender (ID) of the message
-    #[must_use]
     fn sender_id(&self) -> &str {
         &self.sender_id
     }
 
     /// Retrieve the intended receiver (ID) of the message
-    #[must_use]
+    fn receiver_id(&self) -> &str {
+        &self.receiver_id
+    }
+
+    /// Measure the one-way latencies of the round-trip (see [`OneWayLatencies::measure`])
+    fn one_way_latencies(&self) -> Result<Option<OneWayLatencies>> {
+        OneWayLatencies::measure(self.ping_latency_ns, self.sent_at_ns).map(Some)
+    }
+}
+
+impl RpcPing for ArchivedPingMessage {
+    /// Retrieve the sender (ID) of the message
+    fn sender_id(&self) -> &str {
+        &self.sender_id
+    }
+
+    /// Retrieve the intended receiver (ID) of the message
     fn receiver_id(&self) -> &str {
         &self.receiver_id
     }
 }
 
+impl RpcPong for ArchivedPongMessage {
+    /// Retrieve the sender (ID) of the message
+    fn sender_id(&self) -> &str {
+        &self.sender_id
+    }
+
+    /// Retrieve the intended receiver (ID) of the message
+    fn receiver_id(&self) -> &str {
+        &self.receiver_id
+    }
+
+    /// Measure the one-way latencies of the round-trip (see [`OneWayLatencies::measure`])
+    fn one_way_latencies(&self) -> Result<Option<OneWayLatencies>> {
+        OneWayLatencies::measure(
+            self.ping_latency_ns.to_native(),
+            self.sent_at_ns.to_native(),
+        )
+        .map(Some)
+    }
+}
+
 /// Required to make deserialize work properly
 ///
 /// https://github.com/servo/ipc-channel/issues/238
@@ -451,10 +592,88 @@ impl IpcChannelInitComplete {
     }
 }
 
-/// Retrieve current system time as milliseconds since the UNIX epoch
-pub fn get_system_time_millis() -> Result<u128> {
-    SystemTime::now()
-        .duration_since(SystemTime::UNIX_EPOCH)
-        .map(|v| v.as_millis())
-        .context("failed to retrieve system time")
+/// Retrieve the size of the payload carried by every ping from env, or use the default (no payload)
+pub fn payload_size_bytes_from_env_or_default(
+    mut values: impl Iterator<Item = (String, String)>,
+) -> usize {
+    values
+        .find(|(k, _)| k == ENV_VAR_RPC_PAYLOAD_SIZE_BYTES)
+        .and_then(|(_, v)| v.parse().ok())
+        .unwrap_or_default()
+}
+
+/// Report the throughput of the payloads carried by pings (via tracing, and on STDERR like the rest of the results)
+///
+/// Payloads travel in both directions (children echo them back in pongs), so both are counted.
+/// Nothing is reported if pings carry no payload.
+pub fn report_payload_throughput(
+    payload_size_bytes: usize,
+    roundtrips_per_second: f64,
+) -> Result<()> {
+    report_throughput(payload_size_bytes, 2, roundtrips_per_second)
+}
+
+/// Report the throughput of payloads that are only carried by pings, and not echoed back in pongs
+/// (ex. the bulk modes of [`crate::ipcc`])
+///
+/// Nothing is reported if pings carry no payload.
+pub fn report_one_way_payload_throughput(
+    payload_size_bytes: usize,
+    roundtrips_per_second: f64,
+) -> Result<()> {
+    report_throughput(payload_size_bytes, 1, roundtrips_per_second)
+}
+
+/// Report the throughput of payloads that travel in one or both directions of every round-trip
+fn report_throughput(
+    payload_size_bytes: usize,
+    directions: usize,
+    roundtrips_per_second: f64,
+) -> Result<()> {
+    if payload_size_bytes == 0 {
+        return Ok(());
+    }
+    let payload_megabytes_per_second = roundtrips_per_second
+        * f64::value_from(directions * payload_size_bytes)
+            .context("failed to convert payload size to f64")?
+        / (1024.0 * 1024.0);
+    info!(
+        payload_size_bytes,
+        directions, payload_megabytes_per_second, "completed payload transfers"
+    );
+    let ways = if directions == 1 {
+        "one way"
+    } else {
+        "each way"
+    };
+    eprintln!("sent [{payload_size_bytes}] byte payloads {ways} ([{payload_megabytes_per_second:.2}] MiB/second)");
+    Ok(())
+}
+
+/// Build a synthetic binary payload of a given size
+///
+/// Bytes cycle through `0..251` (rather than repeating a single value), so that
+/// codecs which encode bytes as numbers (i.e. JSON) see a realistic mix of values.
+#[must_use]
+pub fn synthetic_payload(size_bytes: usize) -> Vec<u8> {
+    (0..size_bytes)
+        .map(|i| u8::try_from(i % 251).unwrap_or_default())
+        .collect()
+}
+
+/// Retrieve the current time of the monotonic clock (`CLOCK_MONOTONIC`) in nanoseconds
+///
+/// Unlike wall-clock time, the monotonic clock never jumps, and it is shared by every process
+/// on the same host, so timestamps taken by parents & children can be compared with each other.
+pub fn get_monotonic_time_nanos() -> Result<u64> {
+    let now = Duration::from(
+        clock_gettime(ClockId::CLOCK_MONOTONIC).context("failed to retrieve monotonic time")?,
+    );
+    u64::try_from(now.as_nanos()).context("monotonic time does not fit in a u64")
+}
+
+/// Compute the time elapsed (in nanoseconds) since a timestamp taken with [`get_monotonic_time_nanos`],
+/// possibly by another process
+pub fn monotonic_nanos_since(timestamp_ns: u64) -> Result<u64> {
+    Ok(get_monotonic_time_nanos()?.saturating_sub(timestamp_ns))
 }
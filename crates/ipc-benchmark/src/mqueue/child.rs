@@ -0,0 +1,100 @@
+//! Child-specific IPC implementation over POSIX message queues
+
+use std::io::{stdin, BufRead};
+
+use anyhow::{ensure, Context as _, Result};
+use nix::mqueue::MQ_OFlag;
+use tracing::debug;
+use uuid::Uuid;
+
+use crate::mqueue::{MqueueHandle, MqueueInit, MqueueInitResponse};
+use crate::{
+    get_monotonic_time_nanos, monotonic_nanos_since, ChildProcess, PingMessage, PongMessage,
+};
+
+/// A child process that performs IPC via POSIX message queues
+#[derive(Debug)]
+pub struct MqueueChild {
+    /// UUID that should uniquely identify this process
+    uuid: Uuid,
+}
+
+impl Default for MqueueChild {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl MqueueChild {
+    /// Build a new [`MqueueChild`] with a random UUID
+    #[must_use]
+    pub fn new() -> Self {
+        Self {
+            uuid: Uuid::now_v7(),
+        }
+    }
+}
+
+impl ChildProcess for MqueueChild {
+    fn id(&self) -> String {
+        self.uuid.to_string()
+    }
+
+    fn run(self) -> Result<()> {
+        debug!("child process running");
+
+        debug!("reading mqueue init from STDIN");
+        let mut s = String::new();
+        stdin().lock().read_line(&mut s)?;
+
+        // We expect to receive an init message on STDIN
+        let MqueueInit {
+            parent_id,
+            codec,
+            to_child_name,
+            from_child_name,
+        } = serde_json::from_slice(s.as_bytes())
+            .context("failed to read init message from STDIN")?;
+
+        debug!(to_child_name, from_child_name, "opening message queues");
+        let mut from_parent = MqueueHandle::open(to_child_name, MQ_OFlag::O_RDONLY, codec)
+            .context("failed to open queue from parent")?;
+        let mut to_parent = MqueueHandle::open(from_child_name, MQ_OFlag::O_WRONLY, codec)
+            .context("failed to open queue to parent")?;
+
+        to_parent
+            .write_message(&MqueueInitResponse {
+                parent_id: parent_id.clone(),
+                child_id: self.id(),
+            })
+            .context("failed to write init response to parent")?;
+        debug!("successfully wrote init response to parent");
+
+        // Enter reading/writing loop
+        debug!("entering read loop...");
+        loop {
+            let PingMessage {
+                sender_id,
+                receiver_id,
+                sent_at_ns,
+                payload,
+                ..
+            } = from_parent
+                .read_message()
+                .context("failed to read ping message")?;
+            let ping_latency_ns = monotonic_nanos_since(sent_at_ns)?;
+            ensure!(sender_id == parent_id, "sender should be parent");
+            ensure!(receiver_id == self.id(), "receiver should be child");
+
+            to_parent
+                .write_message(&PongMessage {
+                    sender_id: self.id(),
+                    receiver_id: sender_id,
+                    sent_at_ns: get_monotonic_time_nanos()?,
+                    ping_latency_ns,
+                    payload,
+                })
+                .context("failed to send pong to parent")?;
+        }
+    }
+}
@@ -0,0 +1,214 @@
+/*!
+Process IPC over [POSIX message queues][man-mq-overview] (`mq_open()`, via [`nix`][crate-nix]).
+
+The parent creates a pair of queues (one per direction) named after its UUID (and the child's name),
+and sends the names to the child over STDIN. The child opens the existing queues, and announces
+itself with an init response message, after which the ping/pong loop begins.
+
+POSIX message queues preserve message boundaries, so every message is exactly one queue message
+(no length prefix is necessary), but messages are limited to the queue's max message size.
+
+Queues are unlinked when the parent drops them, so they do not outlive the parent
+(even if the child is still running).
+
+NOTE: the default system limits on Linux are 10 messages per queue (`/proc/sys/fs/mqueue/msg_max`)
+and 8192 bytes per message (`/proc/sys/fs/mqueue/msgsize_max`). Requesting larger values
+may require raising those limits (or `CAP_SYS_RESOURCE`).
+
+[man-mq-overview]: <https://man7.org/linux/man-pages/man7/mq_overview.7.html>
+[crate-nix]: <https://crates.io/crates/nix>
+**/
+
+use std::os::fd::AsRawFd as _;
+
+use anyhow::{ensure, Context as _, Result};
+use nix::mqueue::{
+    mq_attr_member_t, mq_getattr, mq_open, mq_receive, mq_send, mq_unlink, MQ_OFlag, MqAttr, MqdT,
+};
+use nix::sys::stat::Mode;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use tracing::{debug, warn};
+
+use crate::codec::{Codec as _, RpcCodec};
+use crate::proto::{impl_init_response_proto, ProtoMessage};
+
+pub mod child;
+pub mod parent;
+
+pub use child::MqueueChild;
+pub use parent::MqueueParent;
+
+/// Default max number of messages held by each queue
+const DEFAULT_MQUEUE_MAX_MESSAGES: usize = 10;
+
+/// Default max size of a single message (in bytes)
+const DEFAULT_MQUEUE_MAX_MSG_SIZE_BYTES: usize = 8192;
+
+/// Priority that all messages are sent with
+///
+/// NOTE: since there is only ever one message in flight in each direction, priorities
+/// have no effect on ordering, so every message uses the same (lowest) priority.
+const MESSAGE_PRIORITY: u32 = 0;
+
+/// Message sent to child processes over STDIN that contains
+/// information necessary for the child to open both queues
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+struct MqueueInit {
+    /// UUID of the parent
+    parent_id: String,
+
+    /// Codec used to encode messages (after this init message)
+    codec: RpcCodec,
+
+    /// Name of the queue that the parent writes to (and the child reads from)
+    to_child_name: String,
+
+    /// Name of the queue that the child writes to (and the parent reads from)
+    from_child_name: String,
+}
+
+/// Message sent from the child process (via the queue) upon successful initialization
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
+struct MqueueInitResponse {
+    /// UUID of the parent
+    parent_id: String,
+
+    /// UUID of the child
+    child_id: String,
+}
+
+impl_init_response_proto!(MqueueInitResponse);
+
+/// A handle over one (open) POSIX message queue, which sends and receives one message per queue message
+struct MqueueHandle {
+    /// Name of the queue (ex. `/ipc-benchmark-<uuid>-<child>-to-child`)
+    name: String,
+
+    /// Open queue descriptor
+    mqd: MqdT,
+
+    /// Whether this handle created the queue (and should unlink it when dropped)
+    owned: bool,
+
+    /// Buffer that every message is received into
+    ///
+    /// NOTE: `mq_receive()` requires this to be at least as large as the queue's max message size
+    recv_buf: Box<[u8]>,
+
+    /// Scratch buffer that every outgoing message is serialized into
+    send_buf: Vec<u8>,
+
+    /// Codec used to encode messages
+    codec: RpcCodec,
+}
+
+impl std::fmt::Debug for MqueueHandle {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("MqueueHandle")
+            .field("name", &self.name)
+            .field("owned", &self.owned)
+            .field("max_msg_size_bytes", &self.recv_buf.len())
+            .field("codec", &self.codec)
+            .finish()
+    }
+}
+
+impl Drop for MqueueHandle {
+    fn drop(&mut self) {
+        // SAFETY: the descriptor is open, and is never used again after this point
+        //
+        // NOTE: on Linux, message queue descriptors are file descriptors, and
+        // nix does not close them on drop
+        unsafe {
+            libc::mq_close(self.mqd.as_raw_fd());
+        }
+        if self.owned {
+            debug!(name = self.name, "unlinking message queue");
+            if let Err(e) = mq_unlink(self.name.as_str()) {
+                warn!(name = self.name, "failed to unlink message queue: {e}");
+            }
+        }
+    }
+}
+
+impl MqueueHandle {
+    /// Create a new (exclusive) queue with the given name and limits, which will be unlinked on drop
+    fn create(
+        name: impl Into<String>,
+        oflag: MQ_OFlag,
+        max_messages: usize,
+        max_msg_size_bytes: usize,
+        codec: RpcCodec,
+    ) -> Result<Self> {
+        let name = name.into();
+        let attr = MqAttr::new(
+            0,
+            mq_attr_member_t::try_from(max_messages).context("failed to convert max messages")?,
+            mq_attr_member_t::try_from(max_msg_size_bytes)
+                .context("failed to convert max message size")?,
+            0,
+        );
+        let mqd = mq_open(
+            name.as_str(),
+            oflag | MQ_OFlag::O_CREAT | MQ_OFlag::O_EXCL,
+            Mode::S_IRUSR | Mode::S_IWUSR,
+            Some(&attr),
+        )
+        .with_context(|| format!("failed to create message queue [{name}] ({attr:?})"))?;
+        Self::from_mqd(name, mqd, true, codec)
+    }
+
+    /// Open an existing queue (created by another process) with the given name
+    fn open(name: impl Into<String>, oflag: MQ_OFlag, codec: RpcCodec) -> Result<Self> {
+        let name = name.into();
+        let mqd = mq_open(name.as_str(), oflag, Mode::empty(), None)
+            .with_context(|| format!("failed to open message queue [{name}]"))?;
+        Self::from_mqd(name, mqd, false, codec)
+    }
+
+    /// Build a [`MqueueHandle`] from an open queue descriptor
+    fn from_mqd(name: String, mqd: MqdT, owned: bool, codec: RpcCodec) -> Result<Self> {
+        let attr = mq_getattr(&mqd)
+            .with_context(|| format!("failed to get attributes of message queue [{name}]"))?;
+        let max_msg_size_bytes =
+            usize::try_from(attr.msgsize()).context("invalid max message size")?;
+        debug!(
+            name,
+            max_msg_size_bytes,
+            max_messages = attr.maxmsg(),
+            "opened message queue"
+        );
+        Ok(Self {
+            name,
+            mqd,
+            owned,
+            recv_buf: vec![0; max_msg_size_bytes].into_boxed_slice(),
+            send_buf: Vec::new(),
+            codec,
+        })
+    }
+
+    /// Perform a blocking read of a single message
+    fn read_message<T: DeserializeOwned + ProtoMessage>(&mut self) -> Result<T> {
+        let mut priority = 0;
+        let len = mq_receive(&self.mqd, &mut self.recv_buf, &mut priority)
+            .with_context(|| format!("failed to receive from message queue [{}]", self.name))?;
+        debug!(len, priority, "received message");
+        self.codec.decode(&self.recv_buf[..len])
+    }
+
+    /// Perform a blocking write of a single message
+    fn write_message<T: Serialize + ProtoMessage>(&mut self, obj: &T) -> Result<usize> {
+        self.send_buf.clear();
+        self.codec.encode_into(obj, &mut self.send_buf)?;
+        let msg_len = self.send_buf.len();
+        ensure!(
+            msg_len <= self.recv_buf.len(),
+            "serialized message of len [{msg_len}] is greater than max message size [{}]",
+            self.recv_buf.len()
+        );
+        mq_send(&self.mqd, &self.send_buf, MESSAGE_PRIORITY)
+            .with_context(|| format!("failed to send to message queue [{}]", self.name))?;
+        Ok(msg_len)
+    }
+}
@@ -0,0 +1,219 @@
+//! Parent-specific IPC implementation over POSIX message queues
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::io::Write;
+use std::process::{Child, Command, Stdio};
+
+use anyhow::{ensure, Context as _, Result};
+use nix::mqueue::MQ_OFlag;
+use tracing::{debug, info};
+use uuid::Uuid;
+
+use crate::codec::RpcCodec;
+use crate::latency::OneWayLatencies;
+use crate::mqueue::{
+    MqueueHandle, MqueueInit, MqueueInitResponse, DEFAULT_MQUEUE_MAX_MESSAGES,
+    DEFAULT_MQUEUE_MAX_MSG_SIZE_BYTES,
+};
+use crate::{
+    get_monotonic_time_nanos, payload_size_bytes_from_env_or_default, synthetic_payload, ChildId,
+    ChildName, ParentProcess, PingMessage, Pinger, PongMessage, RpcPong,
+};
+
+/// Bi-directional channel for communication with a single child
+///
+/// NOTE: both queues are unlinked when this channel is dropped
+struct MqueueChannel {
+    /// Self-reported ID of the child
+    child_id: ChildId,
+
+    /// Queue that the parent writes pings to
+    to_child: MqueueHandle,
+
+    /// Queue that the parent reads pongs from
+    from_child: MqueueHandle,
+}
+
+/// A parent process that performs IPC via POSIX message queues
+pub struct MqueueParent {
+    /// UUID of the parent process
+    uuid: Uuid,
+
+    /// Synthetic payload sent with every ping (see `RPC_PAYLOAD_SIZE_BYTES`)
+    payload: Vec<u8>,
+
+    /// Codec used to encode messages (sent to children in the init message)
+    codec: RpcCodec,
+
+    /// Max number of messages held by each queue
+    max_messages: usize,
+
+    /// Max size of a single message (in bytes)
+    max_msg_size_bytes: usize,
+
+    /// Channels for communicating with children, by child name
+    ///
+    /// SAFETY: We're safe using a `RefCell` here because this structure
+    /// is very much *not* multi-threaded.
+    channels: HashMap<ChildName, RefCell<MqueueChannel>>,
+}
+
+impl std::fmt::Debug for MqueueParent {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("MqueueParent")
+            .field("uuid", &self.uuid)
+            .field("max_messages", &self.max_messages)
+            .field("max_msg_size_bytes", &self.max_msg_size_bytes)
+            .finish()
+    }
+}
+
+impl Default for MqueueParent {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl MqueueParent {
+    /// Create a new [`MqueueParent`]
+    #[must_use]
+    pub fn new() -> Self {
+        let max_messages = std::env::var("MQUEUE_MAX_MESSAGES")
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(DEFAULT_MQUEUE_MAX_MESSAGES);
+        let max_msg_size_bytes = std::env::var("MQUEUE_MAX_MSG_SIZE_BYTES")
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(DEFAULT_MQUEUE_MAX_MSG_SIZE_BYTES);
+        Self {
+            uuid: Uuid::now_v7(),
+            payload: synthetic_payload(payload_size_bytes_from_env_or_default(std::env::vars())),
+            codec: RpcCodec::from_env_or_default(std::env::vars()),
+            max_messages,
+            max_msg_size_bytes,
+            channels: HashMap::new(),
+        }
+    }
+}
+
+impl ParentProcess for MqueueParent {
+    fn id(&self) -> String {
+        self.uuid.to_string()
+    }
+
+    fn spawn_child(&mut self, name: impl AsRef<str>, mut cmd: Command) -> Result<Child> {
+        let name = name.as_ref();
+
+        // Create both queues before the child is started
+        info!(
+            max_messages = self.max_messages,
+            max_msg_size_bytes = self.max_msg_size_bytes,
+            "creating message queues"
+        );
+        let to_child = MqueueHandle::create(
+            format!("/ipc-benchmark-{}-{name}-to-child", self.uuid),
+            MQ_OFlag::O_WRONLY,
+            self.max_messages,
+            self.max_msg_size_bytes,
+            self.codec,
+        )
+        .context("failed to create queue to child")?;
+        let mut from_child = MqueueHandle::create(
+            format!("/ipc-benchmark-{}-{name}-from-child", self.uuid),
+            MQ_OFlag::O_RDONLY,
+            self.max_messages,
+            self.max_msg_size_bytes,
+            self.codec,
+        )
+        .context("failed to create queue from child")?;
+
+        // Spawn the child
+        debug!("spawning child");
+        let mut child = cmd
+            .stdin(Stdio::piped())
+            .spawn()
+            .context("failed to spawn child process")?;
+
+        // Send the init message over STDIN
+        let init_msg = MqueueInit {
+            parent_id: self.id(),
+            codec: self.codec,
+            to_child_name: to_child.name.clone(),
+            from_child_name: from_child.name.clone(),
+        };
+        debug!(?init_msg, "writing init to child STDIN");
+        let mut child_stdin = child.stdin.take().context("failed to get child STDIN")?;
+        child_stdin
+            .write_all(&serde_json::to_vec(&init_msg).context("failed to serialize init msg")?)
+            .context("failed to write init msg")?;
+        child_stdin
+            .write_all(b"\r\n")
+            .context("failed to write new line")?;
+        child_stdin.flush().context("failed to flush child STDIN")?;
+
+        // Wait for the child to respond over the queue
+        debug!("waiting for init response from child");
+        let init_resp: MqueueInitResponse = from_child
+            .read_message()
+            .context("failed to read init response from child")?;
+        ensure!(
+            init_resp.parent_id == self.id(),
+            "parent ID reported by child did not match"
+        );
+        debug!(child_id = init_resp.child_id, "received init response");
+
+        // Save information to local registry
+        self.channels.insert(
+            name.into(),
+            RefCell::new(MqueueChannel {
+                child_id: init_resp.child_id,
+                to_child,
+                from_child,
+            }),
+        );
+
+        Ok(child)
+    }
+}
+
+impl Pinger for MqueueParent {
+    fn roundtrip_ping(&self, child_name: impl AsRef<str>) -> Result<Option<OneWayLatencies>> {
+        let child = child_name.as_ref();
+        let mut chan = self
+            .channels
+            .get(child)
+            .with_context(|| format!("failed to find child [{child}]"))?
+            .borrow_mut();
+        let MqueueChannel {
+            child_id,
+            to_child,
+            from_child,
+        } = &mut *chan;
+
+        debug!(child, "sending ping message to child");
+        to_child
+            .write_message(&PingMessage {
+                sender_id: self.id(),
+                receiver_id: child_id.clone(),
+                sent_at_ns: get_monotonic_time_nanos()?,
+                payload: self.payload.clone(),
+            })
+            .context("failed to send ping message to child")?;
+
+        debug!(child, "reading pong message from child");
+        let pong_msg: PongMessage = from_child
+            .read_message()
+            .context("failed to read pong message")?;
+        let latencies = pong_msg.one_way_latencies()?;
+        ensure!(pong_msg.sender_id() == child_id, "child ID matches");
+        ensure!(pong_msg.receiver_id() == self.id(), "parent ID matches");
+        ensure!(
+            pong_msg.payload() == self.payload.as_slice(),
+            "payload matches"
+        );
+
+        Ok(latencies)
+    }
+}
@@ -0,0 +1,146 @@
+/*!
+Open-loop load generation, where pings are sent on a fixed schedule rather than as soon as the previous pong returns.
+
+By default, parents run *closed-loop*: the next ping is only sent once the previous pong has been received.
+Whenever a round-trip stalls, the pings that would have been sent in the meantime are silently never sent,
+so the stall is only counted once in the latency distribution (this is known as "coordinated omission").
+
+With `OPEN_LOOP_PINGS_PER_SECOND` set, pings are scheduled at fixed intervals instead (like requests arriving
+at a service), and round-trip latency is measured from the time each ping was *intended* to be sent,
+so that time spent queued behind a slow round-trip is counted as well.
+
+When a transport cannot keep up with the target rate, pings fall further and further behind schedule (and are sent
+back-to-back), which shows up as an achieved rate below the target: the transport is saturated.
+**/
+
+use std::time::{Duration, Instant};
+
+use anyhow::{ensure, Context as _, Result};
+use conv::ValueFrom as _;
+use tracing::{info, warn};
+
+/// ENV variable for setting the target rate of pings (which enables open-loop mode)
+const ENV_VAR_OPEN_LOOP_PINGS_PER_SECOND: &str = "OPEN_LOOP_PINGS_PER_SECOND";
+
+/// Fraction of the target rate below which a transport is considered saturated
+const SATURATION_THRESHOLD: f64 = 0.95;
+
+/// Remaining wait under which the schedule spins rather than sleeps
+///
+/// Sleeping usually overshoots by tens of microseconds, which is longer than the interval between pings at high rates.
+const SPIN_THRESHOLD: Duration = Duration::from_micros(100);
+
+/// Fixed-rate schedule of pings, for open-loop load generation
+#[derive(Debug, Clone)]
+pub struct OpenLoopSchedule {
+    /// Target rate of pings
+    pings_per_second: f64,
+
+    /// Interval between two scheduled pings
+    interval: Duration,
+
+    /// When the first ping was scheduled, if any
+    started_at: Option<Instant>,
+
+    /// When the next ping is scheduled
+    next_ping_at: Option<Instant>,
+
+    /// When the most recent round-trip completed, if any
+    last_completed_at: Option<Instant>,
+
+    /// Number of round-trips completed
+    completed: u64,
+}
+
+impl OpenLoopSchedule {
+    /// Create a new [`OpenLoopSchedule`], with a given target rate of pings
+    pub fn new(pings_per_second: f64) -> Result<Self> {
+        ensure!(
+            pings_per_second.is_finite() && pings_per_second > 0.0,
+            "invalid target rate of pings [{pings_per_second}], must be a positive number"
+        );
+        Ok(Self {
+            pings_per_second,
+            interval: Duration::from_secs_f64(1.0 / pings_per_second),
+            started_at: None,
+            next_ping_at: None,
+            last_completed_at: None,
+            completed: 0,
+        })
+    }
+
+    /// Build an [`OpenLoopSchedule`] from the rate in `OPEN_LOOP_PINGS_PER_SECOND`, if present in env
+    pub fn from_env(mut values: impl Iterator<Item = (String, String)>) -> Result<Option<Self>> {
+        values
+            .find(|(k, _)| k == ENV_VAR_OPEN_LOOP_PINGS_PER_SECOND)
+            .map(|(_, v)| {
+                v.parse::<f64>()
+                    .with_context(|| format!("failed to parse target rate of pings [{v}]"))
+                    .and_then(Self::new)
+            })
+            .transpose()
+    }
+
+    /// Wait until the next ping is due, returning the time at which it was scheduled
+    ///
+    /// The first ping is due immediately. Pings that are already late are not waited for,
+    /// so a schedule that has fallen behind catches up by sending pings back-to-back.
+    pub fn wait_for_next(&mut self) -> Instant {
+        let scheduled_at = *self.next_ping_at.get_or_insert_with(Instant::now);
+        self.started_at.get_or_insert(scheduled_at);
+        self.next_ping_at = Some(scheduled_at + self.interval);
+
+        loop {
+            let remaining = scheduled_at.saturating_duration_since(Instant::now());
+            if remaining.is_zero() {
+                return scheduled_at;
+            }
+            if remaining > SPIN_THRESHOLD {
+                std::thread::sleep(remaining - SPIN_THRESHOLD);
+            } else {
+                std::hint::spin_loop();
+            }
+        }
+    }
+
+    /// Record the completion of a round-trip
+    pub fn complete(&mut self, completed_at: Instant) {
+        self.last_completed_at = Some(completed_at);
+        self.completed += 1;
+    }
+
+    /// Rate of round-trips actually achieved (from the first scheduled ping to the last completed round-trip)
+    pub fn achieved_pings_per_second(&self) -> Result<f64> {
+        let (Some(started_at), Some(last_completed_at)) = (self.started_at, self.last_completed_at)
+        else {
+            return Ok(0.0);
+        };
+        Ok(f64::value_from(self.completed)
+            .context("failed to convert completed round-trips to f64")?
+            / last_completed_at.duration_since(started_at).as_secs_f64())
+    }
+
+    /// Report the target & achieved rates (via tracing, and on STDERR like the rest of the results),
+    /// and whether the transport was saturated
+    pub fn report(&self) -> Result<()> {
+        let target_pings_per_second = self.pings_per_second;
+        let achieved_pings_per_second = self.achieved_pings_per_second()?;
+        info!(
+            target_pings_per_second,
+            achieved_pings_per_second, "completed open-loop round-trips"
+        );
+        eprintln!("open-loop rate (round-trips/second): target [{target_pings_per_second:.2}] achieved [{achieved_pings_per_second:.2}]");
+
+        if achieved_pings_per_second < target_pings_per_second * SATURATION_THRESHOLD {
+            warn!(
+                target_pings_per_second,
+                achieved_pings_per_second, "transport is saturated"
+            );
+            eprintln!(
+                "open-loop rate: saturated (achieved rate is below [{:.0}%] of target)",
+                SATURATION_THRESHOLD * 100.0
+            );
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,107 @@
+//! Child-specific IPC implementation over anonymous pipes (STDIN/STDOUT)
+
+use std::fs::File;
+use std::io::{stdin, stdout, BufRead};
+use std::os::fd::AsFd as _;
+
+use anyhow::{ensure, Context as _, Result};
+use tracing::debug;
+use uuid::Uuid;
+
+use crate::pipe::{PipeInit, PipeInitResponse};
+use crate::stream::{is_eof, StreamHandle};
+use crate::{
+    get_monotonic_time_nanos, monotonic_nanos_since, ChildProcess, PingMessage, PongMessage,
+};
+
+/// A child process that performs IPC via its own STDIN/STDOUT
+#[derive(Debug)]
+pub struct PipeChild {
+    /// UUID that should uniquely identify this process
+    uuid: Uuid,
+}
+
+impl Default for PipeChild {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl PipeChild {
+    /// Build a new [`PipeChild`] with a random UUID
+    #[must_use]
+    pub fn new() -> Self {
+        Self {
+            uuid: Uuid::now_v7(),
+        }
+    }
+}
+
+impl ChildProcess for PipeChild {
+    fn id(&self) -> String {
+        self.uuid.to_string()
+    }
+
+    fn run(self) -> Result<()> {
+        debug!("child process running");
+
+        debug!("reading pipe init from STDIN");
+        let mut stdin = stdin().lock();
+        let mut s = String::new();
+        stdin.read_line(&mut s)?;
+
+        // We expect to receive an init message on STDIN
+        let PipeInit { parent_id, codec } = serde_json::from_slice(s.as_bytes())
+            .context("failed to read init message from STDIN")?;
+
+        // Write directly to the STDOUT file descriptor, rather than through the
+        // line-buffered `Stdout`, which would split messages on newline bytes
+        let stdout = File::from(
+            stdout()
+                .as_fd()
+                .try_clone_to_owned()
+                .context("failed to clone STDOUT fd")?,
+        );
+        let mut stream = StreamHandle::new(stdin, stdout, codec);
+
+        stream
+            .write_message(&PipeInitResponse {
+                parent_id: parent_id.clone(),
+                child_id: self.id(),
+            })
+            .context("failed to write init response to parent")?;
+        debug!("successfully wrote init response to parent");
+
+        // Enter reading/writing loop
+        debug!("entering read loop...");
+        loop {
+            let PingMessage {
+                sender_id,
+                receiver_id,
+                sent_at_ns,
+                payload,
+                ..
+            } = match stream.read_message() {
+                Ok(msg) => msg,
+                Err(e) if is_eof(&e) => {
+                    debug!("parent closed STDIN, exiting");
+                    return Ok(());
+                }
+                Err(e) => return Err(e.context("failed to read ping message")),
+            };
+            let ping_latency_ns = monotonic_nanos_since(sent_at_ns)?;
+            ensure!(sender_id == parent_id, "sender should be parent");
+            ensure!(receiver_id == self.id(), "receiver should be child");
+
+            stream
+                .write_message(&PongMessage {
+                    sender_id: self.id(),
+                    receiver_id: sender_id,
+                    sent_at_ns: get_monotonic_time_nanos()?,
+                    ping_latency_ns,
+                    payload,
+                })
+                .context("failed to send pong to parent")?;
+        }
+    }
+}
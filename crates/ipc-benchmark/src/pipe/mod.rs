@@ -0,0 +1,43 @@
+/*!
+Process IPC over the anonymous pipes that are already set up for the child's STDIN/STDOUT.
+
+Every child in this crate bootstraps via an init payload on STDIN -- this transport simply keeps
+using those pipes after the handshake, with length-prefixed messages in both directions
+(parent -> child over STDIN, child -> parent over STDOUT).
+
+Since STDOUT is used for messages, children *must not* write anything else to it
+(ex. logging should go to STDERR).
+**/
+
+use serde::{Deserialize, Serialize};
+
+use crate::codec::RpcCodec;
+use crate::proto::impl_init_response_proto;
+
+pub mod child;
+pub mod parent;
+
+pub use child::PipeChild;
+pub use parent::PipeParent;
+
+/// Message sent to child processes over STDIN (as a single line) before any other messages
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
+struct PipeInit {
+    /// UUID of the parent
+    parent_id: String,
+
+    /// Codec used to encode messages (after this init message)
+    codec: RpcCodec,
+}
+
+/// Message sent from the child process (over STDOUT) upon successful initialization
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
+struct PipeInitResponse {
+    /// UUID of the parent
+    parent_id: String,
+
+    /// UUID of the child
+    child_id: String,
+}
+
+impl_init_response_proto!(PipeInitResponse);
@@ -0,0 +1,167 @@
+//! Parent-specific IPC implementation over anonymous pipes (child STDIN/STDOUT)
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::io::Write;
+use std::process::{Child, ChildStdin, ChildStdout, Command, Stdio};
+
+use anyhow::{ensure, Context as _, Result};
+use tracing::debug;
+use uuid::Uuid;
+
+use crate::codec::RpcCodec;
+use crate::latency::OneWayLatencies;
+use crate::pipe::{PipeInit, PipeInitResponse};
+use crate::stream::StreamHandle;
+use crate::{
+    get_monotonic_time_nanos, payload_size_bytes_from_env_or_default, synthetic_payload, ChildId,
+    ChildName, ParentProcess, PingMessage, Pinger, PongMessage, RpcPong,
+};
+
+/// Bi-directional channel for communication with a single child
+struct PipeChannel {
+    /// Self-reported ID of the child
+    child_id: ChildId,
+
+    /// Pipes connected to the child (reading from STDOUT, writing to STDIN)
+    stream: StreamHandle<ChildStdout, ChildStdin>,
+}
+
+/// A parent process that performs IPC via the anonymous pipes connected to the child's STDIN/STDOUT
+pub struct PipeParent {
+    /// UUID of the parent process
+    uuid: Uuid,
+
+    /// Synthetic payload sent with every ping (see `RPC_PAYLOAD_SIZE_BYTES`)
+    payload: Vec<u8>,
+
+    /// Codec used to encode messages (sent to children in the init message)
+    codec: RpcCodec,
+
+    /// Channels for communicating with children, by child name
+    ///
+    /// SAFETY: We're safe using a `RefCell` here because this structure
+    /// is very much *not* multi-threaded.
+    channels: HashMap<ChildName, RefCell<PipeChannel>>,
+}
+
+impl std::fmt::Debug for PipeParent {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("PipeParent")
+            .field("uuid", &self.uuid)
+            .finish()
+    }
+}
+
+impl Default for PipeParent {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl PipeParent {
+    /// Create a new [`PipeParent`]
+    #[must_use]
+    pub fn new() -> Self {
+        Self {
+            uuid: Uuid::now_v7(),
+            payload: synthetic_payload(payload_size_bytes_from_env_or_default(std::env::vars())),
+            codec: RpcCodec::from_env_or_default(std::env::vars()),
+            channels: HashMap::new(),
+        }
+    }
+}
+
+impl ParentProcess for PipeParent {
+    fn id(&self) -> String {
+        self.uuid.to_string()
+    }
+
+    fn spawn_child(&mut self, name: impl AsRef<str>, mut cmd: Command) -> Result<Child> {
+        let name = name.as_ref();
+
+        // Spawn the child
+        debug!("spawning child");
+        let mut child = cmd
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .spawn()
+            .context("failed to spawn child process")?;
+
+        // Send the init message over STDIN, as a single line
+        let init_msg = PipeInit {
+            parent_id: self.id(),
+            codec: self.codec,
+        };
+        debug!(?init_msg, "writing init to child STDIN");
+        let mut child_stdin = child.stdin.take().context("failed to get child STDIN")?;
+        child_stdin
+            .write_all(&serde_json::to_vec(&init_msg).context("failed to serialize init msg")?)
+            .context("failed to write init msg")?;
+        child_stdin
+            .write_all(b"\r\n")
+            .context("failed to write new line")?;
+        child_stdin.flush().context("failed to flush child STDIN")?;
+
+        // From here on, both pipes carry length-prefixed messages
+        let child_stdout = child.stdout.take().context("failed to get child STDOUT")?;
+        let mut stream = StreamHandle::new(child_stdout, child_stdin, self.codec);
+
+        debug!("waiting for init response from child");
+        let init_resp: PipeInitResponse = stream
+            .read_message()
+            .context("failed to read init response from child")?;
+        ensure!(
+            init_resp.parent_id == self.id(),
+            "parent ID reported by child did not match"
+        );
+        debug!(child_id = init_resp.child_id, "received init response");
+
+        // Save information to local registry
+        self.channels.insert(
+            name.into(),
+            RefCell::new(PipeChannel {
+                child_id: init_resp.child_id,
+                stream,
+            }),
+        );
+
+        Ok(child)
+    }
+}
+
+impl Pinger for PipeParent {
+    fn roundtrip_ping(&self, child_name: impl AsRef<str>) -> Result<Option<OneWayLatencies>> {
+        let child = child_name.as_ref();
+        let mut chan = self
+            .channels
+            .get(child)
+            .with_context(|| format!("failed to find child [{child}]"))?
+            .borrow_mut();
+        let PipeChannel { child_id, stream } = &mut *chan;
+
+        debug!(child, "sending ping message to child");
+        stream
+            .write_message(&PingMessage {
+                sender_id: self.id(),
+                receiver_id: child_id.clone(),
+                sent_at_ns: get_monotonic_time_nanos()?,
+                payload: self.payload.clone(),
+            })
+            .context("failed to send ping message to child")?;
+
+        debug!(child, "reading pong message from child");
+        let pong_msg: PongMessage = stream
+            .read_message()
+            .context("failed to read pong message")?;
+        let latencies = pong_msg.one_way_latencies()?;
+        ensure!(pong_msg.sender_id() == child_id, "child ID matches");
+        ensure!(pong_msg.receiver_id() == self.id(), "parent ID matches");
+        ensure!(
+            pong_msg.payload() == self.payload.as_slice(),
+            "payload matches"
+        );
+
+        Ok(latencies)
+    }
+}
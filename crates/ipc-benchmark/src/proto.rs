@@ -0,0 +1,209 @@
+/*!
+Protobuf schemas for messages sent between parents and children, via [`prost`][crate-prost].
+
+The schemas live in `proto/ipc_benchmark.proto`, and Rust code is generated from them at build time
+(see [`schema`]). Types that can be encoded as protobuf implement [`ProtoMessage`], which converts
+them to & from their generated counterparts.
+
+Protobuf is used on the wire when `RPC_CODEC=protobuf` (see [`crate::codec`]). Init messages sent
+over STDIN are still JSON, but have schemas here as well, so that children that are not written
+in Rust can take part in the handshake.
+
+[crate-prost]: <https://crates.io/crates/prost>
+**/
+
+use anyhow::Result;
+
+use crate::codec::RpcCodec;
+use crate::shmem::zero_copy::ShmemReadMode;
+use crate::{IpcChannelChildInit, IpcChannelChildInitResponse, IpcChannelInitComplete};
+use crate::{PingMessage, PongMessage};
+
+/// Types generated (by `prost-build`) from `proto/ipc_benchmark.proto`
+#[allow(missing_docs, clippy::missing_docs_in_private_items)]
+pub mod schema {
+    include!(concat!(env!("OUT_DIR"), "/ipc_benchmark.rs"));
+}
+
+/// A message that has a protobuf schema (see [`schema`])
+pub trait ProtoMessage: Sized {
+    /// The generated protobuf message this type is encoded as
+    type Proto: prost::Message + Default;
+
+    /// Convert into the protobuf message
+    fn to_proto(&self) -> Result<Self::Proto>;
+
+    /// Convert from the protobuf message
+    fn from_proto(proto: Self::Proto) -> Result<Self>;
+}
+
+/// Implement [`ProtoMessage`] for an init response that contains *only* the parent & child IDs
+/// (i.e. `parent_id` and `child_id` fields), as [`schema::InitResponse`]
+macro_rules! impl_init_response_proto {
+    ($ty:ty) => {
+        impl $crate::proto::ProtoMessage for $ty {
+            type Proto = $crate::proto::schema::InitResponse;
+
+            fn to_proto(&self) -> anyhow::Result<Self::Proto> {
+                Ok(Self::Proto {
+                    parent_id: self.parent_id.clone(),
+                    child_id: self.child_id.clone(),
+                })
+            }
+
+            fn from_proto(proto: Self::Proto) -> anyhow::Result<Self> {
+                Ok(Self {
+                    parent_id: proto.parent_id,
+                    child_id: proto.child_id,
+                })
+            }
+        }
+    };
+}
+pub(crate) use impl_init_response_proto;
+
+impl From<RpcCodec> for schema::RpcCodec {
+    fn from(value: RpcCodec) -> Self {
+        match value {
+            RpcCodec::Json => Self::Json,
+            RpcCodec::Bincode => Self::Bincode,
+            RpcCodec::Postcard => Self::Postcard,
+            RpcCodec::MessagePack => Self::MessagePack,
+            RpcCodec::Cbor => Self::Cbor,
+            RpcCodec::Protobuf => Self::Protobuf,
+        }
+    }
+}
+
+impl From<schema::RpcCodec> for RpcCodec {
+    fn from(value: schema::RpcCodec) -> Self {
+        match value {
+            schema::RpcCodec::Json => Self::Json,
+            schema::RpcCodec::Bincode => Self::Bincode,
+            schema::RpcCodec::Postcard => Self::Postcard,
+            schema::RpcCodec::MessagePack => Self::MessagePack,
+            schema::RpcCodec::Cbor => Self::Cbor,
+            schema::RpcCodec::Protobuf => Self::Protobuf,
+        }
+    }
+}
+
+impl From<ShmemReadMode> for schema::ShmemReadMode {
+    fn from(value: ShmemReadMode) -> Self {
+        match value {
+            ShmemReadMode::Decode => Self::Decode,
+            ShmemReadMode::ZeroCopy => Self::ZeroCopy,
+        }
+    }
+}
+
+impl From<schema::ShmemReadMode> for ShmemReadMode {
+    fn from(value: schema::ShmemReadMode) -> Self {
+        match value {
+            schema::ShmemReadMode::Decode => Self::Decode,
+            schema::ShmemReadMode::ZeroCopy => Self::ZeroCopy,
+        }
+    }
+}
+
+impl ProtoMessage for PingMessage {
+    type Proto = schema::PingMessage;
+
+    fn to_proto(&self) -> Result<Self::Proto> {
+        Ok(Self::Proto {
+            sender_id: self.sender_id.clone(),
+            receiver_id: self.receiver_id.clone(),
+            sent_at_ns: self.sent_at_ns,
+            payload: self.payload.clone(),
+        })
+    }
+
+    fn from_proto(proto: Self::Proto) -> Result<Self> {
+        Ok(Self {
+            sender_id: proto.sender_id,
+            receiver_id: proto.receiver_id,
+            sent_at_ns: proto.sent_at_ns,
+            payload: proto.payload,
+        })
+    }
+}
+
+impl ProtoMessage for PongMessage {
+    type Proto = schema::PongMessage;
+
+    fn to_proto(&self) -> Result<Self::Proto> {
+        Ok(Self::Proto {
+            sender_id: self.sender_id.clone(),
+            receiver_id: self.receiver_id.clone(),
+            sent_at_ns: self.sent_at_ns,
+            ping_latency_ns: self.ping_latency_ns,
+            payload: self.payload.clone(),
+        })
+    }
+
+    fn from_proto(proto: Self::Proto) -> Result<Self> {
+        Ok(Self {
+            sender_id: proto.sender_id,
+            receiver_id: proto.receiver_id,
+            sent_at_ns: proto.sent_at_ns,
+            ping_latency_ns: proto.ping_latency_ns,
+            payload: proto.payload,
+        })
+    }
+}
+
+impl ProtoMessage for IpcChannelChildInit {
+    type Proto = schema::IpcChannelChildInit;
+
+    fn to_proto(&self) -> Result<Self::Proto> {
+        Ok(Self::Proto {
+            parent_id: self.parent_id.clone(),
+            ipc_server_name: self.ipc_server_name.clone(),
+        })
+    }
+
+    fn from_proto(proto: Self::Proto) -> Result<Self> {
+        Ok(Self {
+            parent_id: proto.parent_id,
+            ipc_server_name: proto.ipc_server_name,
+        })
+    }
+}
+
+impl ProtoMessage for IpcChannelChildInitResponse {
+    type Proto = schema::IpcChannelChildInitResponse;
+
+    fn to_proto(&self) -> Result<Self::Proto> {
+        Ok(Self::Proto {
+            parent_id: self.parent_id.clone(),
+            child_id: self.child_id.clone(),
+            ipc_server_name: self.ipc_server_name.clone(),
+        })
+    }
+
+    fn from_proto(proto: Self::Proto) -> Result<Self> {
+        Ok(Self {
+            parent_id: proto.parent_id,
+            child_id: proto.child_id,
+            ipc_server_name: proto.ipc_server_name,
+        })
+    }
+}
+
+impl ProtoMessage for IpcChannelInitComplete {
+    type Proto = schema::IpcChannelInitComplete;
+
+    fn to_proto(&self) -> Result<Self::Proto> {
+        Ok(Self::Proto {
+            parent_id: self.parent_id.clone(),
+            child_id: self.child_id.clone(),
+        })
+    }
+
+    fn from_proto(proto: Self::Proto) -> Result<Self> {
+        Ok(Self {
+            parent_id: proto.parent_id,
+            child_id: proto.child_id,
+        })
+    }
+}
@@ -0,0 +1,468 @@
+/*!
+Framing of messages into fragments, so that messages of any size can be sent over fixed-size shared memory.
+
+Every message written by a shared memory transport is split into one or more fragments, each of which
+fits in the transport's region (or queue). Every fragment starts with a [`FragmentHeader`]:
+
+```text
++------------------+----------------------+------------------+--------------------------+
+| message ID (u64) | fragment index (u32) | total len (u64)  | chunk of message bytes   |
++------------------+----------------------+------------------+--------------------------+
+```
+
+Readers reassemble fragments with a [`Reassembler`]. Messages that fit in a single fragment
+(the common case) are never copied out of shared memory, so they can still be decoded (or accessed,
+see [`crate::shmem::zero_copy`]) in place. Since the len of a message is read from (untrusted) fragment headers,
+messages larger than `SHARED_MEM_MAX_MESSAGE_SIZE_BYTES` (256MiB by default) are rejected before being allocated.
+
+Transports that hold a single message at a time (ex. [`crate::shmem::raw_sync`]) must wait for the reader
+to acknowledge every fragment but the last before overwriting it with the next one, while transports
+that queue messages (ex. [`crate::shmem::ring`]) get that for free from their back-pressure.
+**/
+
+use anyhow::{ensure, Context as _, Result};
+
+/// ENV variable for setting the max size of messages that are reassembled out of fragments
+const ENV_VAR_SHARED_MEM_MAX_MESSAGE_SIZE_BYTES: &str = "SHARED_MEM_MAX_MESSAGE_SIZE_BYTES";
+
+/// Default max size of messages that are reassembled out of fragments (256MiB)
+const DEFAULT_MAX_MESSAGE_SIZE_BYTES: usize = 256 * 1024 * 1024;
+
+/// Size of the [`FragmentHeader`] that precedes every fragment
+pub(crate) const FRAGMENT_HEADER_SIZE_BYTES: usize =
+    size_of::<u64>() + size_of::<u32>() + size_of::<u64>();
+
+/// Header that precedes every fragment of a message
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct FragmentHeader {
+    /// ID of the message this fragment is a part of (unique per writer)
+    pub(crate) message_id: u64,
+
+    /// Index of this fragment in the message (starting at zero)
+    pub(crate) fragment_index: u32,
+
+    /// Length of the whole message in bytes
+    pub(crate) total_len: u64,
+}
+
+impl FragmentHeader {
+    /// Encode the header as LE bytes
+    #[must_use]
+    pub(crate) fn to_bytes(self) -> [u8; FRAGMENT_HEADER_SIZE_BYTES] {
+        let mut bytes = [0u8; FRAGMENT_HEADER_SIZE_BYTES];
+        bytes[0..8].copy_from_slice(&self.message_id.to_le_bytes());
+        bytes[8..12].copy_from_slice(&self.fragment_index.to_le_bytes());
+        bytes[12..20].copy_from_slice(&self.total_len.to_le_bytes());
+        bytes
+    }
+
+    /// Split a fragment into its header and the chunk of message bytes it carries
+    pub(crate) fn split(fragment: &[u8]) -> Result<(Self, &[u8])> {
+        ensure!(
+            fragment.len() >= FRAGMENT_HEADER_SIZE_BYTES,
+            "fragment of len [{}] is too short to hold a header",
+            fragment.len()
+        );
+        let (header, chunk) = fragment.split_at(FRAGMENT_HEADER_SIZE_BYTES);
+        let header = Self {
+            message_id: u64::from_le_bytes(header[0..8].try_into().context("invalid message ID")?),
+            fragment_index: u32::from_le_bytes(
+                header[8..12].try_into().context("invalid fragment index")?,
+            ),
+            total_len: u64::from_le_bytes(header[12..20].try_into().context("invalid total len")?),
+        };
+        Ok((header, chunk))
+    }
+}
+
+/// Splits outgoing messages into fragments
+#[derive(Debug, Default)]
+pub(crate) struct Fragmenter {
+    /// ID given to the next message
+    next_message_id: u64,
+}
+
+impl Fragmenter {
+    /// Split a message into fragments carrying at most `max_chunk_size` message bytes each
+    ///
+    /// Every message produces at least one fragment (even when empty).
+    pub(crate) fn fragments<'a>(
+        &mut self,
+        msg: &'a [u8],
+        max_chunk_size: usize,
+    ) -> Result<impl Iterator<Item = (FragmentHeader, &'a [u8])>> {
+        ensure!(
+            max_chunk_size > 0,
+            "no space for message bytes in fragments (max fragment size must exceed the [{FRAGMENT_HEADER_SIZE_BYTES}] byte header)"
+        );
+        let fragment_count = msg.len().div_ceil(max_chunk_size).max(1);
+        ensure!(
+            u32::try_from(fragment_count).is_ok(),
+            "message of len [{}] would need too many fragments [{fragment_count}]",
+            msg.len()
+        );
+        let total_len = u64::try_from(msg.len()).context("failed to convert msg len to u64")?;
+        let message_id = self.next_message_id;
+        self.next_message_id = self.next_message_id.wrapping_add(1);
+
+        Ok((0..fragment_count).map(move |index| {
+            let start = index * max_chunk_size;
+            let end = msg.len().min(start + max_chunk_size);
+            (
+                FragmentHeader {
+                    message_id,
+                    // NOTE: the fragment count was checked to fit in a u32 above
+                    fragment_index: index as u32,
+                    total_len,
+                },
+                &msg[start..end],
+            )
+        }))
+    }
+}
+
+/// Reassembles incoming fragments into whole messages
+#[derive(Debug)]
+pub(crate) struct Reassembler {
+    /// Max size of messages, which bounds how much is allocated for a message based on its (untrusted) total len
+    max_message_size_bytes: usize,
+
+    /// Bytes of the message being (or most recently) reassembled
+    buf: Vec<u8>,
+
+    /// Header of the first fragment of the message being reassembled, if any
+    in_progress: Option<FragmentHeader>,
+
+    /// Index of the next expected fragment of the message being reassembled
+    next_index: u32,
+}
+
+impl Default for Reassembler {
+    fn default() -> Self {
+        Self::new(DEFAULT_MAX_MESSAGE_SIZE_BYTES)
+    }
+}
+
+impl Reassembler {
+    /// Create a new [`Reassembler`], which rejects messages larger than a given size
+    #[must_use]
+    pub(crate) fn new(max_message_size_bytes: usize) -> Self {
+        Self {
+            max_message_size_bytes,
+            buf: Vec::new(),
+            in_progress: None,
+            next_index: 0,
+        }
+    }
+
+    /// Build a [`Reassembler`] with the max message size in `SHARED_MEM_MAX_MESSAGE_SIZE_BYTES`, if present in env
+    /// (or the default, 256MiB)
+    pub(crate) fn from_env_or_default(mut values: impl Iterator<Item = (String, String)>) -> Self {
+        Self::new(
+            values
+                .find(|(k, _)| k == ENV_VAR_SHARED_MEM_MAX_MESSAGE_SIZE_BYTES)
+                .and_then(|(_, v)| v.parse().ok())
+                .unwrap_or(DEFAULT_MAX_MESSAGE_SIZE_BYTES),
+        )
+    }
+
+    /// Check whether a fragment carries an entire message, which can then be used in place
+    /// (without going through [`Reassembler::push`])
+    pub(crate) fn is_whole_message(&self, header: &FragmentHeader, chunk: &[u8]) -> Result<bool> {
+        if header.fragment_index != 0 || u64::try_from(chunk.len()).ok() != Some(header.total_len) {
+            return Ok(false);
+        }
+        ensure!(
+            self.in_progress.is_none(),
+            "received message [{}] while another message was being reassembled",
+            header.message_id
+        );
+        Ok(true)
+    }
+
+    /// Add a fragment, returning whether the message it belongs to is now complete
+    /// (in which case it can be retrieved with [`Reassembler::message`])
+    pub(crate) fn push(&mut self, header: FragmentHeader, chunk: &[u8]) -> Result<bool> {
+        match self.in_progress {
+            None => {
+                ensure!(
+                    header.fragment_index == 0,
+                    "received fragment [{}] of message [{}] without its first fragment",
+                    header.fragment_index,
+                    header.message_id
+                );
+                let total_len = usize::try_from(header.total_len)
+                    .context("failed to convert total len into usize")?;
+                ensure!(
+                    total_len <= self.max_message_size_bytes,
+                    "message [{}] of len [{total_len}] exceeds max message size [{}]",
+                    header.message_id,
+                    self.max_message_size_bytes
+                );
+                self.buf.clear();
+                self.buf.try_reserve(total_len).with_context(|| {
+                    format!(
+                        "failed to allocate [{total_len}] bytes for message [{}]",
+                        header.message_id
+                    )
+                })?;
+                self.in_progress = Some(header);
+                self.next_index = 0;
+            }
+            Some(first) => {
+                ensure!(
+                    header.message_id == first.message_id && header.total_len == first.total_len,
+                    "received fragment of message [{}] while reassembling message [{}]",
+                    header.message_id,
+                    first.message_id
+                );
+            }
+        }
+        ensure!(
+            header.fragment_index == self.next_index,
+            "received fragment [{}] of message [{}] out of order (expected [{}])",
+            header.fragment_index,
+            header.message_id,
+            self.next_index
+        );
+
+        let received_len = u64::try_from(self.buf.len() + chunk.len())
+            .context("failed to convert received len to u64")?;
+        ensure!(
+            received_len <= header.total_len,
+            "fragments of message [{}] overflow its total len [{}]",
+            header.message_id,
+            header.total_len
+        );
+        self.buf.extend_from_slice(chunk);
+        self.next_index += 1;
+
+        let complete = received_len == header.total_len;
+        if complete {
+            self.in_progress = None;
+        }
+        Ok(complete)
+    }
+
+    /// Add a fragment (header included), returning the message it belongs to once complete
+    ///
+    /// Messages that fit in a single fragment are returned in place, without being copied.
+    pub(crate) fn reassemble<'a>(&'a mut self, fragment: &'a [u8]) -> Result<Option<&'a [u8]>> {
+        let (header, chunk) = FragmentHeader::split(fragment)?;
+        if self.is_whole_message(&header, chunk)? {
+            return Ok(Some(chunk));
+        }
+        Ok(self.push(header, chunk)?.then(|| self.message()))
+    }
+
+    /// Retrieve the most recently completed message
+    #[must_use]
+    pub(crate) fn message(&self) -> &[u8] {
+        &self.buf
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Encode fragments (header included), as a writer would put them in shared memory
+    fn encode<'a>(fragments: impl Iterator<Item = (FragmentHeader, &'a [u8])>) -> Vec<Vec<u8>> {
+        fragments
+            .map(|(header, chunk)| [&header.to_bytes()[..], chunk].concat())
+            .collect()
+    }
+
+    /// Reassemble encoded fragments, returning the message once complete
+    fn reassemble_all(reassembler: &mut Reassembler, fragments: &[Vec<u8>]) -> Result<Vec<u8>> {
+        let (last, rest) = fragments.split_last().context("no fragments")?;
+        for fragment in rest {
+            ensure!(
+                reassembler.reassemble(fragment)?.is_none(),
+                "message completed before its last fragment"
+            );
+        }
+        reassembler
+            .reassemble(last)?
+            .map(<[u8]>::to_vec)
+            .context("message incomplete after its last fragment")
+    }
+
+    /// Check that an error was returned, and that it mentions a given message
+    fn assert_error<T: std::fmt::Debug>(result: Result<T>, expected: &str) {
+        let err = result.expect_err("unexpectedly succeeded");
+        assert!(
+            format!("{err:#}").contains(expected),
+            "error [{err:#}] does not mention [{expected}]"
+        );
+    }
+
+    /// Messages split into several fragments are reassembled whole
+    #[test]
+    fn round_trip_multiple_fragments() -> Result<()> {
+        let msg = b"0123456789";
+        let fragments = encode(Fragmenter::default().fragments(msg, 3)?);
+        assert_eq!(fragments.len(), 4);
+        assert_eq!(
+            reassemble_all(&mut Reassembler::default(), &fragments)?,
+            msg
+        );
+        Ok(())
+    }
+
+    /// Consecutive messages are reassembled by the same reassembler
+    #[test]
+    fn round_trip_consecutive_messages() -> Result<()> {
+        let mut fragmenter = Fragmenter::default();
+        let mut reassembler = Reassembler::default();
+        for msg in [&b"first message"[..], b"second", b"x"] {
+            let fragments = encode(fragmenter.fragments(msg, 4)?);
+            assert_eq!(reassemble_all(&mut reassembler, &fragments)?, msg);
+        }
+        Ok(())
+    }
+
+    /// Chunks that exactly fill the max chunk size produce no trailing empty fragment
+    #[test]
+    fn round_trip_exactly_full_chunks() -> Result<()> {
+        let msg = b"01234567";
+        let fragments = encode(Fragmenter::default().fragments(msg, msg.len())?);
+        assert_eq!(fragments.len(), 1);
+        assert_eq!(
+            reassemble_all(&mut Reassembler::default(), &fragments)?,
+            msg
+        );
+
+        let msg = b"0123456789abcdef";
+        let fragments = encode(Fragmenter::default().fragments(msg, 8)?);
+        assert_eq!(fragments.len(), 2);
+        assert_eq!(
+            reassemble_all(&mut Reassembler::default(), &fragments)?,
+            msg
+        );
+        Ok(())
+    }
+
+    /// Empty messages produce a single (empty) fragment
+    #[test]
+    fn round_trip_empty_message() -> Result<()> {
+        let fragments = encode(Fragmenter::default().fragments(&[], 8)?);
+        assert_eq!(
+            fragments,
+            vec![FragmentHeader {
+                message_id: 0,
+                fragment_index: 0,
+                total_len: 0,
+            }
+            .to_bytes()
+            .to_vec()]
+        );
+        assert!(reassemble_all(&mut Reassembler::default(), &fragments)?.is_empty());
+        Ok(())
+    }
+
+    /// Fragments cannot be empty of message bytes
+    #[test]
+    fn zero_max_chunk_size() {
+        assert_error(
+            Fragmenter::default().fragments(b"msg", 0).map(|_| ()),
+            "no space for message bytes",
+        );
+    }
+
+    /// Fragments too short to hold a header are rejected
+    #[test]
+    fn truncated_header() {
+        assert_error(
+            Reassembler::default().reassemble(&[0u8; FRAGMENT_HEADER_SIZE_BYTES - 1]),
+            "too short to hold a header",
+        );
+    }
+
+    /// Fragments must arrive in order
+    #[test]
+    fn out_of_order_fragment() -> Result<()> {
+        let fragments = encode(Fragmenter::default().fragments(b"0123456789", 3)?);
+        let mut reassembler = Reassembler::default();
+        assert!(reassembler.reassemble(&fragments[0])?.is_none());
+        assert_error(
+            reassembler.reassemble(&fragments[2]),
+            "out of order (expected [1])",
+        );
+        Ok(())
+    }
+
+    /// Fragments of another message cannot interleave with the message being reassembled
+    #[test]
+    fn fragment_of_another_message() -> Result<()> {
+        let mut fragmenter = Fragmenter::default();
+        let first = encode(fragmenter.fragments(b"0123456789", 3)?);
+        let second = encode(fragmenter.fragments(b"abcdefghij", 3)?);
+        let whole = encode(fragmenter.fragments(b"abc", 3)?);
+
+        let mut reassembler = Reassembler::default();
+        assert!(reassembler.reassemble(&first[0])?.is_none());
+        assert_error(
+            reassembler.reassemble(&second[1]),
+            "received fragment of message [1] while reassembling message [0]",
+        );
+        assert_error(
+            reassembler.reassemble(&whole[0]),
+            "received message [2] while another message was being reassembled",
+        );
+        Ok(())
+    }
+
+    /// Fragments cannot carry more bytes than the total len of their message
+    #[test]
+    fn overflowing_total_len() -> Result<()> {
+        let header = |fragment_index| FragmentHeader {
+            message_id: 0,
+            fragment_index,
+            total_len: 4,
+        };
+        let mut reassembler = Reassembler::default();
+        assert!(!reassembler.push(header(0), b"012")?);
+        assert_error(
+            reassembler.push(header(1), b"345"),
+            "fragments of message [0] overflow its total len [4]",
+        );
+        Ok(())
+    }
+
+    /// Messages larger than the max message size are rejected before anything is allocated for them
+    #[test]
+    fn oversized_total_len() -> Result<()> {
+        let fragments = encode(Fragmenter::default().fragments(b"0123456789", 3)?);
+        assert_error(
+            Reassembler::new(8).reassemble(&fragments[0]),
+            "message [0] of len [10] exceeds max message size [8]",
+        );
+
+        let header = FragmentHeader {
+            message_id: 0,
+            fragment_index: 0,
+            total_len: u64::MAX,
+        };
+        assert_error(
+            Reassembler::default().push(header, b"012"),
+            "exceeds max message size",
+        );
+        assert_error(
+            Reassembler::new(usize::MAX).push(header, b"012"),
+            "failed to allocate",
+        );
+        Ok(())
+    }
+
+    /// Reassembly cannot start in the middle of a message
+    #[test]
+    fn missing_first_fragment() -> Result<()> {
+        let fragments = encode(Fragmenter::default().fragments(b"0123456789", 3)?);
+        assert_error(
+            Reassembler::default().reassemble(&fragments[1]),
+            "received fragment [1] of message [0] without its first fragment",
+        );
+        Ok(())
+    }
+}
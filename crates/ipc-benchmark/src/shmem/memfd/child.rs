@@ -0,0 +1,147 @@
+//! Child-specific IPC implementation over `memfd` + `eventfd`
+
+use std::fs::File;
+use std::io::{stdin, BufRead, IoSliceMut};
+use std::os::fd::{AsRawFd as _, FromRawFd as _, OwnedFd, RawFd};
+use std::os::unix::net::UnixStream;
+
+use anyhow::{ensure, Context as _, Result};
+use nix::sys::socket::{recvmsg, ControlMessageOwned, MsgFlags};
+use tracing::debug;
+use uuid::Uuid;
+
+use crate::shmem::memfd::{EventSignal, MemfdHandle, MemfdInit, MemfdInitResponse, NUM_PASSED_FDS};
+use crate::{
+    get_monotonic_time_nanos, monotonic_nanos_since, ChildProcess, PingMessage, PongMessage,
+};
+
+/// A child process that performs IPC via a `memfd` shared region, signaled with `eventfd`s
+#[derive(Debug)]
+pub struct MemfdChild {
+    /// UUID that should uniquely identify this process
+    uuid: Uuid,
+}
+
+impl Default for MemfdChild {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl MemfdChild {
+    /// Build a new [`MemfdChild`] with a random UUID
+    #[must_use]
+    pub fn new() -> Self {
+        Self {
+            uuid: Uuid::now_v7(),
+        }
+    }
+}
+
+/// Receive the file descriptors passed by the parent (see [`NUM_PASSED_FDS`] for ordering)
+fn receive_fds(stream: &UnixStream) -> Result<[OwnedFd; NUM_PASSED_FDS]> {
+    let mut buf = [0u8; 8];
+    let mut iov = [IoSliceMut::new(&mut buf)];
+    let mut cmsg_buf = nix::cmsg_space!([RawFd; NUM_PASSED_FDS]);
+    let msg = recvmsg::<()>(
+        stream.as_raw_fd(),
+        &mut iov,
+        Some(&mut cmsg_buf),
+        MsgFlags::MSG_CMSG_CLOEXEC,
+    )
+    .context("failed to receive file descriptors from parent")?;
+
+    let mut fds = Vec::with_capacity(NUM_PASSED_FDS);
+    for cmsg in msg.cmsgs().context("failed to read control messages")? {
+        if let ControlMessageOwned::ScmRights(raw_fds) = cmsg {
+            // SAFETY: these fds were just received, and are owned by nothing else in this process
+            fds.extend(
+                raw_fds
+                    .into_iter()
+                    .map(|fd| unsafe { OwnedFd::from_raw_fd(fd) }),
+            );
+        }
+    }
+
+    let num_fds = fds.len();
+    fds.try_into().map_err(|_| {
+        anyhow::anyhow!("expected [{NUM_PASSED_FDS}] file descriptors, received [{num_fds}]")
+    })
+}
+
+impl ChildProcess for MemfdChild {
+    fn id(&self) -> String {
+        self.uuid.to_string()
+    }
+
+    fn run(self) -> Result<()> {
+        debug!("child process running");
+
+        debug!("reading memfd init from STDIN");
+        let mut s = String::new();
+        stdin().lock().read_line(&mut s)?;
+
+        // We expect to receive an init message on STDIN
+        let MemfdInit {
+            parent_id,
+            codec,
+            socket_path,
+            slab_size_bytes,
+        } = serde_json::from_slice(s.as_bytes())
+            .context("failed to read init message from STDIN")?;
+
+        debug!(socket_path = %socket_path.display(), "connecting to parent socket");
+        let stream = UnixStream::connect(&socket_path).with_context(|| {
+            format!(
+                "failed to connect to parent socket @ [{}]",
+                socket_path.display()
+            )
+        })?;
+        let [memfd, to_child_fd, to_parent_fd] = receive_fds(&stream)?;
+        debug!("received file descriptors from parent");
+
+        let mut handle = MemfdHandle::new(
+            &File::from(memfd),
+            slab_size_bytes,
+            false,
+            EventSignal::from(to_parent_fd),
+            EventSignal::from(to_child_fd),
+            codec,
+        )?;
+
+        handle
+            .write_message(&MemfdInitResponse {
+                parent_id: parent_id.clone(),
+                child_id: self.id(),
+            })
+            .context("failed to write init response to parent")?;
+        debug!("successfully wrote init response to parent");
+
+        // Enter reading/writing loop
+        debug!("entering read loop...");
+        loop {
+            let PingMessage {
+                sender_id,
+                receiver_id,
+                sent_at_ns,
+                payload,
+                ..
+            } = handle
+                .read_message()
+                .context("failed to read ping message")?;
+            let ping_latency_ns = monotonic_nanos_since(sent_at_ns)?;
+            ensure!(sender_id == parent_id, "sender should be parent");
+            ensure!(receiver_id == self.id(), "receiver should be child");
+
+            handle
+                .write_message(&PongMessage {
+                    sender_id: self.id(),
+                    receiver_id: sender_id,
+                    sent_at_ns: get_monotonic_time_nanos()?,
+                    ping_latency_ns,
+                    payload,
+                })
+                .context("failed to write pong message")?;
+        }
+    }
+}
@@ -0,0 +1,229 @@
+//! Parent-specific IPC implementation over `memfd` + `eventfd`
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{IoSlice, Write};
+use std::os::fd::{AsFd as _, AsRawFd as _};
+use std::os::unix::net::UnixListener;
+use std::process::{Child, Command, Stdio};
+
+use anyhow::{ensure, Context as _, Result};
+use nix::sys::memfd::{memfd_create, MemFdCreateFlag};
+use nix::sys::socket::{sendmsg, ControlMessage, MsgFlags};
+use tracing::debug;
+use uuid::Uuid;
+
+use crate::codec::RpcCodec;
+use crate::latency::OneWayLatencies;
+use crate::shmem::memfd::{
+    EventSignal, MemfdHandle, MemfdInit, MemfdInitResponse,
+    DEFAULT_SHARED_MEM_MEMFD_SLAB_SIZE_BYTES,
+};
+use crate::{
+    get_monotonic_time_nanos, payload_size_bytes_from_env_or_default, synthetic_payload, ChildId,
+    ChildName, ParentProcess, PingMessage, Pinger, PongMessage, RpcPong,
+};
+
+/// Bi-directional channel for communication with a single child
+struct MemfdChannel {
+    /// Self-reported ID of the child
+    child_id: ChildId,
+
+    /// Handle to the shared region
+    handle: MemfdHandle,
+}
+
+/// A parent process that performs IPC via a `memfd` shared region, signaled with `eventfd`s
+pub struct MemfdParent {
+    /// UUID of the parent process
+    uuid: Uuid,
+
+    /// Synthetic payload sent with every ping (see `RPC_PAYLOAD_SIZE_BYTES`)
+    payload: Vec<u8>,
+
+    /// Codec used to encode messages (sent to children in the init message)
+    codec: RpcCodec,
+
+    /// Channels for communicating with children, by child name
+    ///
+    /// SAFETY: We're safe using a `RefCell` here because this structure
+    /// is very much *not* multi-threaded.
+    channels: HashMap<ChildName, RefCell<MemfdChannel>>,
+}
+
+impl std::fmt::Debug for MemfdParent {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("MemfdParent")
+            .field("uuid", &self.uuid)
+            .finish()
+    }
+}
+
+impl Default for MemfdParent {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl MemfdParent {
+    /// Create a new [`MemfdParent`]
+    #[must_use]
+    pub fn new() -> Self {
+        Self {
+            uuid: Uuid::now_v7(),
+            payload: synthetic_payload(payload_size_bytes_from_env_or_default(std::env::vars())),
+            codec: RpcCodec::from_env_or_default(std::env::vars()),
+            channels: HashMap::new(),
+        }
+    }
+}
+
+impl ParentProcess for MemfdParent {
+    fn id(&self) -> String {
+        self.uuid.to_string()
+    }
+
+    fn spawn_child(&mut self, name: impl AsRef<str>, mut cmd: Command) -> Result<Child> {
+        let name = name.as_ref();
+
+        // Create the shared region (one slab per direction) and the signals
+        let slab_size_bytes = std::env::var("SHARED_MEM_MEMFD_SLAB_SIZE_BYTES")
+            .ok()
+            .and_then(|v| v.parse::<usize>().ok())
+            .unwrap_or(DEFAULT_SHARED_MEM_MEMFD_SLAB_SIZE_BYTES);
+        debug!(slab_size_bytes, "creating memfd");
+        let memfd = File::from(
+            memfd_create(c"ipc-benchmark-memfd", MemFdCreateFlag::MFD_CLOEXEC)
+                .context("failed to create memfd")?,
+        );
+        memfd
+            .set_len(
+                u64::try_from(slab_size_bytes * 2)
+                    .context("failed to convert memfd size to u64")?,
+            )
+            .context("failed to set memfd size")?;
+        let to_child_signal = EventSignal::new()?;
+        let to_parent_signal = EventSignal::new()?;
+
+        // Bind a socket in a private temp dir for passing the file descriptors
+        let socket_dir = tempfile::Builder::new()
+            .prefix("ipc-benchmark-memfd-")
+            .tempdir()
+            .context("failed to create temp dir for socket")?;
+        let socket_path = socket_dir.path().join("parent.sock");
+        let listener = UnixListener::bind(&socket_path)
+            .with_context(|| format!("failed to bind unix socket @ [{}]", socket_path.display()))?;
+
+        // Spawn the child
+        debug!("spawning child");
+        let mut child = cmd
+            .stdin(Stdio::piped())
+            .spawn()
+            .context("failed to spawn child process")?;
+
+        // Send the init message over STDIN
+        let init_msg = MemfdInit {
+            parent_id: self.id(),
+            codec: self.codec,
+            socket_path,
+            slab_size_bytes,
+        };
+        debug!(?init_msg, "writing init to child STDIN");
+        let mut child_stdin = child.stdin.take().context("failed to get child STDIN")?;
+        child_stdin
+            .write_all(&serde_json::to_vec(&init_msg).context("failed to serialize init msg")?)
+            .context("failed to write init msg")?;
+        child_stdin
+            .write_all(b"\r\n")
+            .context("failed to write new line")?;
+        child_stdin.flush().context("failed to flush child STDIN")?;
+
+        // Pass the file descriptors to the child once it connects
+        debug!("waiting for child to connect");
+        let (stream, _addr) = listener
+            .accept()
+            .context("failed to accept connection from child")?;
+        let fds = [
+            memfd.as_raw_fd(),
+            to_child_signal.as_fd().as_raw_fd(),
+            to_parent_signal.as_fd().as_raw_fd(),
+        ];
+        debug!(?fds, "sending file descriptors to child");
+        sendmsg::<()>(
+            stream.as_raw_fd(),
+            &[IoSlice::new(b"fds")],
+            &[ControlMessage::ScmRights(&fds)],
+            MsgFlags::empty(),
+            None,
+        )
+        .context("failed to send file descriptors to child")?;
+
+        // Receive the init response via shared memory, confirming that the
+        // child->parent path is working
+        let mut handle = MemfdHandle::new(
+            &memfd,
+            slab_size_bytes,
+            true,
+            to_child_signal,
+            to_parent_signal,
+            self.codec,
+        )?;
+        debug!("waiting for init response from child");
+        let init_resp: MemfdInitResponse = handle
+            .read_message()
+            .context("failed to read init response from child")?;
+        ensure!(
+            init_resp.parent_id == self.id(),
+            "parent ID reported by child did not match"
+        );
+        debug!(child_id = init_resp.child_id, "received init response");
+
+        // Save information to local registry
+        self.channels.insert(
+            name.into(),
+            RefCell::new(MemfdChannel {
+                child_id: init_resp.child_id,
+                handle,
+            }),
+        );
+
+        Ok(child)
+    }
+}
+
+impl Pinger for MemfdParent {
+    fn roundtrip_ping(&self, child_name: impl AsRef<str>) -> Result<Option<OneWayLatencies>> {
+        let child = child_name.as_ref();
+        let mut chan = self
+            .channels
+            .get(child)
+            .with_context(|| format!("failed to find child [{child}]"))?
+            .borrow_mut();
+        let MemfdChannel { child_id, handle } = &mut *chan;
+
+        debug!(child, "writing ping message for child");
+        handle
+            .write_message(&PingMessage {
+                sender_id: self.id(),
+                receiver_id: child_id.clone(),
+                sent_at_ns: get_monotonic_time_nanos()?,
+                payload: self.payload.clone(),
+            })
+            .context("failed to write ping message for child")?;
+
+        debug!(child, "waiting for pong message from child");
+        let pong_msg: PongMessage = handle
+            .read_message()
+            .context("failed to read pong message")?;
+        let latencies = pong_msg.one_way_latencies()?;
+        ensure!(pong_msg.sender_id() == child_id, "child ID matches");
+        ensure!(pong_msg.receiver_id() == self.id(), "parent ID matches");
+        ensure!(
+            pong_msg.payload() == self.payload.as_slice(),
+            "payload matches"
+        );
+
+        Ok(latencies)
+    }
+}
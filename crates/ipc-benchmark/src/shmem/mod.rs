@@ -5,11 +5,29 @@ There are multiple implementations available named mostly after the crates they
 
 - [`shared_mem_queue`][crate-shared-mem-queue]
 - [`raw_sync`][crate-raw-sync]
+- [`memfd`] (Linux only, `memfd` + `eventfd` via [`nix`][crate-nix])
+- [`ring`] (SPSC ring buffers over [`shared_memory`][crate-shared-memory], futex-signaled on Linux)
+- [`signal`] (Linux only, [`shared_memory`][crate-shared-memory] notified with realtime signals via `sigqueue`)
+
+The `raw_sync` and `shared_mem_queue` implementations can also read messages in place, without copying or
+deserializing them (see [`zero_copy`]).
+
+All implementations split messages that don't fit in their shared memory into fragments (see [`framing`]),
+so messages of any size can be sent.
 
 [crate-shared-mem-queue]: https://crates.io/crates/shared-mem-queue
 [crate-raw-sync]: https://crates.io/crates/raw-sync
+[crate-nix]: https://crates.io/crates/nix
+[crate-shared-memory]: https://crates.io/crates/shared_memory
 
 **/
 
+pub mod framing;
+#[cfg(target_os = "linux")]
+pub mod memfd;
 pub mod raw_sync;
+pub mod ring;
 pub mod shared_mem_queue;
+#[cfg(target_os = "linux")]
+pub mod signal;
+pub mod zero_copy;
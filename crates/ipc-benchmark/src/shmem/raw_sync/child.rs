@@ -9,7 +9,11 @@ use uuid::Uuid;
 use crate::shmem::raw_sync::{
     RawSyncInit, RawSyncInitResponse, ShmemHandle, DEFAULT_SHARED_MEM_RAW_SYNC_SLAB_SIZE_BYTES,
 };
-use crate::{get_system_time_millis, ChildProcess, PingMessage, PongMessage};
+use crate::shmem::zero_copy::ShmemReadMode;
+use crate::{
+    get_monotonic_time_nanos, monotonic_nanos_since, ArchivedPingMessage, ChildProcess,
+    PingMessage, PongMessage, RpcPing as _,
+};
 
 /// Parent proceses that uses shared memory as a communication mechanism
 #[derive(Debug)]
@@ -49,7 +53,11 @@ impl ChildProcess for RawSyncChild {
         stdin.read_line(&mut s)?;
 
         // We expect to receive an init message on STDIN
-        let RawSyncInit { write_handle } = serde_json::from_slice(s.as_bytes())
+        let RawSyncInit {
+            write_handle,
+            codec,
+            read_mode,
+        } = serde_json::from_slice(s.as_bytes())
             .context("failed to read init message from STDIN")?;
         let mut write_handle = ShmemHandle::from_serialized(write_handle)?;
         debug!(?write_handle, "received raw sync init");
@@ -68,10 +76,13 @@ impl ChildProcess for RawSyncChild {
         // Write init response message
         debug!("sending init response bytes");
         write_handle
-            .write_message(&RawSyncInitResponse {
-                write_handle: parent_write_handle.to_serialized(),
-                child_id: self.id(),
-            })
+            .write_message(
+                codec,
+                &RawSyncInitResponse {
+                    write_handle: parent_write_handle.to_serialized(),
+                    child_id: self.id(),
+                },
+            )
             .context("failed to write init response from child")?;
 
         // Enter reading/writing loop
@@ -83,21 +94,44 @@ impl ChildProcess for RawSyncChild {
 
             // Read an incoming ping message
             debug!("reading ping response from parent");
-            let PingMessage {
-                sender_id,
-                receiver_id,
-                ..
-            } = parent_write_handle.read_message()?;
-            ensure!(receiver_id == self.id(), "receiver should be child");
+            let (sender_id, ping_latency_ns, payload) = match read_mode {
+                ShmemReadMode::Decode => {
+                    let PingMessage {
+                        sender_id,
+                        receiver_id,
+                        sent_at_ns,
+                        payload,
+                        ..
+                    } = parent_write_handle.read_message(codec)?;
+                    let ping_latency_ns = monotonic_nanos_since(sent_at_ns)?;
+                    ensure!(receiver_id == self.id(), "receiver should be child");
+                    (sender_id, ping_latency_ns, payload)
+                }
+                ShmemReadMode::ZeroCopy => {
+                    let ping = parent_write_handle.access_archived::<ArchivedPingMessage>()?;
+                    let ping_latency_ns = monotonic_nanos_since(ping.sent_at_ns.to_native())?;
+                    ensure!(ping.receiver_id() == self.id(), "receiver should be child");
+                    (
+                        ping.sender_id().to_string(),
+                        ping_latency_ns,
+                        ping.payload.to_vec(),
+                    )
+                }
+            };
 
             // Write message to parent
-            write_handle
-                .write_message(&PongMessage {
-                    sender_id: self.id(),
-                    receiver_id: sender_id,
-                    sent_at_ms: get_system_time_millis()?,
-                })
-                .context("failed to serialize pong message")?;
+            let pong = PongMessage {
+                sender_id: self.id(),
+                receiver_id: sender_id,
+                sent_at_ns: get_monotonic_time_nanos()?,
+                ping_latency_ns,
+                payload,
+            };
+            match read_mode {
+                ShmemReadMode::Decode => write_handle.write_message(codec, &pong),
+                ShmemReadMode::ZeroCopy => write_handle.write_archived(&pong),
+            }
+            .context("failed to serialize pong message")?;
         }
     }
 }
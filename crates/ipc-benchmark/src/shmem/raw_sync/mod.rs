@@ -14,7 +14,13 @@ We choose *not* to use `ipmpsc`[2] here becuase [it doesn't yet reliably support
 use anyhow::{anyhow, ensure, Context as _, Result};
 use raw_sync::events::{BusyEvent, EventImpl, EventInit as _, EventState};
 use raw_sync::Timeout;
-use serde::{Deserialize, Serialize};
+use rkyv::api::high::{HighSerializer, HighValidator};
+use rkyv::bytecheck::CheckBytes;
+use rkyv::rancor;
+use rkyv::ser::allocator::ArenaHandle;
+use rkyv::util::AlignedVec;
+use rkyv::Portable;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
 
 pub mod child;
 pub mod parent;
@@ -24,6 +30,11 @@ pub use parent::RawSyncParent;
 use shared_memory::{Shmem, ShmemConf};
 use tracing::debug;
 
+use crate::codec::{Codec as _, RpcCodec};
+use crate::proto::{schema, ProtoMessage};
+use crate::shmem::framing::{FragmentHeader, Fragmenter, Reassembler, FRAGMENT_HEADER_SIZE_BYTES};
+use crate::shmem::zero_copy::{self, ShmemReadMode};
+
 /// Size of the slab used for shared memory
 const DEFAULT_SHARED_MEM_RAW_SYNC_SLAB_SIZE_BYTES: usize = 128 * 1024;
 
@@ -35,6 +46,12 @@ struct RawSyncInit {
     /// A handle to OS shared memory that must be used by the receiver
     /// (of this `RawSyncInit` message) to write
     write_handle: SerializedShmemHandle,
+
+    /// Codec used to encode messages (after this init message)
+    codec: RpcCodec,
+
+    /// How ping/pong messages are read out of shared memory
+    read_mode: ShmemReadMode,
 }
 
 /// Information returned from a child upon succcessful initialization
@@ -50,6 +67,85 @@ struct RawSyncInitResponse {
     pub(crate) child_id: String,
 }
 
+impl ProtoMessage for RawSyncInit {
+    type Proto = schema::RawSyncInit;
+
+    fn to_proto(&self) -> Result<Self::Proto> {
+        Ok(Self::Proto {
+            write_handle: Some(self.write_handle.to_proto()?),
+            codec: schema::RpcCodec::from(self.codec).into(),
+            read_mode: schema::ShmemReadMode::from(self.read_mode).into(),
+        })
+    }
+
+    fn from_proto(proto: Self::Proto) -> Result<Self> {
+        Ok(Self {
+            write_handle: SerializedShmemHandle::from_proto(
+                proto.write_handle.context("missing write handle")?,
+            )?,
+            codec: schema::RpcCodec::try_from(proto.codec)
+                .map_err(|e| anyhow!("invalid codec: {e}"))?
+                .into(),
+            read_mode: schema::ShmemReadMode::try_from(proto.read_mode)
+                .map_err(|e| anyhow!("invalid read mode: {e}"))?
+                .into(),
+        })
+    }
+}
+
+impl ProtoMessage for RawSyncInitResponse {
+    type Proto = schema::RawSyncInitResponse;
+
+    fn to_proto(&self) -> Result<Self::Proto> {
+        Ok(Self::Proto {
+            write_handle: Some(self.write_handle.to_proto()?),
+            child_id: self.child_id.clone(),
+        })
+    }
+
+    fn from_proto(proto: Self::Proto) -> Result<Self> {
+        Ok(Self {
+            write_handle: SerializedShmemHandle::from_proto(
+                proto.write_handle.context("missing write handle")?,
+            )?,
+            child_id: proto.child_id,
+        })
+    }
+}
+
+/// Offset of the signal set by readers after consuming a fragment (see [`crate::shmem::framing`])
+///
+/// NOTE: the write signal is always at the very start of the region
+const ACK_SIGNAL_OFFSET: usize = 2;
+
+/// Offset of the `u64` LE length prefix of the fragment in the region
+const LEN_PREFIX_OFFSET: usize = 4;
+
+/// Offset of the fragment in the region
+const FRAGMENT_OFFSET: usize = LEN_PREFIX_OFFSET + size_of::<u64>();
+
+/// Build the busy signal located at a given offset in a shared memory region, and set it to clear
+fn busy_signal_at(shmem: &mut Shmem, offset: usize) -> Result<Box<dyn EventImpl>> {
+    let shmem_bytes = unsafe { shmem.as_slice_mut() };
+    let (signal, _size) = unsafe {
+        BusyEvent::new(
+            shmem_bytes
+                .get_mut(offset)
+                .context("shared memory region too small for signal")?,
+            true,
+        )
+        .map_err(|e| anyhow!("failed to build signal for shmem region: {e}"))?
+    };
+    signal
+        .set(EventState::Clear)
+        .map_err(|e| anyhow!("failed to set initial busy signal to clear: {e}"))?;
+    Ok(signal)
+}
+
+/// Handle to a region of shared memory, with busy signals for writes (and acknowledgements) at the start of the region
+///
+/// Messages are split into fragments (see [`crate::shmem::framing`]) that fit in the region,
+/// and only one fragment is held in the region at a time.
 // TODO: Introduce a Handle type for raw sync stuff
 pub(crate) struct ShmemHandle {
     /// Size of shared memory region in bytes
@@ -62,6 +158,15 @@ pub(crate) struct ShmemHandle {
     ///
     /// NOTE: signals are *always* located in the first couple bytes of a shared memory region, for simplicity
     pub(crate) write_signal: Box<dyn EventImpl>,
+
+    /// Signal set by the reader after it has consumed a fragment that is not the last of its message
+    ack_signal: Box<dyn EventImpl>,
+
+    /// Splits messages written to the region into fragments
+    fragmenter: Fragmenter,
+
+    /// Reassembles messages read from the region out of fragments
+    reassembler: Reassembler,
 }
 
 impl std::fmt::Debug for ShmemHandle {
@@ -76,27 +181,28 @@ impl std::fmt::Debug for ShmemHandle {
 impl ShmemHandle {
     /// Create a shared memory, given a certain size, with signaling built in
     pub(crate) fn new(size_bytes: usize) -> Result<Self> {
+        ensure!(
+            size_bytes > FRAGMENT_OFFSET + FRAGMENT_HEADER_SIZE_BYTES,
+            "shared memory size [{size_bytes}] is too small to hold any message"
+        );
         // Create a shmem configuration that the child will use to write to
         let shmem_conf = ShmemConf::new().size(size_bytes);
         let mut shmem = shmem_conf
             .clone()
             .create()
             .context("failed to create shared memory")?;
-        let shmem_bytes = unsafe { shmem.as_slice_mut() };
         // Use the first two bytes as a busy signaling area for the parent
         // The parent sets and child reads to know when messages are ready
-        let (write_signal, _size) = unsafe {
-            BusyEvent::new(shmem_bytes.get_mut(0).unwrap(), true)
-                .map_err(|e| anyhow!("failed to build signal for parent shmem region: {e}"))?
-        };
-        write_signal
-            .set(EventState::Clear)
-            .map_err(|e| anyhow!("failed to set initial busy signal to clear: {e}"))?;
+        let write_signal = busy_signal_at(&mut shmem, 0)?;
+        let ack_signal = busy_signal_at(&mut shmem, ACK_SIGNAL_OFFSET)?;
 
         Ok(Self {
             size_bytes,
             shmem: (shmem_conf, shmem),
             write_signal,
+            ack_signal,
+            fragmenter: Fragmenter::default(),
+            reassembler: Reassembler::from_env_or_default(std::env::vars()),
         })
     }
 
@@ -114,22 +220,19 @@ impl ShmemHandle {
             .open()
             .with_context(|| format!("failed to open shared memory with OS ID [{os_id}]"))?;
 
-        // Rebuild the signal
-        let shmem_bytes = unsafe { shmem.as_slice_mut() };
+        // Rebuild the signals
         // Use the first two bytes as a busy signaling area for the parent
         // The parent sets and child reads to know when messages are ready
-        let (signal, _size) = unsafe {
-            BusyEvent::new(shmem_bytes.get_mut(0).unwrap(), true)
-                .map_err(|e| anyhow!("failed to build signal for parent shmem region: {e}"))?
-        };
-        signal
-            .set(EventState::Clear)
-            .map_err(|e| anyhow!("failed to set initial busy signal to clear: {e}"))?;
+        let write_signal = busy_signal_at(&mut shmem, 0)?;
+        let ack_signal = busy_signal_at(&mut shmem, ACK_SIGNAL_OFFSET)?;
 
         Ok(Self {
             size_bytes,
             shmem: (shmem_conf, shmem),
-            write_signal: signal,
+            write_signal,
+            ack_signal,
+            fragmenter: Fragmenter::default(),
+            reassembler: Reassembler::from_env_or_default(std::env::vars()),
         })
     }
 
@@ -174,66 +277,141 @@ impl ShmemHandle {
         }
     }
 
-    /// Read a single message from the write region
+    /// Read a single message (encoded with the given codec) from the write region
+    fn read_message<T: DeserializeOwned + ProtoMessage>(&mut self, codec: RpcCodec) -> Result<T> {
+        codec.decode(self.message_bytes()?)
+    }
+
+    /// Access a single message (archived with `rkyv`) in place in the write region,
+    /// without copying or deserializing it
     ///
-    /// NOTE: messages are assumed to be LE length-prefixed, and the
-    /// length-prefix should start *after* those initial 2 bytes (e.g. `bytes[2..10]`)
-    fn read_message<'a, T: Deserialize<'a>>(&'a mut self) -> Result<T> {
-        let bytes = unsafe { self.shmem.1.as_slice_mut() };
-        debug!("reading init response from child");
-        let message_len = u64::from_le_bytes(
-            bytes[2..10]
+    /// NOTE: the message must not be accessed after the other side has been signaled to write again.
+    /// Messages that span multiple fragments are accessed in the buffer they were reassembled in.
+    fn access_archived<T>(&mut self) -> Result<&T>
+    where
+        T: Portable + for<'a> CheckBytes<HighValidator<'a, rancor::Error>>,
+    {
+        zero_copy::access(self.message_bytes()?)
+    }
+
+    /// Retrieve the bytes of the message currently in the write region
+    ///
+    /// The first fragment of the message must already have been signaled (see [`Self::wait_for_write_signal`]),
+    /// while the rest of the fragments (if any) are acknowledged & waited for here.
+    fn message_bytes(&mut self) -> Result<&[u8]> {
+        let in_place = loop {
+            let (header, chunk) = FragmentHeader::split(Self::fragment_bytes(&self.shmem.1)?)?;
+            if self.reassembler.is_whole_message(&header, chunk)? {
+                break true;
+            }
+            if self.reassembler.push(header, chunk)? {
+                break false;
+            }
+            // Let the writer know that it can overwrite the region with the next fragment
+            self.ack_signal
+                .set(EventState::Signaled)
+                .map_err(|e| anyhow!("failed to set ack signal: {e}"))?;
+            self.wait_for_write_signal()?;
+        };
+        if in_place {
+            Ok(FragmentHeader::split(Self::fragment_bytes(&self.shmem.1)?)?.1)
+        } else {
+            Ok(self.reassembler.message())
+        }
+    }
+
+    /// Retrieve the bytes of the fragment currently in the write region
+    ///
+    /// NOTE: fragments are assumed to be LE length-prefixed, and the
+    /// length-prefix should start *after* both signals (e.g. `bytes[4..12]`)
+    fn fragment_bytes(shmem: &Shmem) -> Result<&[u8]> {
+        let bytes = unsafe { shmem.as_slice() };
+        debug!("reading fragment from shared memory");
+        let fragment_len = usize::try_from(u64::from_le_bytes(
+            bytes[LEN_PREFIX_OFFSET..FRAGMENT_OFFSET]
                 .try_into()
                 .context("unexpectedly invalid byte range for LE u64")?,
+        ))
+        .context("failed to convert u64 len into usize")?;
+        ensure!(
+            fragment_len <= bytes.len() - FRAGMENT_OFFSET,
+            "invalid length header [{fragment_len}], fragment would overflow available space",
         );
-        assert!(
-            (message_len as usize) < (bytes.len() - 2),
-            "invalid length headder, message must overflow available space",
-        );
-        let msg_bytes = &bytes[10..message_len as usize + 10];
-        debug!(message_len, "read init response");
-        serde_json::from_slice(msg_bytes).context("failed to parse init response JSON")
+        debug!(fragment_len, "read fragment length");
+        Ok(&bytes[FRAGMENT_OFFSET..FRAGMENT_OFFSET + fragment_len])
     }
 
-    /// Get the max message size (not including the `usize`'d length prefix)
+    /// Get the max fragment size (not including the `u64` length prefix)
     #[must_use]
-    fn max_msg_size(&self) -> usize {
-        self.size_bytes - 2 - size_of::<usize>()
+    fn max_fragment_size(&self) -> usize {
+        self.size_bytes - FRAGMENT_OFFSET
+    }
+
+    /// Write a single message (encoded with the given codec) to the write region
+    fn write_message<T: Serialize + ProtoMessage>(
+        &mut self,
+        codec: RpcCodec,
+        obj: &T,
+    ) -> Result<usize> {
+        let msg_bytes = codec.encode(obj)?;
+        self.write_bytes(&msg_bytes)
+    }
+
+    /// Write a single message (archived with `rkyv`) to the write region
+    fn write_archived<T>(&mut self, obj: &T) -> Result<usize>
+    where
+        T: for<'a> rkyv::Serialize<HighSerializer<AlignedVec, ArenaHandle<'a>, rancor::Error>>,
+    {
+        let msg_bytes = zero_copy::archive(obj)?;
+        self.write_bytes(&msg_bytes)
+    }
+
+    /// Write the bytes of a single message to the write region, one fragment at a time
+    fn write_bytes(&mut self, msg_bytes: &[u8]) -> Result<usize> {
+        let max_chunk_size = self.max_fragment_size() - FRAGMENT_HEADER_SIZE_BYTES;
+        for (header, chunk) in self.fragmenter.fragments(msg_bytes, max_chunk_size)? {
+            if header.fragment_index > 0 {
+                // Wait for the reader to consume the previous fragment
+                debug!(
+                    fragment_index = header.fragment_index,
+                    "waiting for ack of previous fragment"
+                );
+                self.ack_signal
+                    .wait(Timeout::Infinite)
+                    .map_err(|e| anyhow!("failed to wait for ack signal: {e}"))?;
+            }
+            self.write_fragment(header, chunk)?;
+        }
+        Ok(msg_bytes.len())
     }
 
-    /// Write a single message to the write region
-    fn write_message<T: Serialize>(&mut self, obj: T) -> Result<usize> {
+    /// Write a single fragment to the write region, and signal the reader
+    fn write_fragment(&mut self, header: FragmentHeader, chunk: &[u8]) -> Result<()> {
         // Clear the write-finished signal
         self.write_signal
             .set(EventState::Clear)
             .map_err(|e| anyhow!("failed to set parent write signal: {e}"))?;
 
-        let max_msg_size = self.max_msg_size();
         let bytes = unsafe { self.shmem.1.as_slice_mut() };
-        let msg_bytes = serde_json::to_vec(&obj).context("failed to serialize ping message")?;
-        let msg_len = msg_bytes.len();
-
-        ensure!(
-            msg_len <= max_msg_size,
-            "serialized message of len [{msg_len}] is greater than max message size [{}]",
-            self.max_msg_size()
-        );
+        let fragment_len = FRAGMENT_HEADER_SIZE_BYTES + chunk.len();
+        let chunk_start = FRAGMENT_OFFSET + FRAGMENT_HEADER_SIZE_BYTES;
 
         // Write out the length prefix
-        bytes[2..10].copy_from_slice(
-            &u64::try_from(msg_bytes.len())
-                .context("failed to convert msg len to u64")?
+        bytes[LEN_PREFIX_OFFSET..FRAGMENT_OFFSET].copy_from_slice(
+            &u64::try_from(fragment_len)
+                .context("failed to convert fragment len to u64")?
                 .to_le_bytes(),
         );
-        // Write out the message bytes
-        bytes[10..msg_bytes.len() + 10].copy_from_slice(&msg_bytes);
+        // Write out the fragment header & message bytes
+        bytes[FRAGMENT_OFFSET..chunk_start].copy_from_slice(&header.to_bytes());
+        bytes[chunk_start..chunk_start + chunk.len()].copy_from_slice(chunk);
 
         // Trigger the write-finished signal
         self.write_signal
             .set(EventState::Signaled)
             .map_err(|e| anyhow!("failed to set parent write signal: {e}"))?;
 
-        Ok(msg_bytes.len())
+        Ok(())
     }
 }
 
@@ -249,6 +427,24 @@ pub(crate) struct SerializedShmemHandle {
     size_bytes: usize,
 }
 
+impl ProtoMessage for SerializedShmemHandle {
+    type Proto = schema::SerializedShmemHandle;
+
+    fn to_proto(&self) -> Result<Self::Proto> {
+        Ok(Self::Proto {
+            os_id: self.os_id.clone(),
+            size_bytes: u64::try_from(self.size_bytes)?,
+        })
+    }
+
+    fn from_proto(proto: Self::Proto) -> Result<Self> {
+        Ok(Self {
+            os_id: proto.os_id,
+            size_bytes: usize::try_from(proto.size_bytes)?,
+        })
+    }
+}
+
 impl Serialize for ShmemHandle {
     fn serialize<S>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error>
     where
@@ -9,10 +9,16 @@ use anyhow::{anyhow, Context as _, Result};
 use tracing::debug;
 use uuid::Uuid;
 
+use crate::codec::RpcCodec;
+use crate::latency::OneWayLatencies;
 use crate::shmem::raw_sync::{
     RawSyncInit, RawSyncInitResponse, ShmemHandle, DEFAULT_SHARED_MEM_RAW_SYNC_SLAB_SIZE_BYTES,
 };
-use crate::{get_system_time_millis, ParentProcess, PingMessage, Pinger, PongMessage};
+use crate::shmem::zero_copy::ShmemReadMode;
+use crate::{
+    get_monotonic_time_nanos, payload_size_bytes_from_env_or_default, synthetic_payload,
+    ArchivedPongMessage, ParentProcess, PingMessage, Pinger, PongMessage, RpcPong as _,
+};
 
 /// ID of a child process that this parent will communicate with
 type ChildId = String;
@@ -36,6 +42,15 @@ pub struct RawSyncParent {
     /// UUID of this shared memory parent
     uuid: Uuid,
 
+    /// Synthetic payload sent with every ping (see `RPC_PAYLOAD_SIZE_BYTES`)
+    payload: Vec<u8>,
+
+    /// Codec used to encode messages (sent to children in the init message)
+    codec: RpcCodec,
+
+    /// How ping/pong messages are read out of shared memory (sent to children in the init message)
+    read_mode: ShmemReadMode,
+
     /// Children processes connected to this parent
     children: RwLock<HashMap<ChildId, SharedMemoryInfo>>,
 }
@@ -47,6 +62,9 @@ impl RawSyncParent {
     pub fn new() -> Self {
         RawSyncParent {
             uuid: Uuid::now_v7(),
+            payload: synthetic_payload(payload_size_bytes_from_env_or_default(std::env::vars())),
+            codec: RpcCodec::from_env_or_default(std::env::vars()),
+            read_mode: ShmemReadMode::from_env_or_default(std::env::vars()),
             children: RwLock::new(HashMap::new()),
         }
     }
@@ -90,6 +108,8 @@ impl ParentProcess for RawSyncParent {
         // Create and send initialization message to the child over STDIN
         let init_msg = RawSyncInit {
             write_handle: child_write_handle.to_serialized(),
+            codec: self.codec,
+            read_mode: self.read_mode,
         };
         debug!(init_msg = ?init_msg, "writing init to child STDIN");
         let mut child_stdin = child.stdin.take().context("failed to get child STDIN")?;
@@ -110,7 +130,7 @@ impl ParentProcess for RawSyncParent {
         let RawSyncInitResponse {
             write_handle,
             child_id,
-        } = child_write_handle.read_message()?;
+        } = child_write_handle.read_message(self.codec)?;
         let parent_write_handle = ShmemHandle::from_serialized(write_handle)
             .context("failed to build parent handle from child init response")?;
         debug!(
@@ -137,7 +157,10 @@ impl ParentProcess for RawSyncParent {
 }
 
 impl Pinger for RawSyncParent {
-    fn roundtrip_ping(&self, child_name: impl AsRef<str>) -> anyhow::Result<()> {
+    fn roundtrip_ping(
+        &self,
+        child_name: impl AsRef<str>,
+    ) -> anyhow::Result<Option<OneWayLatencies>> {
         let child = child_name.as_ref();
         debug!(child = child, "retrieving channel for child");
 
@@ -156,11 +179,16 @@ impl Pinger for RawSyncParent {
 
         // Signal writing as busy
         debug!("signaling to start ping write");
-        parent_write_handle.write_message(&PingMessage {
+        let ping = PingMessage {
             sender_id: self.id(),
             receiver_id: child_id.clone(),
-            sent_at_ms: get_system_time_millis()?,
-        })?;
+            sent_at_ns: get_monotonic_time_nanos()?,
+            payload: self.payload.clone(),
+        };
+        match self.read_mode {
+            ShmemReadMode::Decode => parent_write_handle.write_message(self.codec, &ping)?,
+            ShmemReadMode::ZeroCopy => parent_write_handle.write_archived(&ping)?,
+        };
 
         // Wait until child ready
         debug!("waiting for child to signal incoming message");
@@ -168,15 +196,33 @@ impl Pinger for RawSyncParent {
 
         // Read child message
         debug!("reading pong");
-        let PongMessage {
-            sender_id,
-            receiver_id,
-            ..
-        } = child_write_handle.read_message()?;
+        let latencies = match self.read_mode {
+            ShmemReadMode::Decode => {
+                let PongMessage {
+                    sender_id,
+                    receiver_id,
+                    sent_at_ns,
+                    ping_latency_ns,
+                    payload,
+                    ..
+                } = child_write_handle.read_message(self.codec)?;
+                let latencies = OneWayLatencies::measure(ping_latency_ns, sent_at_ns)?;
+                assert!(&sender_id == child_id);
+                assert!(receiver_id == self.id());
+                assert!(payload == self.payload);
+                Some(latencies)
+            }
+            ShmemReadMode::ZeroCopy => {
+                let pong = child_write_handle.access_archived::<ArchivedPongMessage>()?;
+                let latencies = pong.one_way_latencies()?;
+                assert!(pong.sender_id() == child_id);
+                assert!(pong.receiver_id() == self.id());
+                assert!(pong.payload.as_slice() == self.payload.as_slice());
+                latencies
+            }
+        };
         debug!("successfully read pong");
-        assert!(&sender_id == child_id);
-        assert!(receiver_id == self.id());
 
-        Ok(())
+        Ok(latencies)
     }
 }
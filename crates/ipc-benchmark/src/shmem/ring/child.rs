@@ -0,0 +1,107 @@
+//! Child-specific IPC implementation over shared memory SPSC ring buffers
+
+use std::io::{stdin, BufRead};
+
+use anyhow::{ensure, Context as _, Result};
+use tracing::debug;
+use uuid::Uuid;
+
+use crate::shmem::ring::{RingInit, RingInitResponse, ShmemRing};
+use crate::{
+    get_monotonic_time_nanos, monotonic_nanos_since, ChildProcess, PingMessage, PongMessage,
+};
+
+/// A child process that performs IPC via SPSC ring buffers in shared memory
+#[derive(Debug)]
+pub struct RingChild {
+    /// UUID that should uniquely identify this process
+    uuid: Uuid,
+}
+
+impl Default for RingChild {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl RingChild {
+    /// Build a new [`RingChild`] with a random UUID
+    #[must_use]
+    pub fn new() -> Self {
+        Self {
+            uuid: Uuid::now_v7(),
+        }
+    }
+}
+
+impl ChildProcess for RingChild {
+    fn id(&self) -> String {
+        self.uuid.to_string()
+    }
+
+    /// Execute as the running process.
+    ///
+    /// This command is expected to never return, as child processes
+    /// should handle messages forever.
+    fn run(self) -> Result<()> {
+        debug!("child process running");
+
+        debug!("reading ring init from STDIN");
+        let mut s = String::new();
+        stdin().lock().read_line(&mut s)?;
+
+        // We expect to receive an init message on STDIN
+        let RingInit {
+            parent_id,
+            codec,
+            to_child,
+            from_child,
+        } = serde_json::from_slice(s.as_bytes())
+            .context("failed to read init message from STDIN")?;
+
+        // NOTE: from the child's perspective, the parent's `to_child` ring is the one we read from
+        debug!("opening rings");
+        let mut from_parent = ShmemRing::from_serialized(to_child, codec)
+            .context("failed to open ring from parent")?;
+        let mut to_parent = ShmemRing::from_serialized(from_child, codec)
+            .context("failed to open ring to parent")?;
+
+        to_parent
+            .write_message(&RingInitResponse {
+                parent_id: parent_id.clone(),
+                child_id: self.id(),
+            })
+            .context("failed to write init response to parent")?;
+        debug!("successfully wrote init response to parent");
+
+        // Enter reading/writing loop
+        debug!("entering read loop...");
+        loop {
+            debug!("attempting to read ping");
+            let PingMessage {
+                sender_id,
+                receiver_id,
+                sent_at_ns,
+                payload,
+                ..
+            } = from_parent
+                .read_message()
+                .context("failed to read ping message")?;
+            let ping_latency_ns = monotonic_nanos_since(sent_at_ns)?;
+            ensure!(sender_id == parent_id, "sender should be parent");
+            ensure!(receiver_id == self.id(), "receiver should be child");
+            debug!(parent_id, "successfully received ping from parent");
+
+            // Build & write pong back to the parent
+            to_parent
+                .write_message(&PongMessage {
+                    sender_id: self.id(),
+                    receiver_id: parent_id.clone(),
+                    sent_at_ns: get_monotonic_time_nanos()?,
+                    ping_latency_ns,
+                    payload,
+                })
+                .context("failed to send pong to parent")?;
+        }
+    }
+}
@@ -0,0 +1,457 @@
+/*!
+Process IPC over a lock-free single-producer/single-consumer (SPSC) ring buffer in shared memory
+(via [`shared_memory`][crate-shared-memory]).
+
+Each direction (parent -> child, child -> parent) gets its own ring, which lives in a separate
+shared memory segment:
+
+```text
++------------------------------------------------+----------------------------------+
+| RingHeader (head, tail, futex words, capacity) | data (capacity bytes, wrapping)  |
++------------------------------------------------+----------------------------------+
+```
+
+Messages are split into fragments (see [`crate::shmem::framing`]) that are written as `u64` LE length-prefixed
+frames (as with [`crate::shmem::raw_sync`] and [`crate::shmem::shared_mem_queue`]), and may wrap around the end
+of the data region. Unlike `raw_sync`, any number of frames can be queued, as long as they fit in the ring.
+
+When the ring is empty (for the consumer) or full (for the producer), the waiting side sleeps
+on a [futex][man-futex] rather than spinning, and is woken by the other side. On platforms
+without futexes, waiting falls back to yielding the thread.
+
+[crate-shared-memory]: <https://crates.io/crates/shared_memory>
+[man-futex]: <https://man7.org/linux/man-pages/man2/futex.2.html>
+**/
+
+use std::sync::atomic::{AtomicU32, AtomicU64, Ordering};
+
+use anyhow::{anyhow, ensure, Context as _, Result};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use shared_memory::{Shmem, ShmemConf};
+use tracing::debug;
+
+use crate::codec::{Codec as _, RpcCodec};
+use crate::proto::{impl_init_response_proto, ProtoMessage};
+use crate::shmem::framing::{FragmentHeader, Fragmenter, Reassembler, FRAGMENT_HEADER_SIZE_BYTES};
+
+pub mod child;
+pub mod parent;
+
+pub use child::RingChild;
+pub use parent::RingParent;
+
+/// Default capacity (in bytes) of the data region of each ring
+const DEFAULT_SHARED_MEM_RING_CAPACITY_BYTES: usize = 1024 * 1024;
+
+/// Size of the length prefix that precedes every frame
+const LEN_PREFIX_SIZE_BYTES: usize = size_of::<u64>();
+
+/// Message sent to child processes over STDIN that contains
+/// information necessary for the child to open both rings
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+struct RingInit {
+    /// UUID of the parent
+    parent_id: String,
+
+    /// Codec used to encode messages (after this init message)
+    codec: RpcCodec,
+
+    /// Ring that the parent writes to (and the child reads from)
+    to_child: SerializedShmemRing,
+
+    /// Ring that the child writes to (and the parent reads from)
+    from_child: SerializedShmemRing,
+}
+
+/// Message sent from the child process (via the ring) upon successful initialization
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
+struct RingInitResponse {
+    /// UUID of the parent
+    parent_id: String,
+
+    /// UUID of the child
+    child_id: String,
+}
+
+impl_init_response_proto!(RingInitResponse);
+
+/// Proxy that enables sending a [`ShmemRing`] to another process
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+struct SerializedShmemRing {
+    /// ID for the shared memory segment
+    os_id: String,
+
+    /// Capacity of the data region in bytes
+    capacity_bytes: usize,
+}
+
+/// Wrapper that places a value on its own cache line, to avoid false sharing
+/// between the producer and the consumer
+#[repr(C, align(64))]
+struct CachePadded<T>(T);
+
+/// Header that sits at the start of every ring's shared memory segment
+///
+/// NOTE: `head` and `tail` are *total* byte counts which only ever increase, and are
+/// reduced modulo the capacity to find positions in the data region.
+#[repr(C)]
+struct RingHeader {
+    /// Total number of bytes written (only modified by the producer)
+    head: CachePadded<AtomicU64>,
+
+    /// Total number of bytes read (only modified by the consumer)
+    tail: CachePadded<AtomicU64>,
+
+    /// Futex word that is bumped by the producer after every write
+    ///
+    /// The consumer sleeps on this while the ring is empty.
+    data_seq: CachePadded<AtomicU32>,
+
+    /// Futex word that is bumped by the consumer after every read
+    ///
+    /// The producer sleeps on this while the ring is full.
+    space_seq: CachePadded<AtomicU32>,
+
+    /// Whether the consumer is (about to be) asleep, so the producer can skip waking it when it isn't
+    consumer_waiting: AtomicU32,
+
+    /// Whether the producer is (about to be) asleep, so the consumer can skip waking it when it isn't
+    producer_waiting: AtomicU32,
+
+    /// Capacity of the data region in bytes (written once by the creator)
+    capacity_bytes: AtomicU64,
+}
+
+/// Size of the [`RingHeader`], i.e. the offset at which the data region starts
+const RING_HEADER_SIZE_BYTES: usize = size_of::<RingHeader>();
+
+/// Block until the value at `word` is no longer `expected` (or a spurious wakeup occurs)
+#[cfg(target_os = "linux")]
+fn futex_wait(word: &AtomicU32, expected: u32) {
+    // SAFETY: the futex word lives in shared memory that stays mapped for the duration of the call
+    //
+    // NOTE: FUTEX_PRIVATE_FLAG must *not* be used, as the word is shared between processes.
+    // Errors (ex. EAGAIN when the value already changed, EINTR) are fine to ignore, as callers
+    // always re-check the ring state after waking.
+    unsafe {
+        libc::syscall(
+            libc::SYS_futex,
+            word.as_ptr(),
+            libc::FUTEX_WAIT,
+            expected,
+            std::ptr::null::<libc::timespec>(),
+        );
+    }
+}
+
+/// Wake a single waiter sleeping on `word`
+#[cfg(target_os = "linux")]
+fn futex_wake(word: &AtomicU32) {
+    // SAFETY: the futex word lives in shared memory that stays mapped for the duration of the call
+    unsafe {
+        libc::syscall(libc::SYS_futex, word.as_ptr(), libc::FUTEX_WAKE, 1);
+    }
+}
+
+/// Wait for the value at `word` to change (no futexes available, so we yield instead)
+#[cfg(not(target_os = "linux"))]
+fn futex_wait(word: &AtomicU32, expected: u32) {
+    if word.load(Ordering::SeqCst) == expected {
+        std::thread::yield_now();
+    }
+}
+
+/// Wake waiters on `word` (no-op, as waiters are yielding rather than sleeping)
+#[cfg(not(target_os = "linux"))]
+fn futex_wake(_word: &AtomicU32) {}
+
+/// One direction of communication: a SPSC ring buffer in a shared memory segment
+///
+/// Any given process should only ever write to *or* read from a given ring, never both.
+struct ShmemRing {
+    /// Shared memory segment (header + data region)
+    shmem: Shmem,
+
+    /// Capacity of the data region in bytes
+    capacity_bytes: usize,
+
+    /// Scratch buffer used for serialization, and for reading frames that wrap around
+    buf: Vec<u8>,
+
+    /// Codec used to encode messages
+    codec: RpcCodec,
+
+    /// Splits messages written to the ring into fragments
+    fragmenter: Fragmenter,
+
+    /// Reassembles messages read from the ring out of fragments
+    reassembler: Reassembler,
+}
+
+impl std::fmt::Debug for ShmemRing {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ShmemRing")
+            .field("os_id", &self.shmem.get_os_id())
+            .field("capacity_bytes", &self.capacity_bytes)
+            .field("codec", &self.codec)
+            .finish()
+    }
+}
+
+impl ShmemRing {
+    /// Create a new (empty) ring in a fresh shared memory segment
+    fn new(capacity_bytes: usize, codec: RpcCodec) -> Result<Self> {
+        ensure!(
+            capacity_bytes > LEN_PREFIX_SIZE_BYTES + FRAGMENT_HEADER_SIZE_BYTES,
+            "ring capacity [{capacity_bytes}] is too small to hold any message"
+        );
+        let shmem = ShmemConf::new()
+            .size(RING_HEADER_SIZE_BYTES + capacity_bytes)
+            .create()
+            .context("failed to create shared memory for ring")?;
+
+        // SAFETY: the segment was just created (and is large enough), and nobody else has access yet
+        unsafe {
+            shmem.as_ptr().cast::<RingHeader>().write(RingHeader {
+                head: CachePadded(AtomicU64::new(0)),
+                tail: CachePadded(AtomicU64::new(0)),
+                data_seq: CachePadded(AtomicU32::new(0)),
+                space_seq: CachePadded(AtomicU32::new(0)),
+                consumer_waiting: AtomicU32::new(0),
+                producer_waiting: AtomicU32::new(0),
+                capacity_bytes: AtomicU64::new(
+                    u64::try_from(capacity_bytes).context("failed to convert capacity to u64")?,
+                ),
+            });
+        }
+
+        Ok(Self {
+            shmem,
+            capacity_bytes,
+            buf: Vec::new(),
+            codec,
+            fragmenter: Fragmenter::default(),
+            reassembler: Reassembler::from_env_or_default(std::env::vars()),
+        })
+    }
+
+    /// Open an existing ring (created by another process) from its serialized form
+    fn from_serialized(
+        SerializedShmemRing {
+            os_id,
+            capacity_bytes,
+        }: SerializedShmemRing,
+        codec: RpcCodec,
+    ) -> Result<Self> {
+        let shmem = ShmemConf::new()
+            .os_id(&os_id)
+            .open()
+            .with_context(|| format!("failed to open shared memory with OS ID [{os_id}]"))?;
+        ensure!(
+            shmem.len() >= RING_HEADER_SIZE_BYTES + capacity_bytes,
+            "shared memory [{os_id}] is too small for a ring of capacity [{capacity_bytes}]"
+        );
+        let ring = Self {
+            shmem,
+            capacity_bytes,
+            buf: Vec::new(),
+            codec,
+            fragmenter: Fragmenter::default(),
+            reassembler: Reassembler::from_env_or_default(std::env::vars()),
+        };
+        let header_capacity = ring.header().capacity_bytes.load(Ordering::SeqCst);
+        ensure!(
+            u64::try_from(capacity_bytes).ok() == Some(header_capacity),
+            "ring header capacity [{header_capacity}] does not match expected capacity [{capacity_bytes}]"
+        );
+        Ok(ring)
+    }
+
+    /// Create a serialized version of the [`ShmemRing`] to send to another process
+    fn to_serialized(&self) -> SerializedShmemRing {
+        SerializedShmemRing {
+            os_id: self.shmem.get_os_id().to_string(),
+            capacity_bytes: self.capacity_bytes,
+        }
+    }
+
+    /// Access the header at the start of the shared memory segment
+    fn header(&self) -> &RingHeader {
+        // SAFETY: the header was initialized by the creator, is only accessed via atomics,
+        // and the segment outlives the returned reference
+        unsafe { &*self.shmem.as_ptr().cast::<RingHeader>() }
+    }
+
+    /// Position in the data region for a given (total) byte count
+    fn position(&self, count: u64) -> usize {
+        // NOTE: the remainder is always less than capacity, which is a usize
+        (count % self.capacity_bytes as u64) as usize
+    }
+
+    /// Copy bytes into the data region starting at the given (total) byte count, wrapping if necessary
+    ///
+    /// SAFETY: callers must ensure the target bytes are free (i.e. not yet readable by the consumer)
+    unsafe fn copy_in(&self, at: u64, bytes: &[u8]) {
+        let data = self.shmem.as_ptr().add(RING_HEADER_SIZE_BYTES);
+        let pos = self.position(at);
+        let first = bytes.len().min(self.capacity_bytes - pos);
+        std::ptr::copy_nonoverlapping(bytes.as_ptr(), data.add(pos), first);
+        std::ptr::copy_nonoverlapping(bytes.as_ptr().add(first), data, bytes.len() - first);
+    }
+
+    /// Copy bytes out of the data region starting at the given (total) byte count, wrapping if necessary
+    ///
+    /// SAFETY: callers must ensure the source bytes have been fully written by the producer
+    unsafe fn copy_out(&self, at: u64, out: &mut [u8]) {
+        let data = self.shmem.as_ptr().add(RING_HEADER_SIZE_BYTES);
+        let pos = self.position(at);
+        let first = out.len().min(self.capacity_bytes - pos);
+        std::ptr::copy_nonoverlapping(data.add(pos), out.as_mut_ptr(), first);
+        std::ptr::copy_nonoverlapping(data, out.as_mut_ptr().add(first), out.len() - first);
+    }
+
+    /// Write a single message into the ring (one fragment per frame), blocking while there is not enough space
+    fn write_message<T: Serialize + ProtoMessage>(&mut self, obj: &T) -> Result<usize> {
+        let mut buf = std::mem::take(&mut self.buf);
+        buf.clear();
+        self.codec.encode_into(obj, &mut buf)?;
+
+        // NOTE: the buffer is put back before checking the result, so that it can be reused
+        let max_chunk_size =
+            self.capacity_bytes - LEN_PREFIX_SIZE_BYTES - FRAGMENT_HEADER_SIZE_BYTES;
+        let result = self
+            .fragmenter
+            .fragments(&buf, max_chunk_size)
+            .and_then(|mut fragments| {
+                fragments.try_for_each(|(header, chunk)| self.write_frame(header, chunk))
+            });
+        let msg_len = buf.len();
+        self.buf = buf;
+        result.map(|()| msg_len)
+    }
+
+    /// Write a single fragment into the ring as a length-prefixed frame, blocking while there is not enough space
+    fn write_frame(&self, header: FragmentHeader, chunk: &[u8]) -> Result<()> {
+        let fragment_len = FRAGMENT_HEADER_SIZE_BYTES + chunk.len();
+        let frame_len = u64::try_from(LEN_PREFIX_SIZE_BYTES + fragment_len)
+            .context("failed to convert frame len to u64")?;
+        let capacity = u64::try_from(self.capacity_bytes).context("failed to convert capacity")?;
+
+        let header_bytes = header.to_bytes();
+        let header = self.header();
+        let head = header.head.0.load(Ordering::Relaxed);
+
+        // Wait until there is enough free space for the whole frame
+        loop {
+            let space_seq = header.space_seq.0.load(Ordering::SeqCst);
+            if capacity - (head - header.tail.0.load(Ordering::SeqCst)) >= frame_len {
+                break;
+            }
+            header.producer_waiting.store(1, Ordering::SeqCst);
+            if capacity - (head - header.tail.0.load(Ordering::SeqCst)) < frame_len {
+                debug!("ring is full, waiting for consumer");
+                futex_wait(&header.space_seq.0, space_seq);
+            }
+            header.producer_waiting.store(0, Ordering::SeqCst);
+        }
+
+        // SAFETY: the space between head and (tail + capacity) is owned by the producer
+        let fragment_start = head + LEN_PREFIX_SIZE_BYTES as u64;
+        unsafe {
+            self.copy_in(
+                head,
+                &u64::try_from(fragment_len)
+                    .context("failed to convert fragment len to u64")?
+                    .to_le_bytes(),
+            );
+            self.copy_in(fragment_start, &header_bytes);
+            self.copy_in(fragment_start + FRAGMENT_HEADER_SIZE_BYTES as u64, chunk);
+        };
+
+        // Publish the frame, and wake the consumer if it is asleep
+        header.head.0.store(head + frame_len, Ordering::SeqCst);
+        header.data_seq.0.fetch_add(1, Ordering::SeqCst);
+        if header.consumer_waiting.load(Ordering::SeqCst) == 1 {
+            futex_wake(&header.data_seq.0);
+        }
+
+        Ok(())
+    }
+
+    /// Read a single message from the ring (reading as many frames as it has fragments),
+    /// blocking while the ring is empty
+    fn read_message<T: DeserializeOwned + ProtoMessage>(&mut self) -> Result<T> {
+        loop {
+            let header = self.header();
+            let tail = header.tail.0.load(Ordering::Relaxed);
+
+            // Wait until there is at least one frame
+            loop {
+                let data_seq = header.data_seq.0.load(Ordering::SeqCst);
+                if header.head.0.load(Ordering::SeqCst) != tail {
+                    break;
+                }
+                header.consumer_waiting.store(1, Ordering::SeqCst);
+                if header.head.0.load(Ordering::SeqCst) == tail {
+                    futex_wait(&header.data_seq.0, data_seq);
+                }
+                header.consumer_waiting.store(0, Ordering::SeqCst);
+            }
+
+            // SAFETY: frames are published whole, so everything between tail and head is readable
+            let mut len_bytes = [0u8; LEN_PREFIX_SIZE_BYTES];
+            unsafe { self.copy_out(tail, &mut len_bytes) };
+            let fragment_len = usize::try_from(u64::from_le_bytes(len_bytes))
+                .context("failed to convert u64 len into usize")?;
+            ensure!(
+                LEN_PREFIX_SIZE_BYTES + fragment_len <= self.capacity_bytes,
+                "invalid length header [{fragment_len}], fragment would overflow ring capacity [{}]",
+                self.capacity_bytes
+            );
+            let fragment_start = tail + LEN_PREFIX_SIZE_BYTES as u64;
+            let fragment_pos = self.position(fragment_start);
+
+            // Use the fragment in place when it is contiguous, otherwise copy it out first
+            let mut buf = std::mem::take(&mut self.buf);
+            let fragment = if fragment_pos + fragment_len <= self.capacity_bytes {
+                // SAFETY: the fragment bytes are readable (see above), and within the data region
+                unsafe {
+                    std::slice::from_raw_parts(
+                        self.shmem
+                            .as_ptr()
+                            .add(RING_HEADER_SIZE_BYTES + fragment_pos),
+                        fragment_len,
+                    )
+                }
+            } else {
+                buf.resize(fragment_len, 0);
+                // SAFETY: the fragment bytes are readable (see above)
+                unsafe { self.copy_out(fragment_start, &mut buf) };
+                &buf[..]
+            };
+            let codec = self.codec;
+            let result = self
+                .reassembler
+                .reassemble(fragment)
+                .and_then(|msg| msg.map(|msg| codec.decode::<T>(msg)).transpose());
+            self.buf = buf;
+
+            // Release the frame, and wake the producer if it is asleep
+            let header = self.header();
+            header.tail.0.store(
+                fragment_start
+                    + u64::try_from(fragment_len)
+                        .map_err(|e| anyhow!("invalid fragment len: {e}"))?,
+                Ordering::SeqCst,
+            );
+            header.space_seq.0.fetch_add(1, Ordering::SeqCst);
+            if header.producer_waiting.load(Ordering::SeqCst) == 1 {
+                futex_wake(&header.space_seq.0);
+            }
+
+            if let Some(msg) = result? {
+                return Ok(msg);
+            }
+        }
+    }
+}
@@ -0,0 +1,208 @@
+//! Parent-specific IPC implementation over shared memory SPSC ring buffers
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::io::Write;
+use std::process::{Child, Command, Stdio};
+
+use anyhow::{ensure, Context as _, Result};
+use tracing::{debug, info};
+use uuid::Uuid;
+
+use crate::codec::RpcCodec;
+use crate::latency::OneWayLatencies;
+use crate::shmem::ring::{
+    RingInit, RingInitResponse, ShmemRing, DEFAULT_SHARED_MEM_RING_CAPACITY_BYTES,
+};
+use crate::{
+    get_monotonic_time_nanos, payload_size_bytes_from_env_or_default, synthetic_payload,
+    ParentProcess, PingMessage, Pinger, PongMessage, RpcPong,
+};
+
+/// ID of a child process (as reported by the child)
+type ChildId = String;
+
+/// Name of a child process (known at start time)
+type ChildName = String;
+
+/// Bi-directional channel for communication
+#[derive(Debug)]
+struct RingChannel {
+    /// Self-reported ID of the child
+    child_id: ChildId,
+
+    /// Ring that the parent writes pings to
+    to_child: ShmemRing,
+
+    /// Ring that the parent reads pongs from
+    from_child: ShmemRing,
+}
+
+/// A parent process that performs IPC via SPSC ring buffers in shared memory
+pub struct RingParent {
+    /// UUID of the parent process
+    uuid: Uuid,
+
+    /// Synthetic payload sent with every ping (see `RPC_PAYLOAD_SIZE_BYTES`)
+    payload: Vec<u8>,
+
+    /// Codec used to encode messages (sent to children in the init message)
+    codec: RpcCodec,
+
+    /// Capacity (in bytes) of the data region of each ring
+    capacity_bytes: usize,
+
+    /// Channels for writing to parents by child ID
+    ///
+    /// SAFETY: We're safe using a `RefCell` here because this structure
+    /// is very much *not* multi-threaded.
+    channels: HashMap<ChildName, RefCell<RingChannel>>,
+}
+
+impl std::fmt::Debug for RingParent {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("RingParent")
+            .field("uuid", &self.uuid)
+            .finish()
+    }
+}
+
+impl Default for RingParent {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl RingParent {
+    /// Create a [`RingParent`]
+    #[must_use]
+    pub fn new() -> Self {
+        let capacity_bytes = std::env::var("SHARED_MEM_RING_CAPACITY_BYTES")
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(DEFAULT_SHARED_MEM_RING_CAPACITY_BYTES);
+        Self {
+            uuid: Uuid::now_v7(),
+            payload: synthetic_payload(payload_size_bytes_from_env_or_default(std::env::vars())),
+            codec: RpcCodec::from_env_or_default(std::env::vars()),
+            capacity_bytes,
+            channels: HashMap::new(),
+        }
+    }
+}
+
+impl ParentProcess for RingParent {
+    fn id(&self) -> String {
+        self.uuid.to_string()
+    }
+
+    fn spawn_child(&mut self, name: impl AsRef<str>, mut cmd: Command) -> Result<Child> {
+        // Create (and initialize) both rings before the child can possibly attach to them
+        info!(
+            capacity_bytes = self.capacity_bytes,
+            "determined ring capacity"
+        );
+        debug!("creating rings");
+        let to_child = ShmemRing::new(self.capacity_bytes, self.codec)
+            .context("failed to create ring to child")?;
+        let mut from_child = ShmemRing::new(self.capacity_bytes, self.codec)
+            .context("failed to create ring from child")?;
+
+        let init_msg = RingInit {
+            parent_id: self.uuid.to_string(),
+            codec: self.codec,
+            to_child: to_child.to_serialized(),
+            from_child: from_child.to_serialized(),
+        };
+
+        // Spawn the child
+        debug!("spawning child");
+        let mut child = cmd
+            .stdin(Stdio::piped())
+            .spawn()
+            .context("failed to spawn child process")?;
+
+        // Send the init message over stdin
+        debug!("writing init to child STDIN");
+        let mut child_stdin = child.stdin.take().context("failed to get child STDIN")?;
+        child_stdin
+            .write(&serde_json::to_vec(&init_msg).context("failed to serialize init msg")?)
+            .context("failed to write init msg")?;
+        child_stdin
+            .write(b"\r\n")
+            .context("failed to write new line")?;
+        child_stdin.flush().context("failed to flush child STDIN")?;
+
+        // Wait for the child to respond over the ring
+        debug!("waiting for init response from child");
+        let init_resp: RingInitResponse = from_child
+            .read_message()
+            .context("failed to read init response message")?;
+        ensure!(
+            init_resp.parent_id == self.uuid.to_string(),
+            "parent ID reported by child did not match"
+        );
+
+        // Save information to local registry
+        debug!(child_name = name.as_ref(), "saving child information");
+        self.channels.insert(
+            name.as_ref().into(),
+            RefCell::new(RingChannel {
+                child_id: init_resp.child_id,
+                to_child,
+                from_child,
+            }),
+        );
+
+        Ok(child)
+    }
+}
+
+impl Pinger for RingParent {
+    fn roundtrip_ping(
+        &self,
+        child_name: impl AsRef<str>,
+    ) -> anyhow::Result<Option<OneWayLatencies>> {
+        let child = child_name.as_ref();
+        debug!(child = child, "retrieving channel for child");
+        let mut chan = self
+            .channels
+            .get(child)
+            .context("failed to find child with given name")?
+            .borrow_mut();
+
+        let child_id = chan.child_id.clone();
+        debug!(child_id, child, "found channel for child");
+
+        // Build & send the ping message
+        debug!(child, "sending ping message to child");
+        chan.to_child
+            .write_message(&PingMessage {
+                sender_id: self.uuid.to_string(),
+                receiver_id: child_id.clone(),
+                sent_at_ns: get_monotonic_time_nanos()?,
+                payload: self.payload.clone(),
+            })
+            .context("failed to send ping message to child")?;
+        debug!(child, "successfully sent ping message to child");
+
+        debug!(child, "reading pong message from child");
+        let pong_msg: PongMessage = chan
+            .from_child
+            .read_message()
+            .context("failed to read pong message")?;
+        let latencies = pong_msg.one_way_latencies()?;
+
+        ensure!(pong_msg.sender_id() == child_id, "child ID matches");
+        ensure!(
+            pong_msg.receiver_id() == self.uuid.to_string(),
+            "parent ID matches"
+        );
+        ensure!(
+            pong_msg.payload() == self.payload.as_slice(),
+            "payload matches"
+        );
+
+        Ok(latencies)
+    }
+}
@@ -9,9 +9,13 @@ use tracing::debug;
 use uuid::Uuid;
 
 use crate::shmem::shared_mem_queue::{
-    SharedMemQueueHandle, SharedMemQueueInit, SharedMemQueueInitResponse,
+    SharedMemQueueHandle, SharedMemQueueInit, SharedMemQueueInitResponse, SharedMemQueueView,
+};
+use crate::shmem::zero_copy::ShmemReadMode;
+use crate::{
+    get_monotonic_time_nanos, monotonic_nanos_since, ArchivedPingMessage, ChildProcess,
+    PingMessage, PongMessage, RpcPing as _,
 };
-use crate::{get_system_time_millis, ChildProcess, PingMessage, PongMessage};
 
 /// A child process that performs IPC via shared memory, in particular using [`shared_mem_queue`]
 #[derive(Debug)]
@@ -57,6 +61,8 @@ impl ChildProcess for SharedMemQueueChild {
         // We expect to receive an init message on STDIN
         let SharedMemQueueInit {
             parent_id,
+            codec,
+            read_mode,
             parent_region,
             child_region,
         } = serde_json::from_slice(s.as_bytes())
@@ -116,9 +122,11 @@ impl ChildProcess for SharedMemQueueChild {
         debug!("building shared mem queue for messages received from parent");
         let mut from_parent =
             unsafe { SharedMemQueue::create(from_parent_mmap.as_mut_ptr(), child_region.len) };
+        let mut from_parent_view =
+            unsafe { SharedMemQueueView::attach(from_parent_mmap.as_mut_ptr(), child_region.len) };
 
         let mut to_parent_handle =
-            SharedMemQueueHandle::<SharedMemQueueInitResponse>::new(&mut to_parent);
+            SharedMemQueueHandle::<SharedMemQueueInitResponse>::new(&mut to_parent, codec);
         to_parent_handle
             .blocking_write(&SharedMemQueueInitResponse {
                 parent_id: parent_id.clone(),
@@ -134,26 +142,48 @@ impl ChildProcess for SharedMemQueueChild {
         debug!("entering read loop...");
         loop {
             debug!("attempting to read ping");
-            let mut reader = SharedMemQueueHandle::<PingMessage>::new(&mut from_parent);
-            let PingMessage {
-                sender_id,
-                receiver_id,
-                ..
-            } = reader
-                .blocking_read()
-                .context("failed to deserialize ping message")?;
-            ensure!(sender_id == parent_id, "sender should be parent");
-            ensure!(receiver_id == self.id(), "receiver should be child");
+            let (ping_latency_ns, payload) = match read_mode {
+                ShmemReadMode::Decode => {
+                    let mut reader =
+                        SharedMemQueueHandle::<PingMessage>::new(&mut from_parent, codec);
+                    let PingMessage {
+                        sender_id,
+                        receiver_id,
+                        sent_at_ns,
+                        payload,
+                        ..
+                    } = reader
+                        .blocking_read()
+                        .context("failed to deserialize ping message")?;
+                    let ping_latency_ns = monotonic_nanos_since(sent_at_ns)?;
+                    ensure!(sender_id == parent_id, "sender should be parent");
+                    ensure!(receiver_id == self.id(), "receiver should be child");
+                    (ping_latency_ns, payload)
+                }
+                ShmemReadMode::ZeroCopy => from_parent_view
+                    .read_archived(|ping: &ArchivedPingMessage| {
+                        let ping_latency_ns = monotonic_nanos_since(ping.sent_at_ns.to_native())?;
+                        ensure!(ping.sender_id() == parent_id, "sender should be parent");
+                        ensure!(ping.receiver_id() == self.id(), "receiver should be child");
+                        Ok((ping_latency_ns, ping.payload.to_vec()))
+                    })
+                    .context("failed to access archived ping message")?,
+            };
             debug!(parent_id, "successfully received ping from parent");
 
             // Build & write pong back to the parent
-            to_parent_handle
-                .blocking_write(&PongMessage {
-                    sender_id: self.id(),
-                    receiver_id: parent_id.clone(),
-                    sent_at_ms: get_system_time_millis()?,
-                })
-                .context("failed to send pong to parent")?;
+            let pong = PongMessage {
+                sender_id: self.id(),
+                receiver_id: parent_id.clone(),
+                sent_at_ns: get_monotonic_time_nanos()?,
+                ping_latency_ns,
+                payload,
+            };
+            match read_mode {
+                ShmemReadMode::Decode => to_parent_handle.blocking_write(&pong),
+                ShmemReadMode::ZeroCopy => to_parent_handle.blocking_write_archived(&pong),
+            }
+            .context("failed to send pong to parent")?;
         }
     }
 }
@@ -7,17 +7,33 @@ Generally this method causes the two processes to `mmap()` the *same* file, with
 that the file on disk actually changes -- we're less interested in crash resistance for the
 file undergoing changes, and more for using the memory region as fast buffer (almost like a single packet)
 
+Messages are split into fragments (see [`crate::shmem::framing`]) that are written to the queue as `u64` LE
+length-prefixed frames, so that messages larger than the queue can be sent.
+
 [0]: <https://crates.io/crates/shared_mem_queue>
 **/
 
 use std::io::{BufWriter, Write};
 use std::path::PathBuf;
+use std::ptr::{read_volatile, write_volatile};
+use std::sync::atomic::{fence, Ordering};
 
-use anyhow::{Context as _, Result};
+use anyhow::{anyhow, ensure, Context as _, Result};
 use bytes::{BufMut, BytesMut};
+use rkyv::api::high::{HighSerializer, HighValidator};
+use rkyv::bytecheck::CheckBytes;
+use rkyv::rancor;
+use rkyv::ser::allocator::ArenaHandle;
+use rkyv::util::AlignedVec;
+use rkyv::Portable;
 use serde::{de::DeserializeOwned, Deserialize, Serialize};
 use tracing::debug;
 
+use crate::codec::{Codec as _, RpcCodec};
+use crate::proto::{impl_init_response_proto, schema, ProtoMessage};
+use crate::shmem::framing::{Fragmenter, Reassembler, FRAGMENT_HEADER_SIZE_BYTES};
+use crate::shmem::zero_copy::{self, ShmemReadMode};
+
 mod child;
 pub use child::SharedMemQueueChild;
 
@@ -25,6 +41,9 @@ mod parent;
 pub use parent::SharedMemQueueParent;
 use shared_mem_queue::SharedMemQueue;
 
+/// Size of the length prefix that precedes every frame
+const LEN_PREFIX_SIZE_BYTES: usize = size_of::<u64>();
+
 /// Information related to a shared region
 #[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
 struct SharedRegionInfo {
@@ -47,6 +66,12 @@ struct SharedMemQueueInit {
     /// UUID of the parent
     parent_id: String,
 
+    /// Codec used to encode messages (after this init message)
+    codec: RpcCodec,
+
+    /// How ping/pong messages are read out of shared memory
+    read_mode: ShmemReadMode,
+
     /// Information describing the shared region into which parents should write
     /// in order to send messages to children
     ///
@@ -69,35 +94,114 @@ struct SharedMemQueueInitResponse {
     child_id: String,
 }
 
+impl_init_response_proto!(SharedMemQueueInitResponse);
+
+impl ProtoMessage for SharedRegionInfo {
+    type Proto = schema::SharedRegionInfo;
+
+    fn to_proto(&self) -> Result<Self::Proto> {
+        Ok(Self::Proto {
+            file_path: self
+                .file_path
+                .to_str()
+                .context("shared region file path is not valid UTF-8")?
+                .into(),
+            offset: self.offset,
+            len: u64::try_from(self.len)?,
+        })
+    }
+
+    fn from_proto(proto: Self::Proto) -> Result<Self> {
+        Ok(Self {
+            file_path: proto.file_path.into(),
+            offset: proto.offset,
+            len: usize::try_from(proto.len)?,
+        })
+    }
+}
+
+impl ProtoMessage for SharedMemQueueInit {
+    type Proto = schema::SharedMemQueueInit;
+
+    fn to_proto(&self) -> Result<Self::Proto> {
+        Ok(Self::Proto {
+            parent_id: self.parent_id.clone(),
+            codec: schema::RpcCodec::from(self.codec).into(),
+            read_mode: schema::ShmemReadMode::from(self.read_mode).into(),
+            parent_region: self
+                .parent_region
+                .as_ref()
+                .map(SharedRegionInfo::to_proto)
+                .transpose()?,
+            child_region: Some(self.child_region.to_proto()?),
+        })
+    }
+
+    fn from_proto(proto: Self::Proto) -> Result<Self> {
+        Ok(Self {
+            parent_id: proto.parent_id,
+            codec: schema::RpcCodec::try_from(proto.codec)
+                .map_err(|e| anyhow!("invalid codec: {e}"))?
+                .into(),
+            read_mode: schema::ShmemReadMode::try_from(proto.read_mode)
+                .map_err(|e| anyhow!("invalid read mode: {e}"))?
+                .into(),
+            parent_region: proto
+                .parent_region
+                .map(SharedRegionInfo::from_proto)
+                .transpose()?,
+            child_region: SharedRegionInfo::from_proto(
+                proto.child_region.context("missing child region")?,
+            )?,
+        })
+    }
+}
+
 /// MemQueueReader is a wrapper around a [`SharedMemQueue`] that maintains
 /// a buffer that is as large as the space required for the memqueue to read,
 /// to avoid allocations when processing messages.
 ///
 /// MemQueueReaders can only process one message at a time, and clear internal buffers
 /// after every operation.
+///
+/// NOTE: handles are usually created per message, so the IDs given to fragmented messages
+/// are only unique per handle (which is enough, since every message is read whole by a single handle).
 struct SharedMemQueueHandle<'a, T>
 where
-    T: Sized + Serialize + DeserializeOwned,
+    T: Sized + Serialize + DeserializeOwned + ProtoMessage,
 {
     /// The shared queue that messages will be read from
     queue: &'a mut SharedMemQueue,
     /// Scratch buffer that will contain
     buf: Option<BytesMut>,
+    /// Codec used to encode messages
+    codec: RpcCodec,
+    /// Largest frame (length prefix included) that fits in the queue
+    max_frame_len: usize,
+    /// Splits written messages into fragments
+    fragmenter: Fragmenter,
+    /// Reassembles read messages out of fragments
+    reassembler: Reassembler,
     /// Market for the relevant T
     _t: std::marker::PhantomData<T>,
-    // TODO: customizable serialize/deserialize?
 }
 
 impl<'a, T> SharedMemQueueHandle<'a, T>
 where
-    T: Sized + Serialize + DeserializeOwned,
+    T: Sized + Serialize + DeserializeOwned + ProtoMessage,
 {
     /// Create a new SharedMemQueueHandle from an existing [`SharedMemQueue`]
-    fn new(queue: &'a mut SharedMemQueue) -> SharedMemQueueHandle<'a, T> {
+    fn new(queue: &'a mut SharedMemQueue, codec: RpcCodec) -> SharedMemQueueHandle<'a, T> {
         let buf = BytesMut::with_capacity(queue.space());
+        // NOTE: the queue always keeps one byte free, to tell a full queue from an empty one
+        let max_frame_len = queue.space() + queue.size();
         Self {
             queue,
             buf: Some(buf),
+            codec,
+            max_frame_len,
+            fragmenter: Fragmenter::default(),
+            reassembler: Reassembler::from_env_or_default(std::env::vars()),
             _t: std::marker::PhantomData,
         }
     }
@@ -105,57 +209,58 @@ where
     /// Convert this [`SharedMemQueueHandle`] into one of a different type
     fn into_other<T2>(self) -> SharedMemQueueHandle<'a, T2>
     where
-        T2: Sized + Serialize + DeserializeOwned,
+        T2: Sized + Serialize + DeserializeOwned + ProtoMessage,
     {
-        SharedMemQueueHandle::new(self.queue)
+        SharedMemQueueHandle::new(self.queue, self.codec)
     }
 
     /// Perform a blocking read of an object the queue stored in this [`SharedMemQueueHandle`]
     ///
-    /// NOTE: the data that is written into the queue must Serialized and be `u64` length prefixed.
+    /// NOTE: the data that is written into the queue must Serialized, split into fragments, and be `u64` length prefixed.
     fn blocking_read(&mut self) -> Result<T> {
-        // Read the length-prefix
-        debug!(
-            "[SharedMemQueueHandle::blocking_read] reading length-prefix of underlying queue..."
-        );
         let mut buf = self.buf.take().context("missing buf")?;
-        buf.resize(8, 0);
-        self.queue.blocking_read(&mut buf[0..8]);
-        let len = u64::from_le_bytes(
-            buf[0..8]
-                .try_into()
-                .context("unexpectedly invalid slice length when reading len")?,
-        );
-        debug!(
-            len,
-            "[SharedMemQueueHandle::blocking_read] successfully read length"
-        );
-
-        // Read the rest of the actual message
-        debug!(
-            len,
-            "[SharedMemQueueHandle::blocking_read] reading len bytes from queue into inner buf"
-        );
-        let len = usize::try_from(len).context("failed to convert u64 len into usize")?;
-        let data_start = 8;
-        let data_end = len + 8;
-        buf.resize(data_end, 0u8);
-        self.queue.blocking_read(&mut buf[data_start..data_end]);
-        debug!(
-            len,
-            "[SharedMemQueueHandle::blocking_read] successfully read bytes into inner"
-        );
-
-        // Read the object from the remainig slice
-        debug!(
-            type_name = std::any::type_name::<T>(),
-            "[SharedMemQueueHandle::blocking_read] deserializing bytes into type (JSON)"
-        );
-        let result = serde_json::from_slice(&buf[data_start..data_end])
-            .with_context(|| format!("failed to read from slice [{}->{}]", data_start, data_end))?;
+        let result = loop {
+            // Read the length-prefix
+            debug!(
+                "[SharedMemQueueHandle::blocking_read] reading length-prefix of underlying queue..."
+            );
+            buf.resize(LEN_PREFIX_SIZE_BYTES, 0);
+            self.queue.blocking_read(&mut buf[0..LEN_PREFIX_SIZE_BYTES]);
+            let len = u64::from_le_bytes(
+                buf[0..LEN_PREFIX_SIZE_BYTES]
+                    .try_into()
+                    .context("unexpectedly invalid slice length when reading len")?,
+            );
+            let len = usize::try_from(len).context("failed to convert u64 len into usize")?;
+            ensure!(
+                LEN_PREFIX_SIZE_BYTES + len <= self.max_frame_len,
+                "invalid length prefix [{len}], fragment does not fit in queue"
+            );
+
+            // Read the rest of the fragment
+            debug!(
+                len,
+                "[SharedMemQueueHandle::blocking_read] reading len bytes from queue into inner buf"
+            );
+            buf.resize(len, 0u8);
+            self.queue.blocking_read(&mut buf[..]);
+
+            // Read the object from the message, once all of its fragments have been read
+            if let Some(msg) = self.reassembler.reassemble(&buf)? {
+                debug!(
+                    type_name = std::any::type_name::<T>(),
+                    codec = ?self.codec,
+                    "[SharedMemQueueHandle::blocking_read] deserializing bytes into type"
+                );
+                break self
+                    .codec
+                    .decode(msg)
+                    .with_context(|| format!("failed to read message of len [{}]", msg.len()))?;
+            }
+        };
         debug!(
             type_name = std::any::type_name::<T>(),
-            "[SharedMemQueueHandle::blocking_read] successfully deserialized type (JSON)"
+            "[SharedMemQueueHandle::blocking_read] successfully deserialized type"
         );
 
         // Clear the bytes before we start working with it
@@ -167,7 +272,7 @@ where
 
     /// Perform a blocking write of an object the queue stored in this [`SharedMemQueueHandle`]
     ///
-    /// NOTE: the data that is written into the queue must Serialized and be `u64` length prefixed.
+    /// NOTE: the data that is written into the queue is Serialized, split into fragments, and `u64` length prefixed.
     fn blocking_write(&mut self, obj: &T) -> Result<()> {
         debug!(
             type_name = std::any::type_name::<T>(),
@@ -179,13 +284,9 @@ where
             .context("missing buf during blocking write")?;
         let mut writer = BufWriter::new(buf.writer());
 
-        // Write placeholder for u64 len (we'll fill this in later)
-        writer
-            .write(&[0u8; 8])
-            .context("failed to write length placeholder during blocking write")?;
-
         // Write the serialized object in
-        serde_json::to_writer(&mut writer, obj)
+        self.codec
+            .encode_to_writer(obj, &mut writer)
             .context("failed to write to internal buffer during blocking write")?;
 
         writer
@@ -198,27 +299,193 @@ where
             .context("failed to convert writer back into BytesMut")?
             .into_inner();
 
-        // Fill in the length
-        let obj_bytes_len = buf.len() - 8;
-        buf[0..8].swap_with_slice(&mut u64::to_le_bytes(
-            u64::try_from(obj_bytes_len)
-                .context("failed to convert usize to u64 during blocking write")?,
-        ));
-
         debug!(
             type_name = std::any::type_name::<T>(),
-            msg_bytes_written = obj_bytes_len,
+            msg_bytes_written = buf.len(),
             "[SharedMemQueueHandle::blocking_write] successfully wrote serialized object to internal buffer",
         );
 
-        debug!(
-            bytes_written = obj_bytes_len + 8,
-            "[SharedMemQueueHandle::blocking_write] writing length-prefixed bytes to shared message queue"
-        );
-        self.queue.blocking_write(&buf[0..obj_bytes_len + 8]);
+        self.write_fragments(&buf)?;
 
         buf.clear();
         self.buf = Some(buf);
         Ok(())
     }
+
+    /// Perform a blocking write of an object, archived with `rkyv` (see [`zero_copy`])
+    ///
+    /// NOTE: like [`SharedMemQueueHandle::blocking_write`], the archived bytes are split into fragments,
+    /// and `u64` length prefixed.
+    fn blocking_write_archived(&mut self, obj: &T) -> Result<()>
+    where
+        T: for<'b> rkyv::Serialize<HighSerializer<AlignedVec, ArenaHandle<'b>, rancor::Error>>,
+    {
+        let bytes = zero_copy::archive(obj)?;
+        debug!(
+            type_name = std::any::type_name::<T>(),
+            len = bytes.len(),
+            "[SharedMemQueueHandle::blocking_write_archived] writing archived object to queue"
+        );
+        self.write_fragments(&bytes)
+    }
+
+    /// Split a message into fragments, and write each of them to the queue as a `u64` length-prefixed frame
+    ///
+    /// Frames are written whole, so they can be read in place (see [`SharedMemQueueView`]).
+    fn write_fragments(&mut self, msg: &[u8]) -> Result<()> {
+        let max_chunk_size = self
+            .max_frame_len
+            .saturating_sub(LEN_PREFIX_SIZE_BYTES + FRAGMENT_HEADER_SIZE_BYTES);
+        for (header, chunk) in self.fragmenter.fragments(msg, max_chunk_size)? {
+            let len = u64::try_from(FRAGMENT_HEADER_SIZE_BYTES + chunk.len())
+                .context("failed to convert usize to u64 during blocking write")?;
+            debug!(
+                len,
+                fragment_index = header.fragment_index,
+                "[SharedMemQueueHandle::write_fragments] writing length-prefixed fragment to shared message queue"
+            );
+            self.queue.blocking_write(&len.to_le_bytes());
+            self.queue.blocking_write(&header.to_bytes());
+            self.queue.blocking_write(chunk);
+        }
+        Ok(())
+    }
+}
+
+/// A read-only view of the ring buffer used by a [`SharedMemQueue`], which allows
+/// messages to be accessed *in place*, rather than being copied out of the queue.
+///
+/// [`SharedMemQueue`] does not expose its internals, so this structure mirrors its layout:
+/// the write position (`usize`), the read position (`usize`), then the data itself.
+///
+/// NOTE: fragments that wrap around the end of the ring buffer are copied into an
+/// internal buffer before being accessed, and messages made of more than one fragment are reassembled
+/// (and so copied) before being accessed.
+struct SharedMemQueueView {
+    /// Pointer to the write position of the queue (only modified by the writer)
+    write_pos_ptr: *const usize,
+    /// Pointer to the read position of the queue (only modified by the reader)
+    read_pos_ptr: *mut usize,
+    /// Pointer to the start of the data in the queue
+    data_ptr: *const u8,
+    /// Capacity of the ring buffer
+    capacity: usize,
+    /// Scratch buffer for fragments that wrap around the end of the ring buffer
+    wrap_buf: Vec<u8>,
+    /// Reassembles messages out of fragments
+    reassembler: Reassembler,
+}
+
+// SAFETY: like [`SharedMemQueue`], the view only holds pointers into a region that outlives it,
+// and is only ever used by one thread at a time.
+unsafe impl Send for SharedMemQueueView {}
+
+impl std::fmt::Debug for SharedMemQueueView {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SharedMemQueueView")
+            .field("capacity", &self.capacity)
+            .finish()
+    }
+}
+
+impl SharedMemQueueView {
+    /// Attach to a queue that has been (or will be) created with [`SharedMemQueue::create`]
+    ///
+    /// # Safety
+    ///
+    /// `mem` must point to a region of at least `mem_len` bytes that outlives this view,
+    /// and only one reader may read from the queue at a time.
+    unsafe fn attach(mem: *mut u8, mem_len: usize) -> Self {
+        let header_len = 2 * std::mem::size_of::<usize>();
+        Self {
+            write_pos_ptr: mem as *const usize,
+            read_pos_ptr: (mem as *mut usize).add(1),
+            data_ptr: mem.add(header_len),
+            capacity: mem_len - header_len,
+            wrap_buf: Vec::new(),
+            reassembler: Reassembler::from_env_or_default(std::env::vars()),
+        }
+    }
+
+    /// Number of bytes that are available to read
+    fn size(&self) -> usize {
+        let (wpos, rpos) = unsafe {
+            (
+                read_volatile(self.write_pos_ptr),
+                read_volatile(self.read_pos_ptr),
+            )
+        };
+        (self.capacity + wpos - rpos) % self.capacity
+    }
+
+    /// Spin until at least `len` bytes are available to read
+    fn wait_for(&self, len: usize) {
+        while self.size() < len {
+            std::hint::spin_loop();
+        }
+        fence(Ordering::Acquire);
+    }
+
+    /// Copy bytes starting at `pos` out of the queue (wrapping around the end if necessary)
+    fn copy_out(&self, pos: usize, out: &mut [u8]) {
+        for (i, byte) in out.iter_mut().enumerate() {
+            *byte = unsafe { read_volatile(self.data_ptr.add((pos + i) % self.capacity)) };
+        }
+    }
+
+    /// Release the space occupied by a frame (of a given fragment length) starting at `rpos` back to the writer
+    fn release(&mut self, rpos: usize, len: usize) {
+        fence(Ordering::Release);
+        unsafe {
+            write_volatile(
+                self.read_pos_ptr,
+                (rpos + LEN_PREFIX_SIZE_BYTES + len) % self.capacity,
+            );
+        };
+    }
+
+    /// Perform a blocking read of a fragmented, `u64` length prefixed `rkyv` archive, and access it in place
+    ///
+    /// The archived message is only valid for the duration of `f`, after which
+    /// the space it occupied is released back to the writer.
+    fn read_archived<T, R>(&mut self, f: impl FnOnce(&T) -> Result<R>) -> Result<R>
+    where
+        T: Portable + for<'b> CheckBytes<HighValidator<'b, rancor::Error>>,
+    {
+        loop {
+            // Read the length-prefix
+            self.wait_for(LEN_PREFIX_SIZE_BYTES);
+            let rpos = unsafe { read_volatile(self.read_pos_ptr) };
+            let mut len_bytes = [0u8; LEN_PREFIX_SIZE_BYTES];
+            self.copy_out(rpos, &mut len_bytes);
+            let len = usize::try_from(u64::from_le_bytes(len_bytes))
+                .context("failed to convert u64 len into usize")?;
+            ensure!(
+                len + LEN_PREFIX_SIZE_BYTES < self.capacity,
+                "archived fragment of length [{len}] does not fit in queue"
+            );
+
+            // Wait for the rest of the fragment
+            self.wait_for(len + LEN_PREFIX_SIZE_BYTES);
+            let data_start = (rpos + LEN_PREFIX_SIZE_BYTES) % self.capacity;
+            let mut wrap_buf = std::mem::take(&mut self.wrap_buf);
+            let fragment = if data_start + len <= self.capacity {
+                unsafe { std::slice::from_raw_parts(self.data_ptr.add(data_start), len) }
+            } else {
+                wrap_buf.resize(len, 0);
+                self.copy_out(data_start, &mut wrap_buf);
+                &wrap_buf[..]
+            };
+
+            // Access the message once all of its fragments have been read
+            if let Some(msg) = self.reassembler.reassemble(fragment)? {
+                let result = zero_copy::access(msg).and_then(f);
+                self.wrap_buf = wrap_buf;
+                self.release(rpos, len);
+                return result;
+            }
+            self.wrap_buf = wrap_buf;
+            self.release(rpos, len);
+        }
+    }
 }
@@ -13,11 +13,17 @@ use shared_mem_queue::SharedMemQueue;
 use tracing::{debug, info};
 use uuid::Uuid;
 
-use crate::shmem::shared_mem_queue::SharedMemQueueHandle;
+use crate::codec::RpcCodec;
+use crate::latency::OneWayLatencies;
+use crate::shmem::shared_mem_queue::{SharedMemQueueHandle, SharedMemQueueView};
 use crate::shmem::shared_mem_queue::{
     SharedMemQueueInit, SharedMemQueueInitResponse, SharedRegionInfo,
 };
-use crate::{get_system_time_millis, ParentProcess, PingMessage, Pinger, PongMessage, RpcPong};
+use crate::shmem::zero_copy::ShmemReadMode;
+use crate::{
+    get_monotonic_time_nanos, payload_size_bytes_from_env_or_default, synthetic_payload,
+    ArchivedPongMessage, ParentProcess, PingMessage, Pinger, PongMessage, RpcPong,
+};
 
 /// ID of a child process (as reported by the child)
 type ChildId = String;
@@ -36,6 +42,9 @@ struct SharedMemQueueChannel {
     /// Shared mem queue that children will write to in order to communicate (parents must read from this)
     child: SharedMemQueue,
 
+    /// View of the queue that children write to, for reading messages in place
+    child_view: SharedMemQueueView,
+
     /// File that contains the shared region
     ///
     /// NOTE: this information must be held to ensure that the file is not dropped
@@ -60,6 +69,15 @@ pub struct SharedMemQueueParent {
     /// UUID of the parent process
     uuid: Uuid,
 
+    /// Synthetic payload sent with every ping (see `RPC_PAYLOAD_SIZE_BYTES`)
+    payload: Vec<u8>,
+
+    /// Codec used to encode messages (sent to children in the init message)
+    codec: RpcCodec,
+
+    /// How ping/pong messages are read out of shared memory (sent to children in the init message)
+    read_mode: ShmemReadMode,
+
     /// Channels for writing to parents by child ID
     ///
     /// SAFETY: We're safe using a `RefCell` here because this structure
@@ -71,6 +89,7 @@ impl std::fmt::Debug for SharedMemQueueParent {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         f.debug_struct("SharedMemQueueParent")
             .field("uuid", &self.uuid)
+            .field("read_mode", &self.read_mode)
             .finish()
     }
 }
@@ -88,6 +107,9 @@ impl SharedMemQueueParent {
     pub fn new() -> Self {
         Self {
             uuid: Uuid::now_v7(),
+            payload: synthetic_payload(payload_size_bytes_from_env_or_default(std::env::vars())),
+            codec: RpcCodec::from_env_or_default(std::env::vars()),
+            read_mode: ShmemReadMode::from_env_or_default(std::env::vars()),
             channels: HashMap::new(),
         }
     }
@@ -95,12 +117,8 @@ impl SharedMemQueueParent {
 
 /// Size of shared region (file) to create.
 ///
-/// Each side (child, parent) will only be able to send *half* of this amount
-///
-/// Due to a bug in `shared-memory-queue` we must use *at least* 32MB, as otherwise we run
-/// out of space, and attempt to write to invalid regions of the shared file.
-///
-/// In release mode, we can send 10x as many messages, so we use 320MB
+/// Each side (child, parent) gets a queue in *half* of this amount, and messages that
+/// do not fit in a queue are split into fragments (see [`crate::shmem::framing`]).
 const DEFAULT_SHARED_REGION_LEN_BYTES: usize = 320 * 1024 * 1024;
 
 impl ParentProcess for SharedMemQueueParent {
@@ -159,10 +177,15 @@ impl ParentProcess for SharedMemQueueParent {
         debug!("creating queue for child to write to");
         let mut from_child =
             unsafe { SharedMemQueue::create(from_child_region_mmap.as_mut_ptr(), region_half_len) };
+        let from_child_view = unsafe {
+            SharedMemQueueView::attach(from_child_region_mmap.as_mut_ptr(), region_half_len)
+        };
 
         // Create a message that will inform the child of the shared mmap'd file
         let init_msg = SharedMemQueueInit {
             parent_id: self.uuid.to_string(),
+            codec: self.codec,
+            read_mode: self.read_mode,
             parent_region: Some(SharedRegionInfo {
                 file_path: shared_region_file_path.clone(),
                 offset: shared_region_offset_bytes,
@@ -199,7 +222,8 @@ impl ParentProcess for SharedMemQueueParent {
         //
         // We attempt to receive the response (the first message from the child) via the shared memory region.
 
-        let mut reader = SharedMemQueueHandle::<SharedMemQueueInitResponse>::new(&mut from_child);
+        let mut reader =
+            SharedMemQueueHandle::<SharedMemQueueInitResponse>::new(&mut from_child, self.codec);
         let init_resp: SharedMemQueueInitResponse = reader
             .blocking_read()
             .context("failed to deserialize init response message")?;
@@ -223,7 +247,7 @@ impl ParentProcess for SharedMemQueueParent {
         let to_child = unsafe {
             SharedMemQueue::attach(
                 to_child_region_mmap.as_mut_ptr().add(region_half_len),
-                shared_region_len_bytes,
+                region_half_len,
             )
         };
 
@@ -235,6 +259,7 @@ impl ParentProcess for SharedMemQueueParent {
                 child_id: init_resp.child_id,
                 parent: to_child,
                 child: from_child,
+                child_view: from_child_view,
                 _shared_region_file: shared_region_file,
                 _to_child_region_mmap: to_child_region_mmap,
                 _from_child_region_mmap: from_child_region_mmap,
@@ -246,7 +271,10 @@ impl ParentProcess for SharedMemQueueParent {
 }
 
 impl Pinger for SharedMemQueueParent {
-    fn roundtrip_ping(&self, child_name: impl AsRef<str>) -> anyhow::Result<()> {
+    fn roundtrip_ping(
+        &self,
+        child_name: impl AsRef<str>,
+    ) -> anyhow::Result<Option<OneWayLatencies>> {
         let child = child_name.as_ref();
         debug!(child = child, "retrieving channel for child");
         let mut chan = self
@@ -260,28 +288,59 @@ impl Pinger for SharedMemQueueParent {
 
         // Build the ping message
         debug!(child, "sending ping message to child");
-        let mut outgoing_handle = SharedMemQueueHandle::<PingMessage>::new(&mut chan.parent);
-        outgoing_handle
-            .blocking_write(&PingMessage {
-                sender_id: self.uuid.to_string(),
-                receiver_id: child_id.clone(),
-                sent_at_ms: get_system_time_millis()?,
-            })
-            .context("failed to send ping message to child")?;
+        let mut outgoing_handle =
+            SharedMemQueueHandle::<PingMessage>::new(&mut chan.parent, self.codec);
+        let ping = PingMessage {
+            sender_id: self.uuid.to_string(),
+            receiver_id: child_id.clone(),
+            sent_at_ns: get_monotonic_time_nanos()?,
+            payload: self.payload.clone(),
+        };
+        match self.read_mode {
+            ShmemReadMode::Decode => outgoing_handle.blocking_write(&ping),
+            ShmemReadMode::ZeroCopy => outgoing_handle.blocking_write_archived(&ping),
+        }
+        .context("failed to send ping message to child")?;
         debug!(child, "successfully sent ping message to child");
 
         debug!(child, "reading pong message from child");
-        let mut reader = SharedMemQueueHandle::<PongMessage>::new(&mut chan.child);
-        let pong_msg: PongMessage = reader
-            .blocking_read()
-            .context("failed to deserialize pong message")?;
-
-        ensure!(pong_msg.sender_id() == child_id, "child ID matches");
-        ensure!(
-            pong_msg.receiver_id() == self.uuid.to_string(),
-            "parent ID matches"
-        );
+        let latencies = match self.read_mode {
+            ShmemReadMode::Decode => {
+                let mut reader =
+                    SharedMemQueueHandle::<PongMessage>::new(&mut chan.child, self.codec);
+                let pong_msg: PongMessage = reader
+                    .blocking_read()
+                    .context("failed to deserialize pong message")?;
+                let latencies = pong_msg.one_way_latencies()?;
+                ensure!(pong_msg.sender_id() == child_id, "child ID matches");
+                ensure!(
+                    pong_msg.receiver_id() == self.uuid.to_string(),
+                    "parent ID matches"
+                );
+                ensure!(
+                    pong_msg.payload() == self.payload.as_slice(),
+                    "payload matches"
+                );
+                latencies
+            }
+            ShmemReadMode::ZeroCopy => chan
+                .child_view
+                .read_archived(|pong: &ArchivedPongMessage| {
+                    let latencies = pong.one_way_latencies()?;
+                    ensure!(pong.sender_id() == child_id, "child ID matches");
+                    ensure!(
+                        pong.receiver_id() == self.uuid.to_string(),
+                        "parent ID matches"
+                    );
+                    ensure!(
+                        pong.payload.as_slice() == self.payload.as_slice(),
+                        "payload matches"
+                    );
+                    Ok(latencies)
+                })
+                .context("failed to access archived pong message")?,
+        };
 
-        Ok(())
+        Ok(latencies)
     }
 }
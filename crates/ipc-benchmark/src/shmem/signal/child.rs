@@ -0,0 +1,114 @@
+//! Child-specific IPC implementation over shared memory, notified with realtime signals
+
+use std::io::{stdin, BufRead};
+
+use anyhow::{ensure, Context as _, Result};
+use tracing::debug;
+use uuid::Uuid;
+
+use crate::shmem::framing::{Fragmenter, Reassembler};
+use crate::shmem::signal::{
+    block_notify_signal, SignalHandle, SignalInit, SignalInitResponse, SignalListener,
+    SignalNotifier, SignalRegion,
+};
+use crate::{
+    get_monotonic_time_nanos, monotonic_nanos_since, ChildProcess, PingMessage, PongMessage,
+};
+
+/// A child process that performs IPC via shared memory, notified with realtime signals
+#[derive(Debug)]
+pub struct SignalChild {
+    /// UUID that should uniquely identify this process
+    uuid: Uuid,
+}
+
+impl Default for SignalChild {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl SignalChild {
+    /// Build a new [`SignalChild`] with a random UUID
+    #[must_use]
+    pub fn new() -> Self {
+        Self {
+            uuid: Uuid::now_v7(),
+        }
+    }
+}
+
+impl ChildProcess for SignalChild {
+    fn id(&self) -> String {
+        self.uuid.to_string()
+    }
+
+    fn run(self) -> Result<()> {
+        debug!("child process running");
+
+        // Block the notification signal before anything else (in particular, before any threads are spawned)
+        block_notify_signal()?;
+
+        debug!("reading signal init from STDIN");
+        let mut s = String::new();
+        stdin().lock().read_line(&mut s)?;
+
+        // We expect to receive an init message on STDIN
+        let SignalInit {
+            parent_id,
+            codec,
+            parent_pid,
+            to_child,
+            from_child,
+        } = serde_json::from_slice(s.as_bytes())
+            .context("failed to read init message from STDIN")?;
+
+        let mut handle = SignalHandle {
+            outgoing: SignalRegion::from_serialized(from_child)
+                .context("failed to open region from child")?,
+            incoming: SignalRegion::from_serialized(to_child)
+                .context("failed to open region to child")?,
+            notifier: SignalNotifier::new(parent_pid)?,
+            listener: SignalListener::spawn(parent_pid)?,
+            codec,
+            buf: Vec::new(),
+            fragmenter: Fragmenter::default(),
+            reassembler: Reassembler::from_env_or_default(std::env::vars()),
+        };
+
+        handle
+            .write_message(&SignalInitResponse {
+                parent_id: parent_id.clone(),
+                child_id: self.id(),
+            })
+            .context("failed to write init response to parent")?;
+        debug!("successfully wrote init response to parent");
+
+        // Enter reading/writing loop
+        debug!("entering read loop...");
+        loop {
+            let PingMessage {
+                sender_id,
+                receiver_id,
+                sent_at_ns,
+                payload,
+                ..
+            } = handle
+                .read_message()
+                .context("failed to read ping message")?;
+            let ping_latency_ns = monotonic_nanos_since(sent_at_ns)?;
+            ensure!(sender_id == parent_id, "sender should be parent");
+            ensure!(receiver_id == self.id(), "receiver should be child");
+
+            handle
+                .write_message(&PongMessage {
+                    sender_id: self.id(),
+                    receiver_id: sender_id,
+                    sent_at_ns: get_monotonic_time_nanos()?,
+                    ping_latency_ns,
+                    payload,
+                })
+                .context("failed to send pong to parent")?;
+        }
+    }
+}
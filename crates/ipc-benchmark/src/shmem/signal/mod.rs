@@ -0,0 +1,423 @@
+/*!
+Process IPC over shared memory (via [`shared_memory`][crate-shared-memory]), with notifications sent as
+realtime signals (Linux only).
+
+Data is kept in shared memory like [`crate::shmem::raw_sync`] (one region per direction, each holding a single
+`u64` LE length-prefixed fragment of a message, see [`crate::shmem::framing`]), but rather than spinning on a [`raw_sync::events::BusyEvent`], writers notify
+readers with [`sigqueue(3)`][man-sigqueue]. Readers block the notification signal, and receive it with
+[`sigwaitinfo(2)`][man-sigwaitinfo] on a dedicated thread, which forwards the sequence number carried by the
+signal to whoever is waiting for a message.
+
+Readers acknowledge every fragment but the last one of a message by notifying the writer in turn.
+
+Since signals are sent to a *process* (rather than a channel), each parent supports only one child.
+
+[crate-shared-memory]: <https://crates.io/crates/shared_memory>
+[man-sigqueue]: <https://man7.org/linux/man-pages/man3/sigqueue.3.html>
+[man-sigwaitinfo]: <https://man7.org/linux/man-pages/man2/sigwaitinfo.2.html>
+**/
+
+use std::sync::atomic::{fence, Ordering};
+use std::sync::mpsc;
+
+use anyhow::{ensure, Context as _, Result};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use shared_memory::{Shmem, ShmemConf};
+use tracing::debug;
+
+use crate::codec::{Codec as _, RpcCodec};
+use crate::proto::{impl_init_response_proto, ProtoMessage};
+use crate::shmem::framing::{FragmentHeader, Fragmenter, Reassembler, FRAGMENT_HEADER_SIZE_BYTES};
+
+pub mod child;
+pub mod parent;
+
+pub use child::SignalChild;
+pub use parent::SignalParent;
+
+/// Default size of the shared memory region used for *each direction*
+const DEFAULT_SHARED_MEM_SIGNAL_SLAB_SIZE_BYTES: usize = 128 * 1024;
+
+/// Size of the length prefix that precedes every fragment
+const LEN_PREFIX_SIZE_BYTES: usize = size_of::<u64>();
+
+/// Offset (from `SIGRTMIN`) of the realtime signal used for notifications
+const NOTIFY_SIGNAL_OFFSET: libc::c_int = 1;
+
+/// Message sent to child processes over STDIN that contains
+/// information necessary for the child to open both regions, and notify the parent
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+struct SignalInit {
+    /// UUID of the parent
+    parent_id: String,
+
+    /// Codec used to encode messages (after this init message)
+    codec: RpcCodec,
+
+    /// PID of the parent (which notifications are sent to)
+    parent_pid: u32,
+
+    /// Region that the parent writes to (and the child reads from)
+    to_child: SerializedSignalRegion,
+
+    /// Region that the child writes to (and the parent reads from)
+    from_child: SerializedSignalRegion,
+}
+
+/// Message sent from the child process (via shared memory) upon successful initialization
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
+struct SignalInitResponse {
+    /// UUID of the parent
+    parent_id: String,
+
+    /// UUID of the child
+    child_id: String,
+}
+
+impl_init_response_proto!(SignalInitResponse);
+
+/// Proxy that enables sending a [`SignalRegion`] to another process
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+struct SerializedSignalRegion {
+    /// ID for the shared memory region
+    os_id: String,
+
+    /// Size of the shared memory region in bytes
+    size_bytes: usize,
+}
+
+extern "C" {
+    /// Queue a signal (with a value) to a process, see `sigqueue(3)`
+    ///
+    /// NOTE: this is provided by libc, but not exposed by the [`libc`] crate (as of 0.2.159) for Linux
+    fn sigqueue(pid: libc::pid_t, sig: libc::c_int, value: libc::sigval) -> libc::c_int;
+}
+
+/// Realtime signal used for notifications
+fn notify_signal() -> libc::c_int {
+    libc::SIGRTMIN() + NOTIFY_SIGNAL_OFFSET
+}
+
+/// Build a signal set that contains only the notification signal
+fn notify_signal_set() -> Result<libc::sigset_t> {
+    // SAFETY: the set is initialized by sigemptyset before it is used
+    unsafe {
+        let mut set = std::mem::zeroed::<libc::sigset_t>();
+        ensure!(
+            libc::sigemptyset(&mut set) == 0,
+            "failed to empty signal set: {}",
+            std::io::Error::last_os_error()
+        );
+        ensure!(
+            libc::sigaddset(&mut set, notify_signal()) == 0,
+            "failed to add notification signal to set: {}",
+            std::io::Error::last_os_error()
+        );
+        Ok(set)
+    }
+}
+
+/// Block the notification signal for the calling thread (and any threads it spawns later)
+///
+/// NOTE: this must be called before any other threads are spawned, as the default action
+/// for a realtime signal is to terminate the process, and any thread that does not block it may receive it.
+fn block_notify_signal() -> Result<()> {
+    let set = notify_signal_set()?;
+    // SAFETY: the set is valid, and we do not need the old mask
+    let rc = unsafe { libc::pthread_sigmask(libc::SIG_BLOCK, &set, std::ptr::null_mut()) };
+    ensure!(
+        rc == 0,
+        "failed to block notification signal: {}",
+        std::io::Error::from_raw_os_error(rc)
+    );
+    Ok(())
+}
+
+/// Sends notifications (realtime signals, carrying a sequence number) to a peer process
+#[derive(Debug)]
+struct SignalNotifier {
+    /// PID of the process that is notified
+    peer_pid: libc::pid_t,
+
+    /// Sequence number of the last notification sent
+    seq: usize,
+}
+
+impl SignalNotifier {
+    /// Create a new [`SignalNotifier`] for a given peer process
+    fn new(peer_pid: u32) -> Result<Self> {
+        Ok(Self {
+            peer_pid: libc::pid_t::try_from(peer_pid).context("invalid peer PID")?,
+            seq: 0,
+        })
+    }
+
+    /// Notify the peer
+    fn notify(&mut self) -> Result<()> {
+        self.seq += 1;
+        let value = libc::sigval {
+            sival_ptr: self.seq as *mut libc::c_void,
+        };
+        loop {
+            // SAFETY: sigqueue does not retain any pointers
+            if unsafe { sigqueue(self.peer_pid, notify_signal(), value) } == 0 {
+                return Ok(());
+            }
+            let err = std::io::Error::last_os_error();
+            // EAGAIN means the limit of queued signals has been reached, which should clear up quickly
+            if err.kind() != std::io::ErrorKind::WouldBlock {
+                return Err(err).with_context(|| {
+                    format!("failed to send notification to PID [{}]", self.peer_pid)
+                });
+            }
+            std::thread::yield_now();
+        }
+    }
+}
+
+/// Receives notifications from a peer process, via a dedicated thread blocked in `sigwaitinfo`
+#[derive(Debug)]
+struct SignalListener {
+    /// Sequence numbers forwarded by the listening thread
+    ///
+    /// NOTE: the listening thread is never joined, it lives as long as the process
+    seqs: mpsc::Receiver<usize>,
+
+    /// Sequence number of the last notification received
+    seq: usize,
+}
+
+impl SignalListener {
+    /// Spawn a thread that listens for notifications from a given peer process
+    ///
+    /// NOTE: [`block_notify_signal`] must have been called (by this thread) first
+    fn spawn(peer_pid: u32) -> Result<Self> {
+        let peer_pid = libc::pid_t::try_from(peer_pid).context("invalid peer PID")?;
+        let set = notify_signal_set()?;
+        let (tx, rx) = mpsc::channel();
+        std::thread::Builder::new()
+            .name("signal-listener".into())
+            .spawn(move || loop {
+                // SAFETY: the set is valid, and info is written by the kernel before it is read
+                let mut info = unsafe { std::mem::zeroed::<libc::siginfo_t>() };
+                if unsafe { libc::sigwaitinfo(&set, &mut info) } < 0 {
+                    // EINTR is the only error that can occur with a valid set
+                    continue;
+                }
+                // SAFETY: the signal was sent with sigqueue, so the PID and value are present
+                let (pid, value) = unsafe { (info.si_pid(), info.si_value()) };
+                if pid != peer_pid {
+                    debug!(pid, "ignoring notification from unexpected process");
+                    continue;
+                }
+                if tx.send(value.sival_ptr as usize).is_err() {
+                    return;
+                }
+            })
+            .context("failed to spawn signal listener thread")?;
+
+        Ok(Self { seqs: rx, seq: 0 })
+    }
+
+    /// Wait for the next notification
+    fn wait(&mut self) -> Result<()> {
+        let seq = self.seqs.recv().context("signal listener thread exited")?;
+        self.seq += 1;
+        ensure!(
+            seq == self.seq,
+            "received notification [{seq}] out of order (expected [{}])",
+            self.seq
+        );
+        Ok(())
+    }
+}
+
+/// A region of shared memory that holds a single fragment of a message
+struct SignalRegion {
+    /// Shared memory segment
+    shmem: Shmem,
+
+    /// Size of the region in bytes
+    size_bytes: usize,
+}
+
+impl std::fmt::Debug for SignalRegion {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SignalRegion")
+            .field("os_id", &self.shmem.get_os_id())
+            .field("size_bytes", &self.size_bytes)
+            .finish()
+    }
+}
+
+impl SignalRegion {
+    /// Create a new [`SignalRegion`] of a given size
+    fn new(size_bytes: usize) -> Result<Self> {
+        ensure!(
+            size_bytes > LEN_PREFIX_SIZE_BYTES + FRAGMENT_HEADER_SIZE_BYTES,
+            "region size [{size_bytes}] is too small to hold any message"
+        );
+        let shmem = ShmemConf::new()
+            .size(size_bytes)
+            .create()
+            .context("failed to create shared memory")?;
+        Ok(Self { shmem, size_bytes })
+    }
+
+    /// Open an existing [`SignalRegion`] from a [`SerializedSignalRegion`]
+    fn from_serialized(
+        SerializedSignalRegion { os_id, size_bytes }: SerializedSignalRegion,
+    ) -> Result<Self> {
+        let shmem = ShmemConf::new()
+            .os_id(&os_id)
+            .open()
+            .with_context(|| format!("failed to open shared memory with OS ID [{os_id}]"))?;
+        ensure!(
+            shmem.len() >= size_bytes,
+            "shared memory [{os_id}] is smaller than expected size [{size_bytes}]"
+        );
+        Ok(Self { shmem, size_bytes })
+    }
+
+    /// Create a serialized version of the [`SignalRegion`] to send
+    fn to_serialized(&self) -> SerializedSignalRegion {
+        SerializedSignalRegion {
+            os_id: self.shmem.get_os_id().to_string(),
+            size_bytes: self.size_bytes,
+        }
+    }
+
+    /// Get the max fragment size (not including the length prefix)
+    #[must_use]
+    fn max_fragment_size(&self) -> usize {
+        self.size_bytes - LEN_PREFIX_SIZE_BYTES
+    }
+
+    /// Write a single fragment into the region
+    ///
+    /// NOTE: the reader must not be reading the region (i.e. it must have consumed the previous fragment)
+    fn write_fragment(&mut self, header: FragmentHeader, chunk: &[u8]) -> Result<()> {
+        let fragment_len = FRAGMENT_HEADER_SIZE_BYTES + chunk.len();
+        ensure!(
+            fragment_len <= self.max_fragment_size(),
+            "fragment of len [{fragment_len}] does not fit in region of size [{}]",
+            self.size_bytes
+        );
+
+        // SAFETY: the reader does not touch the region until it is notified
+        let bytes = unsafe { &mut self.shmem.as_slice_mut()[..self.size_bytes] };
+        let (len_bytes, fragment_bytes) = bytes.split_at_mut(LEN_PREFIX_SIZE_BYTES);
+        len_bytes.copy_from_slice(
+            &u64::try_from(fragment_len)
+                .context("failed to convert fragment len to u64")?
+                .to_le_bytes(),
+        );
+        fragment_bytes[..FRAGMENT_HEADER_SIZE_BYTES].copy_from_slice(&header.to_bytes());
+        fragment_bytes[FRAGMENT_HEADER_SIZE_BYTES..fragment_len].copy_from_slice(chunk);
+        fence(Ordering::Release);
+
+        Ok(())
+    }
+
+    /// Retrieve the bytes of the fragment currently in the region
+    fn fragment_bytes(&self) -> Result<&[u8]> {
+        fence(Ordering::Acquire);
+        // SAFETY: the writer does not touch the region until it receives an ack (or a response)
+        let bytes = unsafe { &self.shmem.as_slice()[..self.size_bytes] };
+        let fragment_len = usize::try_from(u64::from_le_bytes(
+            bytes[..LEN_PREFIX_SIZE_BYTES]
+                .try_into()
+                .context("unexpectedly invalid byte range for LE u64")?,
+        ))
+        .context("failed to convert u64 len into usize")?;
+        ensure!(
+            fragment_len <= self.max_fragment_size(),
+            "invalid length prefix [{fragment_len}] for region of size [{}]",
+            self.size_bytes
+        );
+        Ok(&bytes[LEN_PREFIX_SIZE_BYTES..LEN_PREFIX_SIZE_BYTES + fragment_len])
+    }
+}
+
+/// Bi-directional handle to a peer process, over a pair of [`SignalRegion`]s
+#[derive(Debug)]
+struct SignalHandle {
+    /// Region that this side writes to
+    outgoing: SignalRegion,
+
+    /// Region that this side reads from
+    incoming: SignalRegion,
+
+    /// Notifier for the peer (sent after every write)
+    notifier: SignalNotifier,
+
+    /// Listener for notifications from the peer (waited on before every read)
+    listener: SignalListener,
+
+    /// Codec used to encode messages
+    codec: RpcCodec,
+
+    /// Scratch buffer that messages are serialized into, before being split into fragments
+    buf: Vec<u8>,
+
+    /// Splits outgoing messages into fragments
+    fragmenter: Fragmenter,
+
+    /// Reassembles incoming messages out of fragments
+    reassembler: Reassembler,
+}
+
+impl SignalHandle {
+    /// Write a message (one fragment at a time), notifying the peer after every fragment
+    fn write_message<T: Serialize + ProtoMessage>(&mut self, obj: &T) -> Result<usize> {
+        let mut buf = std::mem::take(&mut self.buf);
+        buf.clear();
+        self.codec.encode_into(obj, &mut buf)?;
+
+        let max_chunk_size = self.outgoing.max_fragment_size() - FRAGMENT_HEADER_SIZE_BYTES;
+        for (header, chunk) in self.fragmenter.fragments(&buf, max_chunk_size)? {
+            if header.fragment_index > 0 {
+                // Wait for the peer to acknowledge the previous fragment
+                self.listener
+                    .wait()
+                    .context("failed to wait for fragment ack")?;
+            }
+            self.outgoing.write_fragment(header, chunk)?;
+            self.notifier.notify().context("failed to notify peer")?;
+        }
+
+        let msg_len = buf.len();
+        self.buf = buf;
+        debug!(msg_len, "wrote message and notified peer");
+        Ok(msg_len)
+    }
+
+    /// Wait to be notified by the peer, and read a message (acknowledging all fragments but the last)
+    fn read_message<T: DeserializeOwned + ProtoMessage>(&mut self) -> Result<T> {
+        self.listener
+            .wait()
+            .context("failed to wait for notification")?;
+
+        let in_place = loop {
+            let (header, chunk) = FragmentHeader::split(self.incoming.fragment_bytes()?)?;
+            if self.reassembler.is_whole_message(&header, chunk)? {
+                break true;
+            }
+            if self.reassembler.push(header, chunk)? {
+                break false;
+            }
+            // Acknowledge the fragment, so the peer can write the next one
+            self.notifier.notify().context("failed to ack fragment")?;
+            self.listener
+                .wait()
+                .context("failed to wait for notification")?;
+        };
+
+        if in_place {
+            self.codec
+                .decode(FragmentHeader::split(self.incoming.fragment_bytes()?)?.1)
+        } else {
+            self.codec.decode(self.reassembler.message())
+        }
+    }
+}
@@ -0,0 +1,206 @@
+//! Parent-specific IPC implementation over shared memory, notified with realtime signals
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::io::Write;
+use std::process::{Child, Command, Stdio};
+
+use anyhow::{ensure, Context as _, Result};
+use tracing::{debug, info};
+use uuid::Uuid;
+
+use crate::codec::RpcCodec;
+use crate::latency::OneWayLatencies;
+use crate::shmem::framing::{Fragmenter, Reassembler};
+use crate::shmem::signal::{
+    block_notify_signal, SignalHandle, SignalInit, SignalInitResponse, SignalListener,
+    SignalNotifier, SignalRegion, DEFAULT_SHARED_MEM_SIGNAL_SLAB_SIZE_BYTES,
+};
+use crate::{
+    get_monotonic_time_nanos, payload_size_bytes_from_env_or_default, synthetic_payload, ChildId,
+    ChildName, ParentProcess, PingMessage, Pinger, PongMessage, RpcPong,
+};
+
+/// Bi-directional channel for communication
+#[derive(Debug)]
+struct SignalChannel {
+    /// Self-reported ID of the child
+    child_id: ChildId,
+
+    /// Handle over the regions shared with the child
+    handle: SignalHandle,
+}
+
+/// A parent process that performs IPC via shared memory, notified with realtime signals
+pub struct SignalParent {
+    /// UUID of the parent process
+    uuid: Uuid,
+
+    /// Synthetic payload sent with every ping (see `RPC_PAYLOAD_SIZE_BYTES`)
+    payload: Vec<u8>,
+
+    /// Codec used to encode messages (sent to children in the init message)
+    codec: RpcCodec,
+
+    /// Size (in bytes) of the region used for each direction
+    slab_size_bytes: usize,
+
+    /// Channels for communicating with children, by child name
+    ///
+    /// SAFETY: We're safe using a `RefCell` here because this structure
+    /// is very much *not* multi-threaded.
+    channels: HashMap<ChildName, RefCell<SignalChannel>>,
+}
+
+impl std::fmt::Debug for SignalParent {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SignalParent")
+            .field("uuid", &self.uuid)
+            .finish()
+    }
+}
+
+impl Default for SignalParent {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl SignalParent {
+    /// Create a [`SignalParent`]
+    #[must_use]
+    pub fn new() -> Self {
+        let slab_size_bytes = std::env::var("SHARED_MEM_SIGNAL_SLAB_SIZE_BYTES")
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(DEFAULT_SHARED_MEM_SIGNAL_SLAB_SIZE_BYTES);
+        Self {
+            uuid: Uuid::now_v7(),
+            payload: synthetic_payload(payload_size_bytes_from_env_or_default(std::env::vars())),
+            codec: RpcCodec::from_env_or_default(std::env::vars()),
+            slab_size_bytes,
+            channels: HashMap::new(),
+        }
+    }
+}
+
+impl ParentProcess for SignalParent {
+    fn id(&self) -> String {
+        self.uuid.to_string()
+    }
+
+    fn spawn_child(&mut self, name: impl AsRef<str>, mut cmd: Command) -> Result<Child> {
+        ensure!(
+            self.channels.is_empty(),
+            "only one child is supported, as notifications are sent to the (whole) parent process"
+        );
+
+        // Block the notification signal *before* the child can possibly send one
+        block_notify_signal()?;
+
+        info!(
+            slab_size_bytes = self.slab_size_bytes,
+            "determined region size"
+        );
+        debug!("creating regions");
+        let to_child =
+            SignalRegion::new(self.slab_size_bytes).context("failed to create region to child")?;
+        let from_child = SignalRegion::new(self.slab_size_bytes)
+            .context("failed to create region from child")?;
+
+        let init_msg = SignalInit {
+            parent_id: self.id(),
+            codec: self.codec,
+            parent_pid: std::process::id(),
+            to_child: to_child.to_serialized(),
+            from_child: from_child.to_serialized(),
+        };
+
+        // Spawn the child
+        debug!("spawning child");
+        let mut child = cmd
+            .stdin(Stdio::piped())
+            .spawn()
+            .context("failed to spawn child process")?;
+
+        // Send the init message over stdin
+        debug!(?init_msg, "writing init to child STDIN");
+        let mut child_stdin = child.stdin.take().context("failed to get child STDIN")?;
+        child_stdin
+            .write_all(&serde_json::to_vec(&init_msg).context("failed to serialize init msg")?)
+            .context("failed to write init msg")?;
+        child_stdin
+            .write_all(b"\r\n")
+            .context("failed to write new line")?;
+        child_stdin.flush().context("failed to flush child STDIN")?;
+
+        let mut handle = SignalHandle {
+            outgoing: to_child,
+            incoming: from_child,
+            notifier: SignalNotifier::new(child.id())?,
+            listener: SignalListener::spawn(child.id())?,
+            codec: self.codec,
+            buf: Vec::new(),
+            fragmenter: Fragmenter::default(),
+            reassembler: Reassembler::from_env_or_default(std::env::vars()),
+        };
+
+        // Wait for the child to respond over shared memory
+        debug!("waiting for init response from child");
+        let init_resp: SignalInitResponse = handle
+            .read_message()
+            .context("failed to read init response message")?;
+        ensure!(
+            init_resp.parent_id == self.id(),
+            "parent ID reported by child did not match"
+        );
+        debug!(child_id = init_resp.child_id, "received init response");
+
+        // Save information to local registry
+        self.channels.insert(
+            name.as_ref().into(),
+            RefCell::new(SignalChannel {
+                child_id: init_resp.child_id,
+                handle,
+            }),
+        );
+
+        Ok(child)
+    }
+}
+
+impl Pinger for SignalParent {
+    fn roundtrip_ping(&self, child_name: impl AsRef<str>) -> Result<Option<OneWayLatencies>> {
+        let child = child_name.as_ref();
+        let mut chan = self
+            .channels
+            .get(child)
+            .with_context(|| format!("failed to find child [{child}]"))?
+            .borrow_mut();
+        let SignalChannel { child_id, handle } = &mut *chan;
+
+        debug!(child, "sending ping message to child");
+        handle
+            .write_message(&PingMessage {
+                sender_id: self.id(),
+                receiver_id: child_id.clone(),
+                sent_at_ns: get_monotonic_time_nanos()?,
+                payload: self.payload.clone(),
+            })
+            .context("failed to send ping message to child")?;
+
+        debug!(child, "reading pong message from child");
+        let pong_msg: PongMessage = handle
+            .read_message()
+            .context("failed to read pong message")?;
+        let latencies = pong_msg.one_way_latencies()?;
+        ensure!(pong_msg.sender_id() == child_id, "child ID matches");
+        ensure!(pong_msg.receiver_id() == self.id(), "parent ID matches");
+        ensure!(
+            pong_msg.payload() == self.payload.as_slice(),
+            "payload matches"
+        );
+
+        Ok(latencies)
+    }
+}
@@ -0,0 +1,86 @@
+/*!
+Zero-copy reads of messages out of shared memory, via [`rkyv`][crate-rkyv].
+
+When enabled (see [`ShmemReadMode`]), ping and pong messages are written as `rkyv` archives, and
+readers validate & access the archived message *in place* in the mapped region, rather than
+copying it out and decoding it with a codec (see [`crate::codec`]).
+
+Init messages are unaffected, and are always encoded with the configured codec.
+
+NOTE: `rkyv` is built with the `unaligned` feature, so that archived messages can be accessed
+wherever they happen to land in shared memory (ex. right after a length prefix).
+
+[crate-rkyv]: <https://crates.io/crates/rkyv>
+**/
+
+use anyhow::{anyhow, bail, Result};
+use rkyv::api::high::{HighSerializer, HighValidator};
+use rkyv::bytecheck::CheckBytes;
+use rkyv::rancor;
+use rkyv::ser::allocator::ArenaHandle;
+use rkyv::util::AlignedVec;
+use rkyv::Portable;
+use serde::{Deserialize, Serialize};
+
+/// ENV variable for setting how messages are read out of shared memory
+const ENV_VAR_SHARED_MEM_READ_MODE: &str = "SHARED_MEM_READ_MODE";
+
+/// How ping/pong messages are read out of shared memory
+///
+/// This is normally configured via ENV ("SHARED_MEM_READ_MODE"), and is supported by
+/// the `raw_sync` and `shared_mem_queue` transports.
+#[derive(Debug, Default, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum ShmemReadMode {
+    /// Messages are encoded with the configured codec, then copied out of shared memory and decoded
+    #[default]
+    Decode,
+    /// Messages are archived with `rkyv`, then validated & accessed in place in shared memory
+    ZeroCopy,
+}
+
+impl std::str::FromStr for ShmemReadMode {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "decode" => Ok(Self::Decode),
+            "zero-copy" => Ok(Self::ZeroCopy),
+            _ => bail!("invalid ShmemReadMode value [{s}]"),
+        }
+    }
+}
+
+impl ShmemReadMode {
+    /// Retreive from env or use the default (Decode)
+    pub fn from_env_or_default(values: impl Iterator<Item = (String, String)>) -> Self {
+        for (k, v) in values {
+            if k == ENV_VAR_SHARED_MEM_READ_MODE {
+                return <Self as std::str::FromStr>::from_str(&v).unwrap_or(Self::default());
+            }
+        }
+        Self::default()
+    }
+}
+
+/// Archive a message with `rkyv`
+pub(crate) fn archive<T>(obj: &T) -> Result<AlignedVec>
+where
+    T: for<'a> rkyv::Serialize<HighSerializer<AlignedVec, ArenaHandle<'a>, rancor::Error>>,
+{
+    rkyv::to_bytes::<rancor::Error>(obj)
+        .map_err(|e| anyhow!("failed to archive [{}]: {e}", std::any::type_name::<T>()))
+}
+
+/// Validate an archived message, and access it in place (without copying or deserializing it)
+pub(crate) fn access<T>(bytes: &[u8]) -> Result<&T>
+where
+    T: Portable + for<'a> CheckBytes<HighValidator<'a, rancor::Error>>,
+{
+    rkyv::access::<T, rancor::Error>(bytes).map_err(|e| {
+        anyhow!(
+            "failed to validate archived [{}]: {e}",
+            std::any::type_name::<T>()
+        )
+    })
+}
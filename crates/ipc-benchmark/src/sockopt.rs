@@ -0,0 +1,176 @@
+//! Configurable socket options, shared by the loopback ([`crate::tcp`], [`crate::udp`]) transports
+
+use std::os::fd::AsFd;
+
+use anyhow::{Context as _, Result};
+use nix::sys::socket::{setsockopt, sockopt};
+use serde::{Deserialize, Serialize};
+use tracing::debug;
+
+/// ENV variable for enabling/disabling `TCP_NODELAY` (TCP only)
+const ENV_VAR_SOCKET_TCP_NODELAY: &str = "SOCKET_TCP_NODELAY";
+
+/// ENV variable for setting `SO_BUSY_POLL` (in microseconds, Linux only)
+const ENV_VAR_SOCKET_BUSY_POLL_MICROS: &str = "SOCKET_BUSY_POLL_MICROS";
+
+/// ENV variable for setting `SO_RCVBUF`
+const ENV_VAR_SOCKET_RECV_BUFFER_SIZE_BYTES: &str = "SOCKET_RECV_BUFFER_SIZE_BYTES";
+
+/// ENV variable for setting `SO_SNDBUF`
+const ENV_VAR_SOCKET_SEND_BUFFER_SIZE_BYTES: &str = "SOCKET_SEND_BUFFER_SIZE_BYTES";
+
+/// Options applied to every socket used by a loopback transport (on both parent and child).
+///
+/// This is normally configured via ENV by the parent, and sent to the child as part of the
+/// init payload so that both sides of a connection are configured identically.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub(crate) struct SocketOptions {
+    /// Whether to disable Nagle's algorithm (`TCP_NODELAY`), ignored for UDP
+    ///
+    /// This is enabled by default, as ping-pong traffic is exactly the case Nagle's algorithm penalizes.
+    pub(crate) tcp_nodelay: bool,
+
+    /// Microseconds to busy poll on a blocking receive (`SO_BUSY_POLL`), if set
+    pub(crate) busy_poll_micros: Option<u32>,
+
+    /// Size of the kernel receive buffer (`SO_RCVBUF`), if set
+    pub(crate) recv_buffer_size_bytes: Option<usize>,
+
+    /// Size of the kernel send buffer (`SO_SNDBUF`), if set
+    pub(crate) send_buffer_size_bytes: Option<usize>,
+}
+
+impl Default for SocketOptions {
+    fn default() -> Self {
+        Self {
+            tcp_nodelay: true,
+            busy_poll_micros: None,
+            recv_buffer_size_bytes: None,
+            send_buffer_size_bytes: None,
+        }
+    }
+}
+
+impl SocketOptions {
+    /// Build [`SocketOptions`] from env, using defaults for any values that are missing or invalid
+    pub(crate) fn from_env_or_default(values: impl Iterator<Item = (String, String)>) -> Self {
+        let mut opts = Self::default();
+        for (k, v) in values {
+            match k.as_str() {
+                ENV_VAR_SOCKET_TCP_NODELAY => {
+                    opts.tcp_nodelay = v.parse().unwrap_or(opts.tcp_nodelay);
+                }
+                ENV_VAR_SOCKET_BUSY_POLL_MICROS => opts.busy_poll_micros = v.parse().ok(),
+                ENV_VAR_SOCKET_RECV_BUFFER_SIZE_BYTES => {
+                    opts.recv_buffer_size_bytes = v.parse().ok();
+                }
+                ENV_VAR_SOCKET_SEND_BUFFER_SIZE_BYTES => {
+                    opts.send_buffer_size_bytes = v.parse().ok();
+                }
+                _ => {}
+            }
+        }
+        opts
+    }
+
+    /// Apply the options that are relevant to any socket (i.e. everything but `TCP_NODELAY`)
+    pub(crate) fn apply(&self, socket: &impl AsFd) -> Result<()> {
+        debug!(opts = ?self, "applying socket options");
+        if let Some(size) = self.recv_buffer_size_bytes {
+            setsockopt(socket, sockopt::RcvBuf, &size)
+                .with_context(|| format!("failed to set SO_RCVBUF to [{size}]"))?;
+        }
+        if let Some(size) = self.send_buffer_size_bytes {
+            setsockopt(socket, sockopt::SndBuf, &size)
+                .with_context(|| format!("failed to set SO_SNDBUF to [{size}]"))?;
+        }
+        if let Some(micros) = self.busy_poll_micros {
+            set_busy_poll(socket, micros)?;
+        }
+        Ok(())
+    }
+
+    /// Apply all options to a TCP stream
+    pub(crate) fn apply_tcp(&self, stream: &std::net::TcpStream) -> Result<()> {
+        stream
+            .set_nodelay(self.tcp_nodelay)
+            .with_context(|| format!("failed to set TCP_NODELAY to [{}]", self.tcp_nodelay))?;
+        self.apply(stream)
+    }
+}
+
+/// Set `SO_BUSY_POLL` on a socket
+///
+/// NOTE: `nix` does not (yet) expose this option, so we must go through `libc`
+#[cfg(target_os = "linux")]
+fn set_busy_poll(socket: &impl AsFd, micros: u32) -> Result<()> {
+    use std::os::fd::AsRawFd as _;
+
+    let value = libc::c_int::try_from(micros)
+        .with_context(|| format!("invalid SO_BUSY_POLL value [{micros}]"))?;
+    // SAFETY: the fd is valid for the lifetime of `socket`, and `value` outlives the call
+    let rc = unsafe {
+        libc::setsockopt(
+            socket.as_fd().as_raw_fd(),
+            libc::SOL_SOCKET,
+            libc::SO_BUSY_POLL,
+            std::ptr::from_ref(&value).cast(),
+            libc::socklen_t::try_from(size_of::<libc::c_int>())
+                .context("failed to convert c_int size to socklen_t")?,
+        )
+    };
+    if rc != 0 {
+        return Err(std::io::Error::last_os_error())
+            .with_context(|| format!("failed to set SO_BUSY_POLL to [{micros}]"));
+    }
+    Ok(())
+}
+
+/// Set `SO_BUSY_POLL` on a socket
+///
+/// NOTE: `SO_BUSY_POLL` is Linux-only
+#[cfg(not(target_os = "linux"))]
+fn set_busy_poll(_socket: &impl AsFd, micros: u32) -> Result<()> {
+    anyhow::bail!("SO_BUSY_POLL [{micros}] is not supported on this platform")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Build env values out of key/value pairs
+    fn env(pairs: &[(&str, &str)]) -> impl Iterator<Item = (String, String)> {
+        pairs
+            .iter()
+            .map(|(k, v)| ((*k).to_string(), (*v).to_string()))
+            .collect::<Vec<_>>()
+            .into_iter()
+    }
+
+    /// Options are read from env, and missing or invalid values fall back to defaults
+    #[test]
+    fn from_env_or_default() {
+        assert_eq!(
+            SocketOptions::from_env_or_default(env(&[])),
+            SocketOptions::default()
+        );
+        assert_eq!(
+            SocketOptions::from_env_or_default(env(&[
+                ("SOCKET_TCP_NODELAY", "false"),
+                ("SOCKET_BUSY_POLL_MICROS", "50"),
+                ("SOCKET_RECV_BUFFER_SIZE_BYTES", "262144"),
+                ("SOCKET_SEND_BUFFER_SIZE_BYTES", "invalid"),
+                ("UNRELATED", "1"),
+            ])),
+            SocketOptions {
+                tcp_nodelay: false,
+                busy_poll_micros: Some(50),
+                recv_buffer_size_bytes: Some(262_144),
+                send_buffer_size_bytes: None,
+            }
+        );
+        assert!(
+            SocketOptions::from_env_or_default(env(&[("SOCKET_TCP_NODELAY", "maybe")])).tcp_nodelay
+        );
+    }
+}
@@ -0,0 +1,119 @@
+//! Length-prefixed message framing over plain byte streams (ex. sockets, pipes)
+
+use std::io::{BufReader, Read, Write};
+
+use anyhow::{Context as _, Result};
+use serde::{de::DeserializeOwned, Serialize};
+use tracing::debug;
+
+use crate::codec::{Codec as _, RpcCodec};
+use crate::proto::ProtoMessage;
+
+/// Size of the length prefix that precedes every message
+const LEN_PREFIX_SIZE_BYTES: usize = size_of::<u64>();
+
+/// A handle over a pair of byte streams that can be used to send and receive whole messages
+///
+/// Messages are encoded (with the configured [`RpcCodec`]) and `u64` LE length-prefixed, the same way that
+/// [`crate::shmem::raw_sync`] and [`crate::shmem::shared_mem_queue`] lay them out in shared memory.
+///
+/// The reader and the writer may be the same underlying object (ex. a cloned socket),
+/// or two entirely different objects (ex. the two ends of a pair of pipes).
+pub(crate) struct StreamHandle<R, W>
+where
+    R: Read,
+    W: Write,
+{
+    /// Stream from which messages are read
+    reader: BufReader<R>,
+
+    /// Stream to which messages are written
+    writer: W,
+
+    /// Scratch buffer that is reused for every read and write, to avoid allocations
+    buf: Vec<u8>,
+
+    /// Codec used to encode/decode messages
+    codec: RpcCodec,
+}
+
+impl<R, W> std::fmt::Debug for StreamHandle<R, W>
+where
+    R: Read,
+    W: Write,
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("StreamHandle")
+            .field("buf_capacity", &self.buf.capacity())
+            .field("codec", &self.codec)
+            .finish()
+    }
+}
+
+impl<R, W> StreamHandle<R, W>
+where
+    R: Read,
+    W: Write,
+{
+    /// Create a new [`StreamHandle`] from a reader and a writer
+    pub(crate) fn new(reader: R, writer: W, codec: RpcCodec) -> Self {
+        Self {
+            reader: BufReader::new(reader),
+            writer,
+            buf: Vec::new(),
+            codec,
+        }
+    }
+
+    /// Perform a blocking read of a single message from the underlying reader
+    pub(crate) fn read_message<T: DeserializeOwned + ProtoMessage>(&mut self) -> Result<T> {
+        let mut len_bytes = [0u8; LEN_PREFIX_SIZE_BYTES];
+        self.reader
+            .read_exact(&mut len_bytes)
+            .context("failed to read length prefix from stream")?;
+        let len = usize::try_from(u64::from_le_bytes(len_bytes))
+            .context("failed to convert u64 len into usize")?;
+        debug!(len, "read length prefix from stream");
+
+        self.buf.resize(len, 0);
+        self.reader
+            .read_exact(&mut self.buf[..len])
+            .with_context(|| format!("failed to read message of len [{len}] from stream"))?;
+
+        self.codec.decode(&self.buf[..len])
+    }
+
+    /// Perform a blocking write of a single message to the underlying writer
+    ///
+    /// The length prefix and the message are written with a single call, so that
+    /// message-oriented transports see exactly one write per message.
+    pub(crate) fn write_message<T: Serialize + ProtoMessage>(&mut self, obj: &T) -> Result<usize> {
+        self.buf.clear();
+        self.buf.extend_from_slice(&[0u8; LEN_PREFIX_SIZE_BYTES]);
+        self.codec.encode_into(obj, &mut self.buf)?;
+
+        let msg_len = self.buf.len() - LEN_PREFIX_SIZE_BYTES;
+        self.buf[..LEN_PREFIX_SIZE_BYTES].copy_from_slice(
+            &u64::try_from(msg_len)
+                .context("failed to convert msg len to u64")?
+                .to_le_bytes(),
+        );
+
+        self.writer
+            .write_all(&self.buf)
+            .context("failed to write message to stream")?;
+        self.writer.flush().context("failed to flush stream")?;
+        debug!(msg_len, "wrote message to stream");
+
+        Ok(msg_len)
+    }
+}
+
+/// Check whether an error was caused by the other side of a stream hanging up
+///
+/// Children use this to exit cleanly when the parent goes away, rather than reporting an error.
+pub(crate) fn is_eof(err: &anyhow::Error) -> bool {
+    err.chain()
+        .filter_map(|e| e.downcast_ref::<std::io::Error>())
+        .any(|e| e.kind() == std::io::ErrorKind::UnexpectedEof)
+}
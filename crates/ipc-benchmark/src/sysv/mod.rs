@@ -0,0 +1,45 @@
+/*!
+Implementations for parent-child IPC with [System V IPC][man-sysvipc] primitives (Linux only).
+
+There are two implementations available:
+
+- [`msg`], a single message queue (`msgget`/`msgsnd`/`msgrcv`), with one message type per direction
+- [`shm`], a shared memory segment (`shmget`/`shmat`) guarded by a pair of semaphores (`semget`/`semop`)
+
+All objects are created with `IPC_PRIVATE` keys by the parent, and their IDs are sent to the
+child over STDIN (IDs are system-wide, so no key/path negotiation is necessary). The parent
+removes the objects (`IPC_RMID`) when it drops them.
+
+[man-sysvipc]: <https://man7.org/linux/man-pages/man7/sysvipc.7.html>
+**/
+
+use std::io;
+
+use libc::c_int;
+
+pub mod msg;
+pub mod shm;
+
+/// Permissions for every created object (read/write for the owner only)
+const IPC_PERMISSIONS: c_int = 0o600;
+
+/// Convert the return value of a System V IPC call into a [`Result`], reading `errno` on failure
+fn cvt(ret: c_int) -> io::Result<c_int> {
+    if ret == -1 {
+        Err(io::Error::last_os_error())
+    } else {
+        Ok(ret)
+    }
+}
+
+/// Check whether an error was caused by the parent removing the object that was being waited on
+///
+/// Children use this to exit cleanly when the parent goes away, rather than reporting an error.
+///
+/// NOTE: calls that were blocked at the time of removal fail with `EIDRM`, while calls made
+/// after removal fail with `EINVAL` (as the ID no longer exists).
+fn is_removed(err: &anyhow::Error) -> bool {
+    err.chain()
+        .filter_map(|e| e.downcast_ref::<io::Error>())
+        .any(|e| matches!(e.raw_os_error(), Some(libc::EIDRM | libc::EINVAL)))
+}
@@ -0,0 +1,101 @@
+//! Child-specific IPC implementation over a System V message queue
+
+use std::io::{stdin, BufRead};
+
+use anyhow::{ensure, Context as _, Result};
+use tracing::debug;
+use uuid::Uuid;
+
+use crate::sysv::is_removed;
+use crate::sysv::msg::{SysvMsgHandle, SysvMsgInit, SysvMsgInitResponse};
+use crate::{
+    get_monotonic_time_nanos, monotonic_nanos_since, ChildProcess, PingMessage, PongMessage,
+};
+
+/// A child process that performs IPC via a System V message queue
+#[derive(Debug)]
+pub struct SysvMsgChild {
+    /// UUID that should uniquely identify this process
+    uuid: Uuid,
+}
+
+impl Default for SysvMsgChild {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl SysvMsgChild {
+    /// Build a new [`SysvMsgChild`] with a random UUID
+    #[must_use]
+    pub fn new() -> Self {
+        Self {
+            uuid: Uuid::now_v7(),
+        }
+    }
+}
+
+impl ChildProcess for SysvMsgChild {
+    fn id(&self) -> String {
+        self.uuid.to_string()
+    }
+
+    fn run(self) -> Result<()> {
+        debug!("child process running");
+
+        debug!("reading sysv msg init from STDIN");
+        let mut s = String::new();
+        stdin().lock().read_line(&mut s)?;
+
+        // We expect to receive an init message on STDIN
+        let SysvMsgInit {
+            parent_id,
+            codec,
+            msqid,
+        } = serde_json::from_slice(s.as_bytes())
+            .context("failed to read init message from STDIN")?;
+
+        debug!(msqid, "using message queue");
+        let mut queue = SysvMsgHandle::open(msqid, codec);
+
+        queue
+            .write_message(&SysvMsgInitResponse {
+                parent_id: parent_id.clone(),
+                child_id: self.id(),
+            })
+            .context("failed to write init response to parent")?;
+        debug!("successfully wrote init response to parent");
+
+        // Enter reading/writing loop
+        debug!("entering read loop...");
+        loop {
+            let PingMessage {
+                sender_id,
+                receiver_id,
+                sent_at_ns,
+                payload,
+                ..
+            } = match queue.read_message() {
+                Ok(msg) => msg,
+                Err(e) if is_removed(&e) => {
+                    debug!("parent removed the message queue, exiting");
+                    return Ok(());
+                }
+                Err(e) => return Err(e.context("failed to read ping message")),
+            };
+            let ping_latency_ns = monotonic_nanos_since(sent_at_ns)?;
+            ensure!(sender_id == parent_id, "sender should be parent");
+            ensure!(receiver_id == self.id(), "receiver should be child");
+
+            queue
+                .write_message(&PongMessage {
+                    sender_id: self.id(),
+                    receiver_id: sender_id,
+                    sent_at_ns: get_monotonic_time_nanos()?,
+                    ping_latency_ns,
+                    payload,
+                })
+                .context("failed to send pong to parent")?;
+        }
+    }
+}
@@ -0,0 +1,229 @@
+/*!
+Process IPC over a [System V message queue][man-msgop] (`msgget`/`msgsnd`/`msgrcv`).
+
+A single queue is used for both directions, with the message type (`mtype`) identifying the
+direction ([`MSG_TYPE_TO_CHILD`], [`MSG_TYPE_TO_PARENT`]), so each side only receives
+messages meant for it.
+
+Every message is exactly one queue message, so no length prefix is necessary, but messages are
+limited to [`MAX_MSG_SIZE_BYTES`] (the Linux default for `/proc/sys/kernel/msgmax`).
+
+[man-msgop]: <https://man7.org/linux/man-pages/man2/msgop.2.html>
+**/
+
+use anyhow::{Context as _, Result};
+use libc::{c_int, c_long};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use tracing::{debug, warn};
+
+use crate::codec::{Codec as _, RpcCodec};
+use crate::proto::{impl_init_response_proto, ProtoMessage};
+use crate::sysv::{cvt, IPC_PERMISSIONS};
+
+pub mod child;
+pub mod parent;
+
+pub use child::SysvMsgChild;
+pub use parent::SysvMsgParent;
+
+/// Max size of a single message (in bytes)
+const MAX_MSG_SIZE_BYTES: usize = 8192;
+
+/// Message type for messages sent from the parent to the child
+const MSG_TYPE_TO_CHILD: c_long = 1;
+
+/// Message type for messages sent from the child to the parent
+const MSG_TYPE_TO_PARENT: c_long = 2;
+
+/// Message sent to child processes over STDIN that contains
+/// information necessary for the child to use the queue
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+struct SysvMsgInit {
+    /// UUID of the parent
+    parent_id: String,
+
+    /// Codec used to encode messages (after this init message)
+    codec: RpcCodec,
+
+    /// ID of the message queue (as returned by `msgget`)
+    msqid: c_int,
+}
+
+/// Message sent from the child process (via the queue) upon successful initialization
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
+struct SysvMsgInitResponse {
+    /// UUID of the parent
+    parent_id: String,
+
+    /// UUID of the child
+    child_id: String,
+}
+
+impl_init_response_proto!(SysvMsgInitResponse);
+
+/// Layout of a message, as expected by `msgsnd`/`msgrcv`
+#[repr(C)]
+struct RawMsg {
+    /// Message type (must be positive)
+    mtype: c_long,
+
+    /// Message contents
+    mtext: [u8; MAX_MSG_SIZE_BYTES],
+}
+
+/// A handle over a System V message queue, which sends and receives one message per queue message
+struct SysvMsgHandle {
+    /// ID of the message queue
+    msqid: c_int,
+
+    /// Whether this handle created the queue (and should remove it when dropped)
+    owned: bool,
+
+    /// Message type used for outgoing messages
+    send_mtype: c_long,
+
+    /// Message type used for incoming messages
+    recv_mtype: c_long,
+
+    /// Buffer that every message is serialized into or received into
+    buf: Box<RawMsg>,
+
+    /// Codec used to encode messages
+    codec: RpcCodec,
+}
+
+impl std::fmt::Debug for SysvMsgHandle {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SysvMsgHandle")
+            .field("msqid", &self.msqid)
+            .field("owned", &self.owned)
+            .field("send_mtype", &self.send_mtype)
+            .field("recv_mtype", &self.recv_mtype)
+            .field("codec", &self.codec)
+            .finish()
+    }
+}
+
+impl Drop for SysvMsgHandle {
+    fn drop(&mut self) {
+        if self.owned {
+            debug!(msqid = self.msqid, "removing message queue");
+            // SAFETY: IPC_RMID does not read the buffer argument
+            if let Err(e) =
+                cvt(unsafe { libc::msgctl(self.msqid, libc::IPC_RMID, std::ptr::null_mut()) })
+            {
+                warn!(msqid = self.msqid, "failed to remove message queue: {e}");
+            }
+        }
+    }
+}
+
+impl SysvMsgHandle {
+    /// Create a new (private) message queue, which will be removed on drop
+    ///
+    /// The handle sends messages to the child, and receives messages from the child.
+    fn create(codec: RpcCodec) -> Result<Self> {
+        // SAFETY: msgget has no memory safety requirements
+        let msqid =
+            cvt(unsafe { libc::msgget(libc::IPC_PRIVATE, libc::IPC_CREAT | IPC_PERMISSIONS) })
+                .context("failed to create message queue")?;
+        debug!(msqid, "created message queue");
+        Ok(Self::new(
+            msqid,
+            true,
+            MSG_TYPE_TO_CHILD,
+            MSG_TYPE_TO_PARENT,
+            codec,
+        ))
+    }
+
+    /// Use an existing message queue (created by the parent)
+    ///
+    /// The handle sends messages to the parent, and receives messages from the parent.
+    fn open(msqid: c_int, codec: RpcCodec) -> Self {
+        Self::new(msqid, false, MSG_TYPE_TO_PARENT, MSG_TYPE_TO_CHILD, codec)
+    }
+
+    /// Build a new [`SysvMsgHandle`]
+    fn new(
+        msqid: c_int,
+        owned: bool,
+        send_mtype: c_long,
+        recv_mtype: c_long,
+        codec: RpcCodec,
+    ) -> Self {
+        Self {
+            msqid,
+            owned,
+            send_mtype,
+            recv_mtype,
+            buf: Box::new(RawMsg {
+                mtype: 0,
+                mtext: [0; MAX_MSG_SIZE_BYTES],
+            }),
+            codec,
+        }
+    }
+
+    /// Perform a blocking read of a single message (of the type meant for this side)
+    fn read_message<T: DeserializeOwned + ProtoMessage>(&mut self) -> Result<T> {
+        let len = loop {
+            // SAFETY: the buffer is a valid RawMsg, with MAX_MSG_SIZE_BYTES of message space
+            let ret = unsafe {
+                libc::msgrcv(
+                    self.msqid,
+                    std::ptr::from_mut(&mut *self.buf).cast(),
+                    MAX_MSG_SIZE_BYTES,
+                    self.recv_mtype,
+                    0,
+                )
+            };
+            match usize::try_from(ret) {
+                Ok(len) => break len,
+                Err(_) => {
+                    let err = std::io::Error::last_os_error();
+                    if err.kind() != std::io::ErrorKind::Interrupted {
+                        return Err(err).with_context(|| {
+                            format!("failed to receive from message queue [{}]", self.msqid)
+                        });
+                    }
+                }
+            }
+        };
+        debug!(len, "received message");
+        self.codec.decode(&self.buf.mtext[..len])
+    }
+
+    /// Perform a blocking write of a single message (of the type meant for the other side)
+    fn write_message<T: Serialize + ProtoMessage>(&mut self, obj: &T) -> Result<usize> {
+        // Serialize directly into the message buffer, which fails if the message doesn't fit
+        let mut writer = &mut self.buf.mtext[..];
+        self.codec
+            .encode_to_writer(obj, &mut writer)
+            .with_context(|| format!("max message size [{MAX_MSG_SIZE_BYTES}]"))?;
+        let msg_len = MAX_MSG_SIZE_BYTES - writer.len();
+        self.buf.mtype = self.send_mtype;
+
+        loop {
+            // SAFETY: the buffer is a valid RawMsg, with msg_len bytes of message
+            let ret = unsafe {
+                libc::msgsnd(
+                    self.msqid,
+                    std::ptr::from_ref(&*self.buf).cast(),
+                    msg_len,
+                    0,
+                )
+            };
+            match cvt(ret) {
+                Ok(_) => break,
+                Err(e) if e.kind() == std::io::ErrorKind::Interrupted => continue,
+                Err(e) => {
+                    return Err(e).with_context(|| {
+                        format!("failed to send to message queue [{}]", self.msqid)
+                    })
+                }
+            }
+        }
+        Ok(msg_len)
+    }
+}
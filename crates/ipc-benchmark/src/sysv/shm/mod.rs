@@ -0,0 +1,484 @@
+/*!
+Process IPC over a [System V shared memory segment][man-shmop] (`shmget`/`shmat`), guarded by
+[System V semaphores][man-semop] (`semget`/`semop`).
+
+The segment is split in two halves, one per direction, and each half holds at most one
+`u64` LE length-prefixed fragment of a message at a time (see [`crate::shmem::framing`]).
+Each direction has a semaphore, which the writer increments after writing a fragment, and the reader
+decrements (blocking until it is positive) before reading.
+
+Readers acknowledge every fragment but the last one of a message by incrementing their own semaphore
+(as with the `eventfd`s of [`crate::shmem::memfd`]), which is unambiguous since the writer never waits
+for a reply before it has written the whole message.
+
+[man-shmop]: <https://man7.org/linux/man-pages/man2/shmop.2.html>
+[man-semop]: <https://man7.org/linux/man-pages/man2/semop.2.html>
+**/
+
+use std::ptr::NonNull;
+
+use anyhow::{ensure, Context as _, Result};
+use libc::{c_int, c_short, c_ushort};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use tracing::{debug, warn};
+
+use crate::codec::{Codec as _, RpcCodec};
+use crate::proto::{impl_init_response_proto, ProtoMessage};
+use crate::shmem::framing::{FragmentHeader, Fragmenter, Reassembler, FRAGMENT_HEADER_SIZE_BYTES};
+use crate::sysv::{cvt, IPC_PERMISSIONS};
+
+pub mod child;
+pub mod parent;
+
+pub use child::SysvShmChild;
+pub use parent::SysvShmParent;
+
+/// Default size of the slab used for *each direction* of shared memory
+const DEFAULT_SYSV_SHM_SLAB_SIZE_BYTES: usize = 128 * 1024;
+
+/// Size of the length prefix that precedes every fragment
+const LEN_PREFIX_SIZE_BYTES: usize = size_of::<u64>();
+
+/// Number of semaphores in the set
+const NUM_SEMAPHORES: usize = 2;
+
+/// Semaphore incremented by the parent when a message for the child is ready
+const SEM_TO_CHILD: c_ushort = 0;
+
+/// Semaphore incremented by the child when a message for the parent is ready
+const SEM_TO_PARENT: c_ushort = 1;
+
+/// `semctl` command to set the values of all semaphores in a set
+///
+/// NOTE: this is not exported by [`libc`] on Linux (see `<linux/sem.h>`)
+const SETALL: c_int = 17;
+
+/// Message sent to child processes over STDIN that contains
+/// information necessary for the child to attach to the segment and semaphores
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+struct SysvShmInit {
+    /// UUID of the parent
+    parent_id: String,
+
+    /// Codec used to encode messages (after this init message)
+    codec: RpcCodec,
+
+    /// ID of the shared memory segment (as returned by `shmget`)
+    shmid: c_int,
+
+    /// ID of the semaphore set (as returned by `semget`)
+    semid: c_int,
+
+    /// Size of the slab used for each direction (the segment is twice as large)
+    slab_size_bytes: usize,
+}
+
+/// Message sent from the child process (via shared memory) upon successful initialization
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
+struct SysvShmInitResponse {
+    /// UUID of the parent
+    parent_id: String,
+
+    /// UUID of the child
+    child_id: String,
+}
+
+impl_init_response_proto!(SysvShmInitResponse);
+
+/// A System V shared memory segment, attached to this process
+struct SharedSegment {
+    /// ID of the segment
+    shmid: c_int,
+
+    /// Address the segment is attached at
+    addr: NonNull<u8>,
+
+    /// Size of the segment in bytes
+    len: usize,
+
+    /// Whether this process created the segment (and should remove it when dropped)
+    owned: bool,
+}
+
+impl SharedSegment {
+    /// Create and attach a new (private) segment, which will be removed on drop
+    fn create(len: usize) -> Result<Self> {
+        // SAFETY: shmget has no memory safety requirements
+        let shmid =
+            cvt(unsafe { libc::shmget(libc::IPC_PRIVATE, len, libc::IPC_CREAT | IPC_PERMISSIONS) })
+                .with_context(|| {
+                    format!("failed to create shared memory segment of len [{len}]")
+                })?;
+        debug!(shmid, len, "created shared memory segment");
+        Self::attach(shmid, true)
+    }
+
+    /// Attach an existing segment
+    fn attach(shmid: c_int, owned: bool) -> Result<Self> {
+        // NOTE: if we fail to attach an owned segment, remove it so it isn't leaked
+        let remove_on_err = |e: anyhow::Error| {
+            if owned {
+                // SAFETY: IPC_RMID does not read the buffer argument
+                unsafe { libc::shmctl(shmid, libc::IPC_RMID, std::ptr::null_mut()) };
+            }
+            e
+        };
+
+        // SAFETY: shmid_ds is plain old data, and is filled in by IPC_STAT
+        let mut stat: libc::shmid_ds = unsafe { std::mem::zeroed() };
+        cvt(unsafe { libc::shmctl(shmid, libc::IPC_STAT, &mut stat) })
+            .with_context(|| format!("failed to stat shared memory segment [{shmid}]"))
+            .map_err(remove_on_err)?;
+
+        // SAFETY: we let the kernel choose the address, and check for failure below
+        let addr = unsafe { libc::shmat(shmid, std::ptr::null(), 0) };
+        if addr as isize == -1 {
+            return Err(remove_on_err(
+                anyhow::Error::from(std::io::Error::last_os_error())
+                    .context(format!("failed to attach shared memory segment [{shmid}]")),
+            ));
+        }
+        let addr = NonNull::new(addr.cast::<u8>())
+            .context("unexpectedly null shared memory address")
+            .map_err(remove_on_err)?;
+
+        Ok(Self {
+            shmid,
+            addr,
+            len: stat.shm_segsz,
+            owned,
+        })
+    }
+
+    /// Get the (entire) segment as a slice
+    fn as_slice(&self) -> &[u8] {
+        // SAFETY: the segment is attached for the lifetime of self, and is len bytes long
+        unsafe { std::slice::from_raw_parts(self.addr.as_ptr(), self.len) }
+    }
+
+    /// Get the (entire) segment as a mutable slice
+    fn as_mut_slice(&mut self) -> &mut [u8] {
+        // SAFETY: the segment is attached for the lifetime of self, and is len bytes long
+        unsafe { std::slice::from_raw_parts_mut(self.addr.as_ptr(), self.len) }
+    }
+}
+
+impl Drop for SharedSegment {
+    fn drop(&mut self) {
+        // SAFETY: the segment was attached at this address, and is never used again after this point
+        if let Err(e) = cvt(unsafe { libc::shmdt(self.addr.as_ptr().cast()) }) {
+            warn!(
+                shmid = self.shmid,
+                "failed to detach shared memory segment: {e}"
+            );
+        }
+        if self.owned {
+            debug!(shmid = self.shmid, "removing shared memory segment");
+            // SAFETY: IPC_RMID does not read the buffer argument
+            if let Err(e) =
+                cvt(unsafe { libc::shmctl(self.shmid, libc::IPC_RMID, std::ptr::null_mut()) })
+            {
+                warn!(
+                    shmid = self.shmid,
+                    "failed to remove shared memory segment: {e}"
+                );
+            }
+        }
+    }
+}
+
+/// A System V semaphore set
+struct SemaphoreSet {
+    /// ID of the semaphore set
+    semid: c_int,
+
+    /// Whether this process created the set (and should remove it when dropped)
+    owned: bool,
+}
+
+impl SemaphoreSet {
+    /// Create a new (private) set of [`NUM_SEMAPHORES`] semaphores (all zero), which will be removed on drop
+    fn create() -> Result<Self> {
+        // SAFETY: semget has no memory safety requirements
+        let semid = cvt(unsafe {
+            libc::semget(
+                libc::IPC_PRIVATE,
+                NUM_SEMAPHORES as c_int,
+                libc::IPC_CREAT | IPC_PERMISSIONS,
+            )
+        })
+        .context("failed to create semaphore set")?;
+        let set = Self { semid, owned: true };
+
+        // NOTE: Linux zeroes new semaphores, but POSIX leaves initial values unspecified
+        let values = [0 as c_ushort; NUM_SEMAPHORES];
+        // SAFETY: SETALL reads NUM_SEMAPHORES values from the given array
+        cvt(unsafe { libc::semctl(semid, 0, SETALL, values.as_ptr()) })
+            .context("failed to initialize semaphore set")?;
+        debug!(semid, "created semaphore set");
+        Ok(set)
+    }
+
+    /// Use an existing semaphore set
+    fn open(semid: c_int) -> Self {
+        Self {
+            semid,
+            owned: false,
+        }
+    }
+
+    /// Perform a single operation on a semaphore, retrying if interrupted
+    fn op(&self, sem_num: c_ushort, sem_op: c_short) -> Result<()> {
+        let mut op = libc::sembuf {
+            sem_num,
+            sem_op,
+            sem_flg: 0,
+        };
+        loop {
+            // SAFETY: exactly one operation is passed
+            match cvt(unsafe { libc::semop(self.semid, &mut op, 1) }) {
+                Ok(_) => return Ok(()),
+                Err(e) if e.kind() == std::io::ErrorKind::Interrupted => continue,
+                Err(e) => {
+                    return Err(e).with_context(|| {
+                        format!(
+                            "failed to perform op [{sem_op}] on semaphore [{sem_num}] of set [{}]",
+                            self.semid
+                        )
+                    })
+                }
+            }
+        }
+    }
+
+    /// Increment a semaphore (signaling the other side)
+    fn post(&self, sem_num: c_ushort) -> Result<()> {
+        self.op(sem_num, 1)
+    }
+
+    /// Block until a semaphore is positive, then decrement it
+    fn wait(&self, sem_num: c_ushort) -> Result<()> {
+        self.op(sem_num, -1)
+    }
+}
+
+impl Drop for SemaphoreSet {
+    fn drop(&mut self) {
+        if self.owned {
+            debug!(semid = self.semid, "removing semaphore set");
+            // SAFETY: IPC_RMID takes no argument
+            if let Err(e) = cvt(unsafe { libc::semctl(self.semid, 0, libc::IPC_RMID) }) {
+                warn!(semid = self.semid, "failed to remove semaphore set: {e}");
+            }
+        }
+    }
+}
+
+/// Handle to a System V shared memory segment and semaphore set, usable for sending messages in both directions
+struct SysvShmHandle {
+    /// Segment containing both halves
+    segment: SharedSegment,
+
+    /// Semaphores used to signal that messages are ready
+    semaphores: SemaphoreSet,
+
+    /// Size of each half of the segment in bytes
+    slab_size_bytes: usize,
+
+    /// Offset of the half of the segment that this side writes to
+    write_offset: usize,
+
+    /// Offset of the half of the segment that this side reads from
+    read_offset: usize,
+
+    /// Semaphore incremented by this side after a fragment has been written (or acknowledged)
+    write_sem: c_ushort,
+
+    /// Semaphore incremented by the other side after a fragment has been written (or acknowledged)
+    read_sem: c_ushort,
+
+    /// Codec used to encode messages
+    codec: RpcCodec,
+
+    /// Scratch buffer that messages are serialized into, before being split into fragments
+    buf: Vec<u8>,
+
+    /// Splits messages written to the segment into fragments
+    fragmenter: Fragmenter,
+
+    /// Reassembles messages read from the segment out of fragments
+    reassembler: Reassembler,
+}
+
+impl std::fmt::Debug for SysvShmHandle {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SysvShmHandle")
+            .field("shmid", &self.segment.shmid)
+            .field("semid", &self.semaphores.semid)
+            .field("slab_size_bytes", &self.slab_size_bytes)
+            .field("codec", &self.codec)
+            .finish()
+    }
+}
+
+impl SysvShmHandle {
+    /// Create a new segment & semaphore set (for use by the parent), which will be removed on drop
+    fn create(slab_size_bytes: usize, codec: RpcCodec) -> Result<Self> {
+        let segment = SharedSegment::create(slab_size_bytes * 2)?;
+        let semaphores = SemaphoreSet::create()?;
+        Self::new(segment, semaphores, slab_size_bytes, true, codec)
+    }
+
+    /// Attach to an existing segment & semaphore set (for use by the child)
+    fn open(shmid: c_int, semid: c_int, slab_size_bytes: usize, codec: RpcCodec) -> Result<Self> {
+        let segment = SharedSegment::attach(shmid, false)?;
+        let semaphores = SemaphoreSet::open(semid);
+        Self::new(segment, semaphores, slab_size_bytes, false, codec)
+    }
+
+    /// Build a new [`SysvShmHandle`]
+    ///
+    /// # Arguments
+    ///
+    /// * `segment` - the segment backing both halves
+    /// * `semaphores` - the semaphore set used for signaling
+    /// * `slab_size_bytes` - size of each half of the segment
+    /// * `is_parent` - whether this handle is used by the parent (which writes to the first half)
+    /// * `codec` - codec used to encode messages
+    ///
+    fn new(
+        segment: SharedSegment,
+        semaphores: SemaphoreSet,
+        slab_size_bytes: usize,
+        is_parent: bool,
+        codec: RpcCodec,
+    ) -> Result<Self> {
+        ensure!(
+            slab_size_bytes > LEN_PREFIX_SIZE_BYTES + FRAGMENT_HEADER_SIZE_BYTES,
+            "slab size [{slab_size_bytes}] is too small to hold any message"
+        );
+        ensure!(
+            segment.len >= slab_size_bytes * 2,
+            "segment of len [{}] is too small for two slabs of [{slab_size_bytes}] bytes",
+            segment.len
+        );
+        let (write_offset, read_offset, write_sem, read_sem) = if is_parent {
+            (0, slab_size_bytes, SEM_TO_CHILD, SEM_TO_PARENT)
+        } else {
+            (slab_size_bytes, 0, SEM_TO_PARENT, SEM_TO_CHILD)
+        };
+        Ok(Self {
+            segment,
+            semaphores,
+            slab_size_bytes,
+            write_offset,
+            read_offset,
+            write_sem,
+            read_sem,
+            codec,
+            buf: Vec::new(),
+            fragmenter: Fragmenter::default(),
+            reassembler: Reassembler::from_env_or_default(std::env::vars()),
+        })
+    }
+
+    /// Get the max fragment size (not including the length prefix)
+    #[must_use]
+    fn max_fragment_size(&self) -> usize {
+        self.slab_size_bytes - LEN_PREFIX_SIZE_BYTES
+    }
+
+    /// Write a single message to the write half of the segment (one fragment at a time), signaling the other side
+    fn write_message<T: Serialize + ProtoMessage>(&mut self, obj: &T) -> Result<usize> {
+        let mut buf = std::mem::take(&mut self.buf);
+        buf.clear();
+        self.codec.encode_into(obj, &mut buf)?;
+
+        let max_chunk_size = self.max_fragment_size() - FRAGMENT_HEADER_SIZE_BYTES;
+        for (header, chunk) in self.fragmenter.fragments(&buf, max_chunk_size)? {
+            if header.fragment_index > 0 {
+                // Wait for the other side to acknowledge the previous fragment
+                self.semaphores
+                    .wait(self.read_sem)
+                    .context("failed to wait for fragment ack")?;
+            }
+            self.write_fragment(header, chunk)?;
+            self.semaphores.post(self.write_sem)?;
+        }
+
+        let msg_len = buf.len();
+        self.buf = buf;
+        Ok(msg_len)
+    }
+
+    /// Write a single fragment to the write half of the segment
+    fn write_fragment(&mut self, header: FragmentHeader, chunk: &[u8]) -> Result<()> {
+        let (write_offset, slab_size_bytes) = (self.write_offset, self.slab_size_bytes);
+        let slab = &mut self.segment.as_mut_slice()[write_offset..write_offset + slab_size_bytes];
+        let (len_bytes, fragment_bytes) = slab.split_at_mut(LEN_PREFIX_SIZE_BYTES);
+        let fragment_len = FRAGMENT_HEADER_SIZE_BYTES + chunk.len();
+
+        len_bytes.copy_from_slice(
+            &u64::try_from(fragment_len)
+                .context("failed to convert fragment len to u64")?
+                .to_le_bytes(),
+        );
+        fragment_bytes[..FRAGMENT_HEADER_SIZE_BYTES].copy_from_slice(&header.to_bytes());
+        fragment_bytes[FRAGMENT_HEADER_SIZE_BYTES..fragment_len].copy_from_slice(chunk);
+        Ok(())
+    }
+
+    /// Wait for the other side to signal, then read a single message from the read half of the segment
+    fn read_message<T: DeserializeOwned + ProtoMessage>(&mut self) -> Result<T> {
+        self.semaphores.wait(self.read_sem)?;
+
+        let in_place = loop {
+            let slab =
+                &self.segment.as_slice()[self.read_offset..self.read_offset + self.slab_size_bytes];
+            let (header, chunk) = FragmentHeader::split(Self::fragment_in(slab)?)?;
+            if self.reassembler.is_whole_message(&header, chunk)? {
+                break true;
+            }
+            if self.reassembler.push(header, chunk)? {
+                break false;
+            }
+            // Acknowledge the fragment, so the other side can write the next one
+            self.semaphores
+                .post(self.write_sem)
+                .context("failed to ack fragment")?;
+            self.semaphores.wait(self.read_sem)?;
+        };
+
+        if in_place {
+            self.codec
+                .decode(FragmentHeader::split(self.fragment_bytes()?)?.1)
+        } else {
+            self.codec.decode(self.reassembler.message())
+        }
+    }
+
+    /// Retrieve the bytes of the fragment currently in the read half of the segment
+    fn fragment_bytes(&self) -> Result<&[u8]> {
+        Self::fragment_in(
+            &self.segment.as_slice()[self.read_offset..self.read_offset + self.slab_size_bytes],
+        )
+    }
+
+    /// Retrieve the bytes of the fragment in a given slab
+    fn fragment_in(slab: &[u8]) -> Result<&[u8]> {
+        let max_fragment_size = slab.len() - LEN_PREFIX_SIZE_BYTES;
+        let fragment_len = usize::try_from(u64::from_le_bytes(
+            slab[..LEN_PREFIX_SIZE_BYTES]
+                .try_into()
+                .context("unexpectedly invalid byte range for LE u64")?,
+        ))
+        .context("failed to convert u64 len into usize")?;
+        ensure!(
+            fragment_len <= max_fragment_size,
+            "invalid length header [{fragment_len}], fragment would overflow available space [{max_fragment_size}]",
+        );
+        debug!(fragment_len, "reading fragment from shared memory segment");
+
+        Ok(&slab[LEN_PREFIX_SIZE_BYTES..LEN_PREFIX_SIZE_BYTES + fragment_len])
+    }
+}
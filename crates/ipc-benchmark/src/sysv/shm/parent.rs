@@ -0,0 +1,183 @@
+//! Parent-specific IPC implementation over a System V shared memory segment (and semaphores)
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::io::Write;
+use std::process::{Child, Command, Stdio};
+
+use anyhow::{ensure, Context as _, Result};
+use tracing::{debug, info};
+use uuid::Uuid;
+
+use crate::codec::RpcCodec;
+use crate::latency::OneWayLatencies;
+use crate::sysv::shm::{
+    SysvShmHandle, SysvShmInit, SysvShmInitResponse, DEFAULT_SYSV_SHM_SLAB_SIZE_BYTES,
+};
+use crate::{
+    get_monotonic_time_nanos, payload_size_bytes_from_env_or_default, synthetic_payload, ChildId,
+    ChildName, ParentProcess, PingMessage, Pinger, PongMessage, RpcPong,
+};
+
+/// Bi-directional channel for communication with a single child
+///
+/// NOTE: the segment and semaphores are removed when this channel is dropped
+struct SysvShmChannel {
+    /// Self-reported ID of the child
+    child_id: ChildId,
+
+    /// Segment & semaphores shared with the child
+    shm: SysvShmHandle,
+}
+
+/// A parent process that performs IPC via a System V shared memory segment (and semaphores)
+pub struct SysvShmParent {
+    /// UUID of the parent process
+    uuid: Uuid,
+
+    /// Synthetic payload sent with every ping (see `RPC_PAYLOAD_SIZE_BYTES`)
+    payload: Vec<u8>,
+
+    /// Codec used to encode messages (sent to children in the init message)
+    codec: RpcCodec,
+
+    /// Size of the slab used for each direction of shared memory
+    slab_size_bytes: usize,
+
+    /// Channels for communicating with children, by child name
+    ///
+    /// SAFETY: We're safe using a `RefCell` here because this structure
+    /// is very much *not* multi-threaded.
+    channels: HashMap<ChildName, RefCell<SysvShmChannel>>,
+}
+
+impl std::fmt::Debug for SysvShmParent {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SysvShmParent")
+            .field("uuid", &self.uuid)
+            .field("slab_size_bytes", &self.slab_size_bytes)
+            .finish()
+    }
+}
+
+impl Default for SysvShmParent {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl SysvShmParent {
+    /// Create a new [`SysvShmParent`]
+    #[must_use]
+    pub fn new() -> Self {
+        let slab_size_bytes = std::env::var("SYSV_SHM_SLAB_SIZE_BYTES")
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(DEFAULT_SYSV_SHM_SLAB_SIZE_BYTES);
+        Self {
+            uuid: Uuid::now_v7(),
+            payload: synthetic_payload(payload_size_bytes_from_env_or_default(std::env::vars())),
+            codec: RpcCodec::from_env_or_default(std::env::vars()),
+            slab_size_bytes,
+            channels: HashMap::new(),
+        }
+    }
+}
+
+impl ParentProcess for SysvShmParent {
+    fn id(&self) -> String {
+        self.uuid.to_string()
+    }
+
+    fn spawn_child(&mut self, name: impl AsRef<str>, mut cmd: Command) -> Result<Child> {
+        let name = name.as_ref();
+
+        // Create the segment & semaphores before the child is started
+        info!(
+            slab_size_bytes = self.slab_size_bytes,
+            "creating shared memory segment"
+        );
+        let mut shm = SysvShmHandle::create(self.slab_size_bytes, self.codec)
+            .context("failed to create shared memory segment & semaphores")?;
+
+        // Spawn the child
+        debug!("spawning child");
+        let mut child = cmd
+            .stdin(Stdio::piped())
+            .spawn()
+            .context("failed to spawn child process")?;
+
+        // Send the init message over STDIN
+        let init_msg = SysvShmInit {
+            parent_id: self.id(),
+            codec: self.codec,
+            shmid: shm.segment.shmid,
+            semid: shm.semaphores.semid,
+            slab_size_bytes: self.slab_size_bytes,
+        };
+        debug!(?init_msg, "writing init to child STDIN");
+        let mut child_stdin = child.stdin.take().context("failed to get child STDIN")?;
+        child_stdin
+            .write_all(&serde_json::to_vec(&init_msg).context("failed to serialize init msg")?)
+            .context("failed to write init msg")?;
+        child_stdin
+            .write_all(b"\r\n")
+            .context("failed to write new line")?;
+        child_stdin.flush().context("failed to flush child STDIN")?;
+
+        // Wait for the child to respond via shared memory
+        debug!("waiting for init response from child");
+        let init_resp: SysvShmInitResponse = shm
+            .read_message()
+            .context("failed to read init response from child")?;
+        ensure!(
+            init_resp.parent_id == self.id(),
+            "parent ID reported by child did not match"
+        );
+        debug!(child_id = init_resp.child_id, "received init response");
+
+        // Save information to local registry
+        self.channels.insert(
+            name.into(),
+            RefCell::new(SysvShmChannel {
+                child_id: init_resp.child_id,
+                shm,
+            }),
+        );
+
+        Ok(child)
+    }
+}
+
+impl Pinger for SysvShmParent {
+    fn roundtrip_ping(&self, child_name: impl AsRef<str>) -> Result<Option<OneWayLatencies>> {
+        let child = child_name.as_ref();
+        let mut chan = self
+            .channels
+            .get(child)
+            .with_context(|| format!("failed to find child [{child}]"))?
+            .borrow_mut();
+        let SysvShmChannel { child_id, shm } = &mut *chan;
+
+        debug!(child, "sending ping message to child");
+        shm.write_message(&PingMessage {
+            sender_id: self.id(),
+            receiver_id: child_id.clone(),
+            sent_at_ns: get_monotonic_time_nanos()?,
+            payload: self.payload.clone(),
+        })
+        .context("failed to send ping message to child")?;
+
+        debug!(child, "reading pong message from child");
+        let pong_msg: PongMessage = shm.read_message().context("failed to read pong message")?;
+        let latencies = pong_msg.one_way_latencies()?;
+        ensure!(pong_msg.sender_id() == child_id, "child ID matches");
+        ensure!(pong_msg.receiver_id() == self.id(), "parent ID matches");
+        ensure!(
+            pong_msg.payload() == self.payload.as_slice(),
+            "payload matches"
+        );
+
+        Ok(latencies)
+    }
+}
@@ -0,0 +1,114 @@
+//! Child-specific IPC implementation over TCP (loopback)
+
+use std::io::{stdin, BufRead};
+use std::net::TcpStream;
+
+use anyhow::{ensure, Context as _, Result};
+use tracing::debug;
+use uuid::Uuid;
+
+use crate::stream::{is_eof, StreamHandle};
+use crate::tcp::{TcpInit, TcpInitResponse};
+use crate::{
+    get_monotonic_time_nanos, monotonic_nanos_since, ChildProcess, PingMessage, PongMessage,
+};
+
+/// A child process that performs IPC via TCP over the loopback interface
+#[derive(Debug)]
+pub struct TcpChild {
+    /// UUID that should uniquely identify this process
+    uuid: Uuid,
+}
+
+impl Default for TcpChild {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl TcpChild {
+    /// Build a new [`TcpChild`] with a random UUID
+    #[must_use]
+    pub fn new() -> Self {
+        Self {
+            uuid: Uuid::now_v7(),
+        }
+    }
+}
+
+impl ChildProcess for TcpChild {
+    fn id(&self) -> String {
+        self.uuid.to_string()
+    }
+
+    fn run(self) -> Result<()> {
+        debug!("child process running");
+
+        debug!("reading tcp init from STDIN");
+        let mut s = String::new();
+        stdin().lock().read_line(&mut s)?;
+
+        // We expect to receive an init message on STDIN
+        let TcpInit {
+            parent_id,
+            codec,
+            addr,
+            socket_options,
+        } = serde_json::from_slice(s.as_bytes())
+            .context("failed to read init message from STDIN")?;
+
+        debug!(%addr, "connecting to parent");
+        let stream = TcpStream::connect(addr)
+            .with_context(|| format!("failed to connect to parent @ [{addr}]"))?;
+        socket_options
+            .apply_tcp(&stream)
+            .context("failed to apply socket options")?;
+        let mut stream = StreamHandle::new(
+            stream
+                .try_clone()
+                .context("failed to clone TCP stream for reading")?,
+            stream,
+            codec,
+        );
+
+        stream
+            .write_message(&TcpInitResponse {
+                parent_id: parent_id.clone(),
+                child_id: self.id(),
+            })
+            .context("failed to write init response to parent")?;
+        debug!("successfully wrote init response to parent");
+
+        // Enter reading/writing loop
+        debug!("entering read loop...");
+        loop {
+            let PingMessage {
+                sender_id,
+                receiver_id,
+                sent_at_ns,
+                payload,
+                ..
+            } = match stream.read_message() {
+                Ok(msg) => msg,
+                Err(e) if is_eof(&e) => {
+                    debug!("parent closed the connection, exiting");
+                    return Ok(());
+                }
+                Err(e) => return Err(e.context("failed to read ping message")),
+            };
+            let ping_latency_ns = monotonic_nanos_since(sent_at_ns)?;
+            ensure!(sender_id == parent_id, "sender should be parent");
+            ensure!(receiver_id == self.id(), "receiver should be child");
+
+            stream
+                .write_message(&PongMessage {
+                    sender_id: self.id(),
+                    receiver_id: sender_id,
+                    sent_at_ns: get_monotonic_time_nanos()?,
+                    ping_latency_ns,
+                    payload,
+                })
+                .context("failed to send pong to parent")?;
+        }
+    }
+}
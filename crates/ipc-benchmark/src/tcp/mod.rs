@@ -0,0 +1,54 @@
+/*!
+Process IPC over TCP on the [loopback interface][wiki-loopback] (`127.0.0.1`).
+
+The parent listens on an ephemeral port and sends the address to the child over STDIN.
+Once the child connects, pings and pongs travel as length-prefixed messages over the connection.
+
+Socket options (ex. `TCP_NODELAY`, `SO_BUSY_POLL`, buffer sizes) can be configured via ENV,
+see [`crate::sockopt`].
+
+[wiki-loopback]: <https://en.wikipedia.org/wiki/Loopback#Virtual_loopback_interface>
+**/
+
+use std::net::SocketAddr;
+
+use serde::{Deserialize, Serialize};
+
+use crate::codec::RpcCodec;
+use crate::proto::impl_init_response_proto;
+use crate::sockopt::SocketOptions;
+
+pub mod child;
+pub mod parent;
+
+pub use child::TcpChild;
+pub use parent::TcpParent;
+
+/// Message sent to child processes over STDIN that contains
+/// information necessary for the child to connect to the parent
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+struct TcpInit {
+    /// UUID of the parent
+    parent_id: String,
+
+    /// Codec used to encode messages (after this init message)
+    codec: RpcCodec,
+
+    /// Address the parent is listening on
+    addr: SocketAddr,
+
+    /// Options that should be applied to the child's socket
+    socket_options: SocketOptions,
+}
+
+/// Message sent from the child process (over the connection) upon successful connection
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
+struct TcpInitResponse {
+    /// UUID of the parent
+    parent_id: String,
+
+    /// UUID of the child
+    child_id: String,
+}
+
+impl_init_response_proto!(TcpInitResponse);
@@ -0,0 +1,193 @@
+//! Parent-specific IPC implementation over TCP (loopback)
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::io::Write;
+use std::net::{Ipv4Addr, TcpListener, TcpStream};
+use std::process::{Child, Command, Stdio};
+
+use anyhow::{ensure, Context as _, Result};
+use tracing::debug;
+use uuid::Uuid;
+
+use crate::codec::RpcCodec;
+use crate::latency::OneWayLatencies;
+use crate::sockopt::SocketOptions;
+use crate::stream::StreamHandle;
+use crate::tcp::{TcpInit, TcpInitResponse};
+use crate::{
+    get_monotonic_time_nanos, payload_size_bytes_from_env_or_default, synthetic_payload, ChildId,
+    ChildName, ParentProcess, PingMessage, Pinger, PongMessage, RpcPong,
+};
+
+/// Bi-directional channel for communication with a single child
+struct TcpChannel {
+    /// Self-reported ID of the child
+    child_id: ChildId,
+
+    /// Connected stream to the child
+    stream: StreamHandle<TcpStream, TcpStream>,
+}
+
+/// A parent process that performs IPC via TCP over the loopback interface
+pub struct TcpParent {
+    /// UUID of the parent process
+    uuid: Uuid,
+
+    /// Synthetic payload sent with every ping (see `RPC_PAYLOAD_SIZE_BYTES`)
+    payload: Vec<u8>,
+
+    /// Codec used to encode messages (sent to children in the init message)
+    codec: RpcCodec,
+
+    /// Channels for communicating with children, by child name
+    ///
+    /// SAFETY: We're safe using a `RefCell` here because this structure
+    /// is very much *not* multi-threaded.
+    channels: HashMap<ChildName, RefCell<TcpChannel>>,
+
+    /// Options applied to every socket (configured via ENV)
+    socket_options: SocketOptions,
+}
+
+impl std::fmt::Debug for TcpParent {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("TcpParent")
+            .field("uuid", &self.uuid)
+            .field("socket_options", &self.socket_options)
+            .finish()
+    }
+}
+
+impl Default for TcpParent {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl TcpParent {
+    /// Create a new [`TcpParent`]
+    #[must_use]
+    pub fn new() -> Self {
+        Self {
+            uuid: Uuid::now_v7(),
+            payload: synthetic_payload(payload_size_bytes_from_env_or_default(std::env::vars())),
+            codec: RpcCodec::from_env_or_default(std::env::vars()),
+            channels: HashMap::new(),
+            socket_options: SocketOptions::from_env_or_default(std::env::vars()),
+        }
+    }
+}
+
+impl ParentProcess for TcpParent {
+    fn id(&self) -> String {
+        self.uuid.to_string()
+    }
+
+    fn spawn_child(&mut self, name: impl AsRef<str>, mut cmd: Command) -> Result<Child> {
+        let name = name.as_ref();
+
+        // Listen on an ephemeral loopback port, before the child is started
+        let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, 0))
+            .context("failed to bind TCP listener on loopback")?;
+        let addr = listener
+            .local_addr()
+            .context("failed to get TCP listener address")?;
+        debug!(%addr, "listening on loopback");
+
+        // Spawn the child
+        debug!("spawning child");
+        let mut child = cmd
+            .stdin(Stdio::piped())
+            .spawn()
+            .context("failed to spawn child process")?;
+
+        // Send the init message over STDIN
+        let init_msg = TcpInit {
+            parent_id: self.id(),
+            codec: self.codec,
+            addr,
+            socket_options: self.socket_options.clone(),
+        };
+        debug!(?init_msg, "writing init to child STDIN");
+        let mut child_stdin = child.stdin.take().context("failed to get child STDIN")?;
+        child_stdin
+            .write_all(&serde_json::to_vec(&init_msg).context("failed to serialize init msg")?)
+            .context("failed to write init msg")?;
+        child_stdin
+            .write_all(b"\r\n")
+            .context("failed to write new line")?;
+        child_stdin.flush().context("failed to flush child STDIN")?;
+
+        // Wait for the child to connect, and read the init response over the new connection
+        debug!("waiting for child to connect");
+        let (stream, _addr) = listener
+            .accept()
+            .context("failed to accept connection from child")?;
+        self.socket_options
+            .apply_tcp(&stream)
+            .context("failed to apply socket options")?;
+        let mut stream = StreamHandle::new(
+            stream
+                .try_clone()
+                .context("failed to clone TCP stream for reading")?,
+            stream,
+            self.codec,
+        );
+        let init_resp: TcpInitResponse = stream
+            .read_message()
+            .context("failed to read init response from child")?;
+        ensure!(
+            init_resp.parent_id == self.id(),
+            "parent ID reported by child did not match"
+        );
+        debug!(child_id = init_resp.child_id, "received init response");
+
+        // Save information to local registry
+        self.channels.insert(
+            name.into(),
+            RefCell::new(TcpChannel {
+                child_id: init_resp.child_id,
+                stream,
+            }),
+        );
+
+        Ok(child)
+    }
+}
+
+impl Pinger for TcpParent {
+    fn roundtrip_ping(&self, child_name: impl AsRef<str>) -> Result<Option<OneWayLatencies>> {
+        let child = child_name.as_ref();
+        let mut chan = self
+            .channels
+            .get(child)
+            .with_context(|| format!("failed to find child [{child}]"))?
+            .borrow_mut();
+        let TcpChannel { child_id, stream } = &mut *chan;
+
+        debug!(child, "sending ping message to child");
+        stream
+            .write_message(&PingMessage {
+                sender_id: self.id(),
+                receiver_id: child_id.clone(),
+                sent_at_ns: get_monotonic_time_nanos()?,
+                payload: self.payload.clone(),
+            })
+            .context("failed to send ping message to child")?;
+
+        debug!(child, "reading pong message from child");
+        let pong_msg: PongMessage = stream
+            .read_message()
+            .context("failed to read pong message")?;
+        let latencies = pong_msg.one_way_latencies()?;
+        ensure!(pong_msg.sender_id() == child_id, "child ID matches");
+        ensure!(pong_msg.receiver_id() == self.id(), "parent ID matches");
+        ensure!(
+            pong_msg.payload() == self.payload.as_slice(),
+            "payload matches"
+        );
+
+        Ok(latencies)
+    }
+}
@@ -0,0 +1,421 @@
+/*!
+Statistics across repeated trials of a benchmark.
+
+A single run of a transport is noisy (scheduling, frequency scaling, other load on the host), so results
+are only defensible across repeated trials, each with fresh parent & child processes (see `just ipc-trials`).
+Trials are summarized by their mean, (sample) standard deviation and a 95% confidence interval for the mean,
+computed with [Student's t-distribution][wiki-t-distribution] since the number of trials is usually small.
+
+Results are read back from the output of each trial (see [`TrialResults::parse`]), so that trials can
+run the parent binaries as-is. The result lines they print are formatted by the helpers in this module
+(ex. [`format_throughput_line`]), so that the lines that are written and the lines that are parsed cannot drift apart.
+
+[wiki-t-distribution]: <https://en.wikipedia.org/wiki/Student%27s_t-distribution>
+**/
+
+use anyhow::{ensure, Context as _, Result};
+use conv::ValueFrom as _;
+use tracing::info;
+
+/// Critical values of Student's t-distribution for a two-sided 95% confidence interval, by degrees of freedom
+///
+/// Degrees of freedom between tabulated values use the next lowest one (which is slightly conservative).
+const T_CRITICAL_VALUES_95: [(usize, f64); 33] = [
+    (1, 12.706),
+    (2, 4.303),
+    (3, 3.182),
+    (4, 2.776),
+    (5, 2.571),
+    (6, 2.447),
+    (7, 2.365),
+    (8, 2.306),
+    (9, 2.262),
+    (10, 2.228),
+    (11, 2.201),
+    (12, 2.179),
+    (13, 2.160),
+    (14, 2.145),
+    (15, 2.131),
+    (16, 2.120),
+    (17, 2.110),
+    (18, 2.101),
+    (19, 2.093),
+    (20, 2.086),
+    (21, 2.080),
+    (22, 2.074),
+    (23, 2.069),
+    (24, 2.064),
+    (25, 2.060),
+    (26, 2.056),
+    (27, 2.052),
+    (28, 2.048),
+    (29, 2.045),
+    (30, 2.042),
+    (40, 2.021),
+    (60, 2.000),
+    (120, 1.980),
+];
+
+/// Critical value of the normal distribution for a two-sided 95% confidence interval (for large numbers of trials)
+const Z_CRITICAL_VALUE_95: f64 = 1.960;
+
+/// Start of the line reporting the throughput of a run (see [`format_throughput_line`])
+const THROUGHPUT_LINE_PREFIX: &str = "completed [";
+
+/// End of the line reporting the throughput of a run
+const THROUGHPUT_LINE_SUFFIX: &str = "round-trips/second)";
+
+/// Position of round-trips per second among the bracketed values of the throughput line
+const THROUGHPUT_LINE_ROUNDTRIPS_PER_SECOND_INDEX: usize = 2;
+
+/// Start of the line reporting the CPU cost of a run (see [`format_cpu_cost_line`])
+const CPU_COST_LINE_PREFIX: &str = "cpu cost: [";
+
+/// Value printed in place of round-trips per CPU-second when no CPU time was accounted for
+/// (ex. very short runs), which is skipped when parsing
+const NOT_AVAILABLE: &str = "n/a";
+
+/// Position of round-trips per CPU-second among the bracketed values of the CPU cost line
+const CPU_COST_LINE_ROUNDTRIPS_PER_CPU_SECOND_INDEX: usize = 1;
+
+/// Name of the latencies that are summarized across trials (see [`format_latency_line`])
+pub(crate) const ROUNDTRIP_LATENCY_NAME: &str = "round-trip";
+
+/// Text between the name of latencies and their values in latency lines
+const LATENCY_LINE_INFIX: &str = " latency (us): ";
+
+/// Position of the p50 latency among the bracketed values of latency lines
+const LATENCY_LINE_P50_INDEX: usize = 0;
+
+/// Position of the p99 latency among the bracketed values of latency lines
+const LATENCY_LINE_P99_INDEX: usize = 2;
+
+/// Summary of a single metric across trials
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TrialStats {
+    /// Number of trials
+    pub trials: usize,
+
+    /// Mean across trials
+    pub mean: f64,
+
+    /// Sample standard deviation across trials
+    pub stddev: f64,
+
+    /// Half-width of the 95% confidence interval for the mean
+    pub ci95_half_width: f64,
+}
+
+impl TrialStats {
+    /// Summarize the values of a metric across trials (at least 2 are needed)
+    pub fn from_samples(samples: &[f64]) -> Result<Self> {
+        let trials = samples.len();
+        ensure!(
+            trials >= 2,
+            "at least 2 trials are needed to compute a confidence interval, got [{trials}]"
+        );
+
+        let n = f64::value_from(trials).context("failed to convert trials to f64")?;
+        let mean = samples.iter().sum::<f64>() / n;
+        let variance = samples.iter().map(|v| (v - mean).powi(2)).sum::<f64>() / (n - 1.0);
+        let stddev = variance.sqrt();
+        Ok(Self {
+            trials,
+            mean,
+            stddev,
+            ci95_half_width: t_critical_value_95(trials - 1) * stddev / n.sqrt(),
+        })
+    }
+
+    /// Lower bound of the 95% confidence interval for the mean
+    #[must_use]
+    pub fn ci95_low(&self) -> f64 {
+        self.mean - self.ci95_half_width
+    }
+
+    /// Upper bound of the 95% confidence interval for the mean
+    #[must_use]
+    pub fn ci95_high(&self) -> f64 {
+        self.mean + self.ci95_half_width
+    }
+
+    /// Report the summary (via tracing, and on STDERR like the rest of the results), under a given name
+    /// (ex. `round-trips/second`)
+    pub fn report(&self, name: &str) {
+        let Self {
+            trials,
+            mean,
+            stddev,
+            ci95_half_width,
+        } = *self;
+        let ci95_low = self.ci95_low();
+        let ci95_high = self.ci95_high();
+        info!(
+            name,
+            trials, mean, stddev, ci95_low, ci95_high, "computed trial statistics"
+        );
+        eprintln!("{name}: mean [{mean:.2}] stddev [{stddev:.2}] 95% ci [{ci95_low:.2} - {ci95_high:.2}] (+/- [{ci95_half_width:.2}], [{trials}] trials)");
+    }
+}
+
+/// Results of repeated trials, as read back from their output
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct TrialResults {
+    /// Round-trips per (wall) second of every trial
+    pub roundtrips_per_second: Vec<f64>,
+
+    /// Round-trips per CPU-second of every trial (see [`crate::cpu`])
+    pub roundtrips_per_cpu_second: Vec<f64>,
+
+    /// Median round-trip latency (in microseconds) of every trial (see [`crate::latency`])
+    pub p50_us: Vec<f64>,
+
+    /// 99th percentile round-trip latency (in microseconds) of every trial
+    pub p99_us: Vec<f64>,
+}
+
+impl TrialResults {
+    /// Parse the output of one or more trials (ex. the STDERR of parent binaries, concatenated)
+    ///
+    /// Lines that are not results (ex. logs) are ignored.
+    pub fn parse(output: &str) -> Result<Self> {
+        let mut results = Self::default();
+        for line in output.lines().map(str::trim) {
+            let push = |samples: &mut Vec<f64>, index: usize| -> Result<()> {
+                let value = bracketed(line)
+                    .nth(index)
+                    .with_context(|| format!("missing value in result line [{line}]"))?;
+                if value == NOT_AVAILABLE {
+                    return Ok(());
+                }
+                samples.push(value.parse::<f64>().with_context(|| {
+                    format!("failed to parse value [{value}] in result line [{line}]")
+                })?);
+                Ok(())
+            };
+            if line.starts_with(THROUGHPUT_LINE_PREFIX) && line.ends_with(THROUGHPUT_LINE_SUFFIX) {
+                push(
+                    &mut results.roundtrips_per_second,
+                    THROUGHPUT_LINE_ROUNDTRIPS_PER_SECOND_INDEX,
+                )?;
+            } else if line.starts_with(CPU_COST_LINE_PREFIX) {
+                push(
+                    &mut results.roundtrips_per_cpu_second,
+                    CPU_COST_LINE_ROUNDTRIPS_PER_CPU_SECOND_INDEX,
+                )?;
+            } else if line
+                .strip_prefix(ROUNDTRIP_LATENCY_NAME)
+                .is_some_and(|rest| rest.starts_with(LATENCY_LINE_INFIX))
+            {
+                push(&mut results.p50_us, LATENCY_LINE_P50_INDEX)?;
+                push(&mut results.p99_us, LATENCY_LINE_P99_INDEX)?;
+            }
+        }
+        Ok(results)
+    }
+
+    /// Report the summary of every metric that was found in the output of trials
+    pub fn report(&self) -> Result<()> {
+        ensure!(
+            !self.roundtrips_per_second.is_empty(),
+            "no results found in the output of trials"
+        );
+        for (name, samples) in [
+            ("round-trips/second", &self.roundtrips_per_second),
+            ("round-trips/cpu-second", &self.roundtrips_per_cpu_second),
+            ("round-trip latency p50 (us)", &self.p50_us),
+            ("round-trip latency p99 (us)", &self.p99_us),
+        ] {
+            if samples.is_empty() {
+                continue;
+            }
+            TrialStats::from_samples(samples)
+                .with_context(|| format!("failed to summarize [{name}]"))?
+                .report(name);
+        }
+        Ok(())
+    }
+}
+
+/// Format the line reporting the throughput of a run (printed by every parent binary)
+#[must_use]
+pub fn format_throughput_line(
+    roundtrips: u64,
+    test_duration_seconds: u64,
+    roundtrips_per_second: f64,
+) -> String {
+    format!("{THROUGHPUT_LINE_PREFIX}{roundtrips}] ping-pong round-trips [{test_duration_seconds}] seconds ([{roundtrips_per_second}] {THROUGHPUT_LINE_SUFFIX}")
+}
+
+/// Format the line reporting the CPU cost of a run (see [`crate::cpu::CpuCost::report`])
+///
+/// Round-trips per CPU-second are printed as `n/a` if missing (i.e. if no CPU time was accounted for).
+#[must_use]
+pub fn format_cpu_cost_line(
+    cpu_seconds: f64,
+    roundtrips_per_cpu_second: Option<f64>,
+    roundtrips_per_second: f64,
+) -> String {
+    let roundtrips_per_cpu_second =
+        roundtrips_per_cpu_second.map_or_else(|| NOT_AVAILABLE.into(), |v| format!("{v:.2}"));
+    format!("{CPU_COST_LINE_PREFIX}{cpu_seconds:.3}] cpu-seconds ([{roundtrips_per_cpu_second}] round-trips/cpu-second, [{roundtrips_per_second:.2}] round-trips/wall-second)")
+}
+
+/// Format a line reporting tail latencies (in microseconds), under a given name (ex. `round-trip`)
+/// (see [`crate::latency::RoundtripLatencies::report`])
+#[must_use]
+pub fn format_latency_line(
+    name: &str,
+    p50_us: f64,
+    p90_us: f64,
+    p99_us: f64,
+    p999_us: f64,
+    max_us: f64,
+) -> String {
+    format!("{name}{LATENCY_LINE_INFIX}p50 [{p50_us:.2}] p90 [{p90_us:.2}] p99 [{p99_us:.2}] p99.9 [{p999_us:.2}] max [{max_us:.2}]")
+}
+
+/// Critical value for a two-sided 95% confidence interval, with a given number of degrees of freedom
+fn t_critical_value_95(degrees_of_freedom: usize) -> f64 {
+    if degrees_of_freedom > 120 {
+        return Z_CRITICAL_VALUE_95;
+    }
+    T_CRITICAL_VALUES_95
+        .iter()
+        .rev()
+        .find(|(df, _)| *df <= degrees_of_freedom)
+        .map_or(T_CRITICAL_VALUES_95[0].1, |(_, t)| *t)
+}
+
+/// Values between square brackets in a result line (ex. `[123]`), in order
+fn bracketed(line: &str) -> impl Iterator<Item = &str> {
+    line.split('[')
+        .skip(1)
+        .filter_map(|s| s.split_once(']').map(|(value, _)| value))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Output of a trial, as printed by a parent binary (along with lines that are not summarized)
+    const TRIAL_OUTPUT: &str = "\
+2026-10-17T10:00:00.000000Z  INFO uds: spawned child process
+warmup: [30112] round-trips over [1] seconds (excluded from results)
+completed [29866] ping-pong round-trips [1] seconds ([29866] round-trips/second)
+parent cpu: user [0.512s] system [0.401s] context switches: voluntary [29870] involuntary [12] max rss [7340 KiB]
+child cpu: user [0.498s] system [0.389s] context switches: voluntary [29866] involuntary [9] max rss [6256 KiB]
+cpu cost: [1.800] cpu-seconds ([16592.22] round-trips/cpu-second, [29866.00] round-trips/wall-second)
+sent [1024] byte payloads each way ([58.33] MiB/second)
+round-trip latency (us): p50 [30.16] p90 [36.42] p99 [71.23] p99.9 [161.15] max [2850.82]
+parent->child latency (us): p50 [14.92] p90 [18.03] p99 [35.61] p99.9 [80.13] max [1425.41]
+child->parent latency (us): p50 [10.92] p90 [17.44] p99 [46.53] p99.9 [161.15] max [2850.82]
+";
+
+    /// Check that two floats are equal, up to the precision of reported values
+    fn assert_close(actual: f64, expected: f64) {
+        assert!(
+            (actual - expected).abs() < 0.01,
+            "[{actual}] is not close to [{expected}]"
+        );
+    }
+
+    /// Values are extracted from the lines printed by parent binaries, and other lines are ignored
+    #[test]
+    fn parse_trial_output() -> Result<()> {
+        let results = TrialResults::parse(&TRIAL_OUTPUT.repeat(2))?;
+        assert_eq!(
+            results,
+            TrialResults {
+                roundtrips_per_second: vec![29866.0, 29866.0],
+                roundtrips_per_cpu_second: vec![16592.22, 16592.22],
+                p50_us: vec![30.16, 30.16],
+                p99_us: vec![71.23, 71.23],
+            }
+        );
+        Ok(())
+    }
+
+    /// Lines formatted by the helpers used by parent binaries are the ones that are parsed
+    #[test]
+    fn parse_formatted_lines() -> Result<()> {
+        let lines = [
+            format_throughput_line(29866, 1, 29866.0),
+            format_cpu_cost_line(1.8, Some(16592.222), 29866.0),
+            format_latency_line(ROUNDTRIP_LATENCY_NAME, 30.16, 36.42, 71.23, 161.15, 2850.82),
+            format_latency_line("child->parent", 10.92, 17.44, 46.53, 161.15, 2850.82),
+        ];
+        for line in &lines {
+            assert!(
+                TRIAL_OUTPUT.lines().any(|l| l == line),
+                "formatted line [{line}] differs from the lines printed by parent binaries"
+            );
+        }
+        let output = lines.join("\n");
+        let results = TrialResults::parse(&output)?;
+        assert_eq!(
+            results,
+            TrialResults {
+                roundtrips_per_second: vec![29866.0],
+                roundtrips_per_cpu_second: vec![16592.22],
+                p50_us: vec![30.16],
+                p99_us: vec![71.23],
+            }
+        );
+        Ok(())
+    }
+
+    /// Round-trips per CPU-second are skipped when not available, rather than summarized as infinite
+    #[test]
+    fn parse_missing_cpu_cost() -> Result<()> {
+        let line = format_cpu_cost_line(0.0, None, 29866.0);
+        assert_eq!(
+            line,
+            "cpu cost: [0.000] cpu-seconds ([n/a] round-trips/cpu-second, [29866.00] round-trips/wall-second)"
+        );
+        assert!(TrialResults::parse(&line)?
+            .roundtrips_per_cpu_second
+            .is_empty());
+        Ok(())
+    }
+
+    /// Result lines with invalid values are rejected rather than skipped
+    #[test]
+    fn parse_invalid_value() {
+        let err = TrialResults::parse(
+            "completed [10] ping-pong round-trips [1] seconds ([ten] round-trips/second)",
+        )
+        .expect_err("unexpectedly parsed invalid value");
+        assert!(format!("{err:#}").contains("failed to parse value [ten]"));
+    }
+
+    /// Summary of a known set of samples
+    #[test]
+    fn stats_from_samples() -> Result<()> {
+        let stats = TrialStats::from_samples(&[25482.0, 28275.0, 31947.0])?;
+        assert_eq!(stats.trials, 3);
+        assert_close(stats.mean, 28568.0);
+        assert_close(stats.stddev, 3242.44);
+        assert_close(stats.ci95_half_width, 8055.33);
+        assert_close(stats.ci95_low(), 28568.0 - 8055.33);
+        assert_close(stats.ci95_high(), 28568.0 + 8055.33);
+        Ok(())
+    }
+
+    /// A single trial is not enough for a confidence interval
+    #[test]
+    fn stats_from_single_sample() {
+        assert!(TrialStats::from_samples(&[25482.0]).is_err());
+    }
+
+    /// Critical values are looked up by degrees of freedom, falling back to the next lowest tabulated one
+    #[test]
+    fn t_critical_values() {
+        assert_close(t_critical_value_95(1), 12.706);
+        assert_close(t_critical_value_95(2), 4.303);
+        assert_close(t_critical_value_95(30), 2.042);
+        assert_close(t_critical_value_95(35), 2.042);
+        assert_close(t_critical_value_95(120), 1.980);
+        assert_close(t_critical_value_95(121), Z_CRITICAL_VALUE_95);
+    }
+}
@@ -0,0 +1,105 @@
+//! Child-specific IPC implementation over UDP (loopback)
+
+use std::io::{stdin, BufRead};
+use std::net::{Ipv4Addr, UdpSocket};
+
+use anyhow::{ensure, Context as _, Result};
+use tracing::debug;
+use uuid::Uuid;
+
+use crate::udp::{DatagramHandle, UdpInit, UdpInitResponse};
+use crate::{
+    get_monotonic_time_nanos, monotonic_nanos_since, ChildProcess, PingMessage, PongMessage,
+};
+
+/// A child process that performs IPC via UDP over the loopback interface
+#[derive(Debug)]
+pub struct UdpChild {
+    /// UUID that should uniquely identify this process
+    uuid: Uuid,
+}
+
+impl Default for UdpChild {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl UdpChild {
+    /// Build a new [`UdpChild`] with a random UUID
+    #[must_use]
+    pub fn new() -> Self {
+        Self {
+            uuid: Uuid::now_v7(),
+        }
+    }
+}
+
+impl ChildProcess for UdpChild {
+    fn id(&self) -> String {
+        self.uuid.to_string()
+    }
+
+    fn run(self) -> Result<()> {
+        debug!("child process running");
+
+        debug!("reading udp init from STDIN");
+        let mut s = String::new();
+        stdin().lock().read_line(&mut s)?;
+
+        // We expect to receive an init message on STDIN
+        let UdpInit {
+            parent_id,
+            codec,
+            addr,
+            socket_options,
+        } = serde_json::from_slice(s.as_bytes())
+            .context("failed to read init message from STDIN")?;
+
+        debug!(%addr, "connecting to parent");
+        let socket = UdpSocket::bind((Ipv4Addr::LOCALHOST, 0))
+            .context("failed to bind UDP socket on loopback")?;
+        socket_options
+            .apply(&socket)
+            .context("failed to apply socket options")?;
+        socket
+            .connect(addr)
+            .with_context(|| format!("failed to connect UDP socket to parent @ [{addr}]"))?;
+        let mut socket = DatagramHandle::new(socket, codec);
+
+        socket
+            .write_message(&UdpInitResponse {
+                parent_id: parent_id.clone(),
+                child_id: self.id(),
+            })
+            .context("failed to write init response to parent")?;
+        debug!("successfully wrote init response to parent");
+
+        // Enter reading/writing loop
+        debug!("entering read loop...");
+        loop {
+            let PingMessage {
+                sender_id,
+                receiver_id,
+                sent_at_ns,
+                payload,
+                ..
+            } = socket
+                .read_message()
+                .context("failed to read ping message")?;
+            let ping_latency_ns = monotonic_nanos_since(sent_at_ns)?;
+            ensure!(sender_id == parent_id, "sender should be parent");
+            ensure!(receiver_id == self.id(), "receiver should be child");
+
+            socket
+                .write_message(&PongMessage {
+                    sender_id: self.id(),
+                    receiver_id: sender_id,
+                    sent_at_ns: get_monotonic_time_nanos()?,
+                    ping_latency_ns,
+                    payload,
+                })
+                .context("failed to send pong to parent")?;
+        }
+    }
+}
@@ -0,0 +1,132 @@
+/*!
+Process IPC over UDP on the [loopback interface][wiki-loopback] (`127.0.0.1`).
+
+The parent binds an ephemeral port and sends the address to the child over STDIN. The child binds
+its own ephemeral port, and announces itself with an init response datagram, after which both
+sockets are `connect()`ed to each other.
+
+Every message is exactly one datagram, so no length prefix is necessary, but messages are limited
+to [`MAX_DATAGRAM_SIZE_BYTES`]. Loopback UDP is not *guaranteed* to be reliable, but as only one
+message is ever in flight at a time, losses are not expected (a lost message will stall the test).
+
+Socket options (ex. `SO_BUSY_POLL`, buffer sizes) can be configured via ENV, see [`crate::sockopt`].
+
+[wiki-loopback]: <https://en.wikipedia.org/wiki/Loopback#Virtual_loopback_interface>
+**/
+
+use std::net::{SocketAddr, UdpSocket};
+
+use anyhow::{ensure, Context as _, Result};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use tracing::debug;
+
+use crate::codec::{Codec as _, RpcCodec};
+use crate::proto::{impl_init_response_proto, ProtoMessage};
+use crate::sockopt::SocketOptions;
+
+pub mod child;
+pub mod parent;
+
+pub use child::UdpChild;
+pub use parent::UdpParent;
+
+/// Largest message that can be sent in a single (IPv4) UDP datagram
+const MAX_DATAGRAM_SIZE_BYTES: usize = 65_507;
+
+/// Message sent to child processes over STDIN that contains
+/// information necessary for the child to connect to the parent
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+struct UdpInit {
+    /// UUID of the parent
+    parent_id: String,
+
+    /// Codec used to encode messages (after this init message)
+    codec: RpcCodec,
+
+    /// Address the parent's socket is bound to
+    addr: SocketAddr,
+
+    /// Options that should be applied to the child's socket
+    socket_options: SocketOptions,
+}
+
+/// Message sent from the child process (as the first datagram) upon successful setup
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
+struct UdpInitResponse {
+    /// UUID of the parent
+    parent_id: String,
+
+    /// UUID of the child
+    child_id: String,
+}
+
+impl_init_response_proto!(UdpInitResponse);
+
+/// A handle over a connected [`UdpSocket`] that sends and receives one message per datagram
+struct DatagramHandle {
+    /// Connected socket
+    socket: UdpSocket,
+
+    /// Buffer that every datagram is received into
+    ///
+    /// NOTE: this is always [`MAX_DATAGRAM_SIZE_BYTES`] long, so that it never needs to be resized
+    recv_buf: Box<[u8]>,
+
+    /// Scratch buffer that every outgoing message is serialized into
+    send_buf: Vec<u8>,
+
+    /// Codec used to encode messages
+    codec: RpcCodec,
+}
+
+impl std::fmt::Debug for DatagramHandle {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("DatagramHandle")
+            .field("local_addr", &self.socket.local_addr().ok())
+            .field("peer_addr", &self.socket.peer_addr().ok())
+            .field("codec", &self.codec)
+            .finish()
+    }
+}
+
+impl DatagramHandle {
+    /// Create a new [`DatagramHandle`] from a socket that has already been `connect()`ed
+    fn new(socket: UdpSocket, codec: RpcCodec) -> Self {
+        Self {
+            socket,
+            recv_buf: vec![0; MAX_DATAGRAM_SIZE_BYTES].into_boxed_slice(),
+            send_buf: Vec::new(),
+            codec,
+        }
+    }
+
+    /// Perform a blocking read of a single message (datagram)
+    fn read_message<T: DeserializeOwned + ProtoMessage>(&mut self) -> Result<T> {
+        let len = self
+            .socket
+            .recv(&mut self.recv_buf)
+            .context("failed to receive datagram")?;
+        debug!(len, "received datagram");
+        self.codec.decode(&self.recv_buf[..len])
+    }
+
+    /// Perform a blocking write of a single message (datagram)
+    fn write_message<T: Serialize + ProtoMessage>(&mut self, obj: &T) -> Result<usize> {
+        self.send_buf.clear();
+        self.codec.encode_into(obj, &mut self.send_buf)?;
+        let msg_len = self.send_buf.len();
+        ensure!(
+            msg_len <= MAX_DATAGRAM_SIZE_BYTES,
+            "serialized message of len [{msg_len}] is greater than max datagram size [{MAX_DATAGRAM_SIZE_BYTES}]",
+        );
+        let sent = self
+            .socket
+            .send(&self.send_buf)
+            .context("failed to send datagram")?;
+        ensure!(
+            sent == msg_len,
+            "partial datagram send ([{sent}] of [{msg_len}] bytes)"
+        );
+        Ok(msg_len)
+    }
+}
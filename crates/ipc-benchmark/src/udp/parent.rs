@@ -0,0 +1,192 @@
+//! Parent-specific IPC implementation over UDP (loopback)
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::io::Write;
+use std::net::{Ipv4Addr, UdpSocket};
+use std::process::{Child, Command, Stdio};
+
+use anyhow::{ensure, Context as _, Result};
+use tracing::debug;
+use uuid::Uuid;
+
+use crate::codec::{Codec as _, RpcCodec};
+use crate::latency::OneWayLatencies;
+use crate::sockopt::SocketOptions;
+use crate::udp::{DatagramHandle, UdpInit, UdpInitResponse, MAX_DATAGRAM_SIZE_BYTES};
+use crate::{
+    get_monotonic_time_nanos, payload_size_bytes_from_env_or_default, synthetic_payload, ChildId,
+    ChildName, ParentProcess, PingMessage, Pinger, PongMessage, RpcPong,
+};
+
+/// Bi-directional channel for communication with a single child
+struct UdpChannel {
+    /// Self-reported ID of the child
+    child_id: ChildId,
+
+    /// Socket connected to the child
+    socket: DatagramHandle,
+}
+
+/// A parent process that performs IPC via UDP over the loopback interface
+pub struct UdpParent {
+    /// UUID of the parent process
+    uuid: Uuid,
+
+    /// Synthetic payload sent with every ping (see `RPC_PAYLOAD_SIZE_BYTES`)
+    payload: Vec<u8>,
+
+    /// Codec used to encode messages (sent to children in the init message)
+    codec: RpcCodec,
+
+    /// Channels for communicating with children, by child name
+    ///
+    /// SAFETY: We're safe using a `RefCell` here because this structure
+    /// is very much *not* multi-threaded.
+    channels: HashMap<ChildName, RefCell<UdpChannel>>,
+
+    /// Options applied to every socket (configured via ENV)
+    socket_options: SocketOptions,
+}
+
+impl std::fmt::Debug for UdpParent {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("UdpParent")
+            .field("uuid", &self.uuid)
+            .field("socket_options", &self.socket_options)
+            .finish()
+    }
+}
+
+impl Default for UdpParent {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl UdpParent {
+    /// Create a new [`UdpParent`]
+    #[must_use]
+    pub fn new() -> Self {
+        Self {
+            uuid: Uuid::now_v7(),
+            payload: synthetic_payload(payload_size_bytes_from_env_or_default(std::env::vars())),
+            codec: RpcCodec::from_env_or_default(std::env::vars()),
+            channels: HashMap::new(),
+            socket_options: SocketOptions::from_env_or_default(std::env::vars()),
+        }
+    }
+}
+
+impl ParentProcess for UdpParent {
+    fn id(&self) -> String {
+        self.uuid.to_string()
+    }
+
+    fn spawn_child(&mut self, name: impl AsRef<str>, mut cmd: Command) -> Result<Child> {
+        let name = name.as_ref();
+
+        // Bind an ephemeral loopback port, before the child is started
+        let socket = UdpSocket::bind((Ipv4Addr::LOCALHOST, 0))
+            .context("failed to bind UDP socket on loopback")?;
+        self.socket_options
+            .apply(&socket)
+            .context("failed to apply socket options")?;
+        let addr = socket
+            .local_addr()
+            .context("failed to get UDP socket address")?;
+        debug!(%addr, "bound UDP socket on loopback");
+
+        // Spawn the child
+        debug!("spawning child");
+        let mut child = cmd
+            .stdin(Stdio::piped())
+            .spawn()
+            .context("failed to spawn child process")?;
+
+        // Send the init message over STDIN
+        let init_msg = UdpInit {
+            parent_id: self.id(),
+            codec: self.codec,
+            addr,
+            socket_options: self.socket_options.clone(),
+        };
+        debug!(?init_msg, "writing init to child STDIN");
+        let mut child_stdin = child.stdin.take().context("failed to get child STDIN")?;
+        child_stdin
+            .write_all(&serde_json::to_vec(&init_msg).context("failed to serialize init msg")?)
+            .context("failed to write init msg")?;
+        child_stdin
+            .write_all(b"\r\n")
+            .context("failed to write new line")?;
+        child_stdin.flush().context("failed to flush child STDIN")?;
+
+        // Wait for the init response, which tells us where the child is
+        debug!("waiting for init response from child");
+        let mut buf = vec![0; MAX_DATAGRAM_SIZE_BYTES];
+        let (len, child_addr) = socket
+            .recv_from(&mut buf)
+            .context("failed to receive init response from child")?;
+        let init_resp: UdpInitResponse = self
+            .codec
+            .decode(&buf[..len])
+            .context("failed to deserialize init response from child")?;
+        ensure!(
+            init_resp.parent_id == self.id(),
+            "parent ID reported by child did not match"
+        );
+        debug!(child_id = init_resp.child_id, %child_addr, "received init response");
+
+        // From here on, only accept datagrams from the child
+        socket
+            .connect(child_addr)
+            .with_context(|| format!("failed to connect UDP socket to child @ [{child_addr}]"))?;
+
+        // Save information to local registry
+        self.channels.insert(
+            name.into(),
+            RefCell::new(UdpChannel {
+                child_id: init_resp.child_id,
+                socket: DatagramHandle::new(socket, self.codec),
+            }),
+        );
+
+        Ok(child)
+    }
+}
+
+impl Pinger for UdpParent {
+    fn roundtrip_ping(&self, child_name: impl AsRef<str>) -> Result<Option<OneWayLatencies>> {
+        let child = child_name.as_ref();
+        let mut chan = self
+            .channels
+            .get(child)
+            .with_context(|| format!("failed to find child [{child}]"))?
+            .borrow_mut();
+        let UdpChannel { child_id, socket } = &mut *chan;
+
+        debug!(child, "sending ping message to child");
+        socket
+            .write_message(&PingMessage {
+                sender_id: self.id(),
+                receiver_id: child_id.clone(),
+                sent_at_ns: get_monotonic_time_nanos()?,
+                payload: self.payload.clone(),
+            })
+            .context("failed to send ping message to child")?;
+
+        debug!(child, "reading pong message from child");
+        let pong_msg: PongMessage = socket
+            .read_message()
+            .context("failed to read pong message")?;
+        let latencies = pong_msg.one_way_latencies()?;
+        ensure!(pong_msg.sender_id() == child_id, "child ID matches");
+        ensure!(pong_msg.receiver_id() == self.id(), "parent ID matches");
+        ensure!(
+            pong_msg.payload() == self.payload.as_slice(),
+            "payload matches"
+        );
+
+        Ok(latencies)
+    }
+}
@@ -0,0 +1,114 @@
+//! Child-specific IPC implementation over Unix domain sockets
+
+use std::io::{stdin, BufRead};
+use std::os::unix::net::UnixStream;
+
+use anyhow::{ensure, Context as _, Result};
+use tracing::debug;
+use uuid::Uuid;
+
+use crate::stream::{is_eof, StreamHandle};
+use crate::uds::{UdsInit, UdsInitResponse};
+use crate::{
+    get_monotonic_time_nanos, monotonic_nanos_since, ChildProcess, PingMessage, PongMessage,
+};
+
+/// A child process that performs IPC via a Unix domain socket (`SOCK_STREAM`)
+#[derive(Debug)]
+pub struct UdsChild {
+    /// UUID that should uniquely identify this process
+    uuid: Uuid,
+}
+
+impl Default for UdsChild {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl UdsChild {
+    /// Build a new [`UdsChild`] with a random UUID
+    #[must_use]
+    pub fn new() -> Self {
+        Self {
+            uuid: Uuid::now_v7(),
+        }
+    }
+}
+
+impl ChildProcess for UdsChild {
+    fn id(&self) -> String {
+        self.uuid.to_string()
+    }
+
+    fn run(self) -> Result<()> {
+        debug!("child process running");
+
+        debug!("reading uds init from STDIN");
+        let mut s = String::new();
+        stdin().lock().read_line(&mut s)?;
+
+        // We expect to receive an init message on STDIN
+        let UdsInit {
+            parent_id,
+            codec,
+            socket_path,
+        } = serde_json::from_slice(s.as_bytes())
+            .context("failed to read init message from STDIN")?;
+
+        debug!(socket_path = %socket_path.display(), "connecting to parent socket");
+        let stream = UnixStream::connect(&socket_path).with_context(|| {
+            format!(
+                "failed to connect to parent socket @ [{}]",
+                socket_path.display()
+            )
+        })?;
+        let mut stream = StreamHandle::new(
+            stream
+                .try_clone()
+                .context("failed to clone unix stream for reading")?,
+            stream,
+            codec,
+        );
+
+        stream
+            .write_message(&UdsInitResponse {
+                parent_id: parent_id.clone(),
+                child_id: self.id(),
+            })
+            .context("failed to write init response to parent")?;
+        debug!("successfully wrote init response to parent");
+
+        // Enter reading/writing loop
+        debug!("entering read loop...");
+        loop {
+            let PingMessage {
+                sender_id,
+                receiver_id,
+                sent_at_ns,
+                payload,
+                ..
+            } = match stream.read_message() {
+                Ok(msg) => msg,
+                Err(e) if is_eof(&e) => {
+                    debug!("parent closed the connection, exiting");
+                    return Ok(());
+                }
+                Err(e) => return Err(e.context("failed to read ping message")),
+            };
+            let ping_latency_ns = monotonic_nanos_since(sent_at_ns)?;
+            ensure!(sender_id == parent_id, "sender should be parent");
+            ensure!(receiver_id == self.id(), "receiver should be child");
+
+            stream
+                .write_message(&PongMessage {
+                    sender_id: self.id(),
+                    receiver_id: sender_id,
+                    sent_at_ns: get_monotonic_time_nanos()?,
+                    ping_latency_ns,
+                    payload,
+                })
+                .context("failed to send pong to parent")?;
+        }
+    }
+}
@@ -0,0 +1,52 @@
+/*!
+Process IPC over a plain [Unix domain socket][wiki-uds] (`SOCK_STREAM`).
+
+The parent binds a listening socket in a private temporary directory, then sends the path
+to the child over STDIN. Once the child connects, pings and pongs travel as length-prefixed
+messages over the connected stream.
+
+This is the same underlying mechanism that [`ipc-channel`][crate-ipc-channel] uses on Linux,
+minus the library itself, which makes it a useful baseline for [`crate::ipcc`].
+
+[wiki-uds]: <https://en.wikipedia.org/wiki/Unix_domain_socket>
+[crate-ipc-channel]: <https://crates.io/crates/ipc-channel>
+**/
+
+use std::path::PathBuf;
+
+use serde::{Deserialize, Serialize};
+
+use crate::codec::RpcCodec;
+use crate::proto::impl_init_response_proto;
+
+pub mod child;
+pub mod parent;
+
+pub use child::UdsChild;
+pub use parent::UdsParent;
+
+/// Message sent to child processes over STDIN that contains
+/// information necessary for the child to connect to the parent
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
+struct UdsInit {
+    /// UUID of the parent
+    parent_id: String,
+
+    /// Codec used to encode messages (after this init message)
+    codec: RpcCodec,
+
+    /// Path to the Unix domain socket the parent is listening on
+    socket_path: PathBuf,
+}
+
+/// Message sent from the child process (over the socket) upon successful connection
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
+struct UdsInitResponse {
+    /// UUID of the parent
+    parent_id: String,
+
+    /// UUID of the child
+    child_id: String,
+}
+
+impl_init_response_proto!(UdsInitResponse);
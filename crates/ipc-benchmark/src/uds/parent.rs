@@ -0,0 +1,194 @@
+//! Parent-specific IPC implementation over Unix domain sockets
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::io::Write;
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::process::{Child, Command, Stdio};
+
+use anyhow::{ensure, Context as _, Result};
+use tempfile::TempDir;
+use tracing::debug;
+use uuid::Uuid;
+
+use crate::codec::RpcCodec;
+use crate::latency::OneWayLatencies;
+use crate::stream::StreamHandle;
+use crate::uds::{UdsInit, UdsInitResponse};
+use crate::{
+    get_monotonic_time_nanos, payload_size_bytes_from_env_or_default, synthetic_payload, ChildId,
+    ChildName, ParentProcess, PingMessage, Pinger, PongMessage, RpcPong,
+};
+
+/// Bi-directional channel for communication with a single child
+struct UdsChannel {
+    /// Self-reported ID of the child
+    child_id: ChildId,
+
+    /// Connected stream to the child
+    stream: StreamHandle<UnixStream, UnixStream>,
+
+    /// Temporary directory that contains the socket file
+    ///
+    /// NOTE: this must be held so that the directory is not removed while the child is running
+    _socket_dir: TempDir,
+}
+
+/// A parent process that performs IPC via a Unix domain socket (`SOCK_STREAM`)
+pub struct UdsParent {
+    /// UUID of the parent process
+    uuid: Uuid,
+
+    /// Synthetic payload sent with every ping (see `RPC_PAYLOAD_SIZE_BYTES`)
+    payload: Vec<u8>,
+
+    /// Codec used to encode messages (sent to children in the init message)
+    codec: RpcCodec,
+
+    /// Channels for communicating with children, by child name
+    ///
+    /// SAFETY: We're safe using a `RefCell` here because this structure
+    /// is very much *not* multi-threaded.
+    channels: HashMap<ChildName, RefCell<UdsChannel>>,
+}
+
+impl std::fmt::Debug for UdsParent {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("UdsParent")
+            .field("uuid", &self.uuid)
+            .finish()
+    }
+}
+
+impl Default for UdsParent {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl UdsParent {
+    /// Create a new [`UdsParent`]
+    #[must_use]
+    pub fn new() -> Self {
+        Self {
+            uuid: Uuid::now_v7(),
+            payload: synthetic_payload(payload_size_bytes_from_env_or_default(std::env::vars())),
+            codec: RpcCodec::from_env_or_default(std::env::vars()),
+            channels: HashMap::new(),
+        }
+    }
+}
+
+impl ParentProcess for UdsParent {
+    fn id(&self) -> String {
+        self.uuid.to_string()
+    }
+
+    fn spawn_child(&mut self, name: impl AsRef<str>, mut cmd: Command) -> Result<Child> {
+        let name = name.as_ref();
+
+        // Bind a socket in a private temp dir, before the child is started
+        let socket_dir = tempfile::Builder::new()
+            .prefix("ipc-benchmark-uds-")
+            .tempdir()
+            .context("failed to create temp dir for socket")?;
+        let socket_path = socket_dir.path().join("parent.sock");
+        debug!(socket_path = %socket_path.display(), "binding unix socket");
+        let listener = UnixListener::bind(&socket_path)
+            .with_context(|| format!("failed to bind unix socket @ [{}]", socket_path.display()))?;
+
+        // Spawn the child
+        debug!("spawning child");
+        let mut child = cmd
+            .stdin(Stdio::piped())
+            .spawn()
+            .context("failed to spawn child process")?;
+
+        // Send the init message over STDIN
+        let init_msg = UdsInit {
+            parent_id: self.id(),
+            codec: self.codec,
+            socket_path,
+        };
+        debug!(?init_msg, "writing init to child STDIN");
+        let mut child_stdin = child.stdin.take().context("failed to get child STDIN")?;
+        child_stdin
+            .write_all(&serde_json::to_vec(&init_msg).context("failed to serialize init msg")?)
+            .context("failed to write init msg")?;
+        child_stdin
+            .write_all(b"\r\n")
+            .context("failed to write new line")?;
+        child_stdin.flush().context("failed to flush child STDIN")?;
+
+        // Wait for the child to connect, and read the init response over the new connection
+        debug!("waiting for child to connect");
+        let (stream, _addr) = listener
+            .accept()
+            .context("failed to accept connection from child")?;
+        let mut stream = StreamHandle::new(
+            stream
+                .try_clone()
+                .context("failed to clone unix stream for reading")?,
+            stream,
+            self.codec,
+        );
+        let init_resp: UdsInitResponse = stream
+            .read_message()
+            .context("failed to read init response from child")?;
+        ensure!(
+            init_resp.parent_id == self.id(),
+            "parent ID reported by child did not match"
+        );
+        debug!(child_id = init_resp.child_id, "received init response");
+
+        // Save information to local registry
+        self.channels.insert(
+            name.into(),
+            RefCell::new(UdsChannel {
+                child_id: init_resp.child_id,
+                stream,
+                _socket_dir: socket_dir,
+            }),
+        );
+
+        Ok(child)
+    }
+}
+
+impl Pinger for UdsParent {
+    fn roundtrip_ping(&self, child_name: impl AsRef<str>) -> Result<Option<OneWayLatencies>> {
+        let child = child_name.as_ref();
+        let mut chan = self
+            .channels
+            .get(child)
+            .with_context(|| format!("failed to find child [{child}]"))?
+            .borrow_mut();
+        let UdsChannel {
+            child_id, stream, ..
+        } = &mut *chan;
+
+        debug!(child, "sending ping message to child");
+        stream
+            .write_message(&PingMessage {
+                sender_id: self.id(),
+                receiver_id: child_id.clone(),
+                sent_at_ns: get_monotonic_time_nanos()?,
+                payload: self.payload.clone(),
+            })
+            .context("failed to send ping message to child")?;
+
+        debug!(child, "reading pong message from child");
+        let pong_msg: PongMessage = stream
+            .read_message()
+            .context("failed to read pong message")?;
+        let latencies = pong_msg.one_way_latencies()?;
+        ensure!(pong_msg.sender_id() == child_id, "child ID matches");
+        ensure!(pong_msg.receiver_id() == self.id(), "parent ID matches");
+        ensure!(
+            pong_msg.payload() == self.payload.as_slice(),
+            "payload matches"
+        );
+
+        Ok(latencies)
+    }
+}
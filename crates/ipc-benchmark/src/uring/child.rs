@@ -0,0 +1,133 @@
+//! Child-specific IPC implementation driven by `io_uring`
+
+use std::io::{stdin, stdout, BufRead};
+use std::os::fd::AsFd as _;
+use std::os::unix::net::UnixStream;
+
+use anyhow::{ensure, Context as _, Result};
+use tracing::debug;
+use uuid::Uuid;
+
+use crate::stream::is_eof;
+use crate::uring::{UringHandle, UringInit, UringInitResponse, UringTransport};
+use crate::{
+    get_monotonic_time_nanos, monotonic_nanos_since, ChildProcess, PingMessage, PongMessage,
+};
+
+/// A child process that performs IPC over a socket or pipes, driven by `io_uring`
+#[derive(Debug)]
+pub struct UringChild {
+    /// UUID that should uniquely identify this process
+    uuid: Uuid,
+}
+
+impl Default for UringChild {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl UringChild {
+    /// Build a new [`UringChild`] with a random UUID
+    #[must_use]
+    pub fn new() -> Self {
+        Self {
+            uuid: Uuid::now_v7(),
+        }
+    }
+}
+
+impl ChildProcess for UringChild {
+    fn id(&self) -> String {
+        self.uuid.to_string()
+    }
+
+    fn run(self) -> Result<()> {
+        debug!("child process running");
+
+        debug!("reading uring init from STDIN");
+        let mut s = String::new();
+        stdin().lock().read_line(&mut s)?;
+
+        // We expect to receive an init message on STDIN
+        let UringInit {
+            parent_id,
+            codec,
+            transport,
+            socket_path,
+            options,
+        } = serde_json::from_slice(s.as_bytes())
+            .context("failed to read init message from STDIN")?;
+
+        // NOTE: reading from the STDIN fd directly is safe here, since the parent sends nothing
+        // past the init line until it has received our init response
+        let (read_fd, write_fd) = match transport {
+            UringTransport::Uds => {
+                let socket_path = socket_path.context("missing socket path in init message")?;
+                debug!(socket_path = %socket_path.display(), "connecting to parent socket");
+                let stream = UnixStream::connect(&socket_path).with_context(|| {
+                    format!(
+                        "failed to connect to parent socket @ [{}]",
+                        socket_path.display()
+                    )
+                })?;
+                let read_stream = stream
+                    .try_clone()
+                    .context("failed to clone unix stream for reading")?;
+                (read_stream.into(), stream.into())
+            }
+            UringTransport::Pipe => (
+                stdin()
+                    .as_fd()
+                    .try_clone_to_owned()
+                    .context("failed to duplicate STDIN")?,
+                stdout()
+                    .as_fd()
+                    .try_clone_to_owned()
+                    .context("failed to duplicate STDOUT")?,
+            ),
+        };
+        let mut handle = UringHandle::new(read_fd, write_fd, &options, codec)
+            .context("failed to create io_uring handle")?;
+
+        handle
+            .write_message(&UringInitResponse {
+                parent_id: parent_id.clone(),
+                child_id: self.id(),
+            })
+            .context("failed to write init response to parent")?;
+        debug!("successfully wrote init response to parent");
+
+        // Enter reading/writing loop
+        debug!("entering read loop...");
+        loop {
+            let PingMessage {
+                sender_id,
+                receiver_id,
+                sent_at_ns,
+                payload,
+                ..
+            } = match handle.read_message() {
+                Ok(msg) => msg,
+                Err(e) if is_eof(&e) => {
+                    debug!("parent closed the connection, exiting");
+                    return Ok(());
+                }
+                Err(e) => return Err(e.context("failed to read ping message")),
+            };
+            let ping_latency_ns = monotonic_nanos_since(sent_at_ns)?;
+            ensure!(sender_id == parent_id, "sender should be parent");
+            ensure!(receiver_id == self.id(), "receiver should be child");
+
+            handle
+                .write_message(&PongMessage {
+                    sender_id: self.id(),
+                    receiver_id: sender_id,
+                    sent_at_ns: get_monotonic_time_nanos()?,
+                    ping_latency_ns,
+                    payload,
+                })
+                .context("failed to send pong to parent")?;
+        }
+    }
+}
@@ -0,0 +1,428 @@
+/*!
+Process IPC over Unix domain sockets or anonymous pipes, with every read and write driven by [`io_uring`][io-uring] (Linux only).
+
+Each side (parent and child) owns a single ring, and submits one read or write at a time, waiting for its completion.
+This sits in between the blocking syscalls used by most transports and the busy-spinning used by some shared memory ones,
+particularly with the options below:
+
+- `SQPOLL`, where a kernel thread polls the submission queue, so submitting does not require a syscall
+- registered (fixed) buffers, which the kernel maps once rather than on every operation
+
+The transport used (see [`UringTransport`]) and the options are configured via ENV by the parent,
+and sent to the child as part of the init payload so that both sides are configured identically.
+
+Messages are serialized (JSON) and `u64` LE length-prefixed, the same way as [`crate::uds`] and [`crate::pipe`],
+but are limited to the size of the buffers (see `IO_URING_BUFFER_SIZE_BYTES`), since every message is read or written
+by a single operation.
+
+[io-uring]: https://man7.org/linux/man-pages/man7/io_uring.7.html
+**/
+
+use std::io::{Cursor, Write as _};
+use std::os::fd::{AsRawFd as _, OwnedFd};
+use std::path::PathBuf;
+
+use anyhow::{bail, ensure, Context as _, Result};
+use io_uring::{opcode, squeue, types, IoUring};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use tracing::debug;
+
+use crate::codec::{Codec as _, RpcCodec};
+use crate::proto::{impl_init_response_proto, ProtoMessage};
+
+pub mod child;
+pub mod parent;
+
+pub use child::UringChild;
+pub use parent::UringParent;
+
+/// ENV variable for setting the transport driven by `io_uring`
+const ENV_VAR_IO_URING_TRANSPORT: &str = "IO_URING_TRANSPORT";
+
+/// ENV variable for enabling/disabling `SQPOLL`
+const ENV_VAR_IO_URING_SQPOLL: &str = "IO_URING_SQPOLL";
+
+/// ENV variable for setting how long the `SQPOLL` kernel thread stays awake without work (in milliseconds)
+const ENV_VAR_IO_URING_SQPOLL_IDLE_MILLIS: &str = "IO_URING_SQPOLL_IDLE_MILLIS";
+
+/// ENV variable for enabling/disabling registered buffers
+const ENV_VAR_IO_URING_REGISTERED_BUFFERS: &str = "IO_URING_REGISTERED_BUFFERS";
+
+/// ENV variable for setting the size of the read and write buffers
+const ENV_VAR_IO_URING_BUFFER_SIZE_BYTES: &str = "IO_URING_BUFFER_SIZE_BYTES";
+
+/// Number of entries in the submission queue
+///
+/// Only one operation is ever in flight, so this does not need to be large.
+const RING_ENTRIES: u32 = 8;
+
+/// Size of the length prefix that precedes every message
+const LEN_PREFIX_SIZE_BYTES: usize = size_of::<u64>();
+
+/// Index of the read buffer, when buffers are registered
+const READ_BUF_INDEX: u16 = 0;
+
+/// Index of the write buffer, when buffers are registered
+const WRITE_BUF_INDEX: u16 = 1;
+
+/// Offset passed with every read/write, which means "the current position" (required for pipes)
+const CURRENT_POSITION_OFFSET: u64 = u64::MAX;
+
+/// Underlying transport whose reads and writes are driven by `io_uring`
+///
+/// This is normally configured via ENV ("IO_URING_TRANSPORT"), and parsed
+/// into this structure for easy usage from code.
+#[derive(Debug, Default, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum UringTransport {
+    /// A Unix domain socket (`SOCK_STREAM`), see [`crate::uds`]
+    #[default]
+    Uds,
+    /// The anonymous pipes connected to the child's STDIN/STDOUT, see [`crate::pipe`]
+    Pipe,
+}
+
+impl std::str::FromStr for UringTransport {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "uds" => Ok(Self::Uds),
+            "pipe" => Ok(Self::Pipe),
+            _ => bail!("invalid UringTransport value [{s}]"),
+        }
+    }
+}
+
+impl UringTransport {
+    /// Retreive from env or use the default (Uds)
+    pub fn from_env_or_default(values: impl Iterator<Item = (String, String)>) -> Self {
+        for (k, v) in values {
+            if k == ENV_VAR_IO_URING_TRANSPORT {
+                return <Self as std::str::FromStr>::from_str(&v).unwrap_or(Self::default());
+            }
+        }
+        Self::default()
+    }
+}
+
+/// Options applied to the ring (and buffers) used on both parent and child
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+struct UringOptions {
+    /// Whether to use a kernel thread to poll the submission queue (`IORING_SETUP_SQPOLL`)
+    ///
+    /// NOTE: on kernels older than 5.11, this requires `CAP_SYS_ADMIN`
+    sqpoll: bool,
+
+    /// Milliseconds the `SQPOLL` kernel thread stays awake without work, before it must be woken up
+    sqpoll_idle_millis: u32,
+
+    /// Whether to register the read and write buffers with the ring, and use `READ_FIXED`/`WRITE_FIXED`
+    registered_buffers: bool,
+
+    /// Size of the read and write buffers (i.e. the max message size, including the length prefix)
+    buffer_size_bytes: usize,
+}
+
+impl Default for UringOptions {
+    fn default() -> Self {
+        Self {
+            sqpoll: false,
+            sqpoll_idle_millis: 1000,
+            registered_buffers: false,
+            buffer_size_bytes: 64 * 1024,
+        }
+    }
+}
+
+impl UringOptions {
+    /// Build [`UringOptions`] from ENV, using defaults for any values that are missing or invalid
+    fn from_env() -> Self {
+        let default = Self::default();
+        Self {
+            sqpoll: std::env::var(ENV_VAR_IO_URING_SQPOLL)
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(default.sqpoll),
+            sqpoll_idle_millis: std::env::var(ENV_VAR_IO_URING_SQPOLL_IDLE_MILLIS)
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(default.sqpoll_idle_millis),
+            registered_buffers: std::env::var(ENV_VAR_IO_URING_REGISTERED_BUFFERS)
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(default.registered_buffers),
+            buffer_size_bytes: std::env::var(ENV_VAR_IO_URING_BUFFER_SIZE_BYTES)
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(default.buffer_size_bytes),
+        }
+    }
+}
+
+/// Message sent to child processes over STDIN (as a single line) before any other messages
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
+struct UringInit {
+    /// UUID of the parent
+    parent_id: String,
+
+    /// Codec used to encode messages (after this init message)
+    codec: RpcCodec,
+
+    /// Transport that should be used
+    transport: UringTransport,
+
+    /// Path to the socket the child should connect to (for [`UringTransport::Uds`])
+    socket_path: Option<PathBuf>,
+
+    /// Options for the ring used by the child
+    options: UringOptions,
+}
+
+/// Message sent from the child process (over the transport) upon successful initialization
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
+struct UringInitResponse {
+    /// UUID of the parent
+    parent_id: String,
+
+    /// UUID of the child
+    child_id: String,
+}
+
+impl_init_response_proto!(UringInitResponse);
+
+/// A handle over a pair of file descriptors, which sends and receives whole messages via `io_uring`
+///
+/// The read and write file descriptors may refer to the same object (ex. a duplicated socket),
+/// or two entirely different objects (ex. the two ends of a pair of pipes).
+struct UringHandle {
+    /// Ring through which all reads and writes are submitted
+    ///
+    /// NOTE: this must be dropped before the buffers, as they may be registered with it
+    ring: IoUring,
+
+    /// File descriptor from which messages are read
+    read_fd: OwnedFd,
+
+    /// File descriptor to which messages are written
+    write_fd: OwnedFd,
+
+    /// Whether the buffers below are registered with the ring
+    registered_buffers: bool,
+
+    /// Buffer that reads are performed into
+    ///
+    /// Bytes from `read_start` to `read_end` have been read, but not consumed yet.
+    read_buf: Box<[u8]>,
+
+    /// Start of the unconsumed bytes in `read_buf`
+    read_start: usize,
+
+    /// End of the unconsumed bytes in `read_buf`
+    read_end: usize,
+
+    /// Buffer that messages are serialized into, and written from
+    write_buf: Box<[u8]>,
+
+    /// Codec used to encode messages
+    codec: RpcCodec,
+}
+
+impl std::fmt::Debug for UringHandle {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("UringHandle")
+            .field("read_fd", &self.read_fd)
+            .field("write_fd", &self.write_fd)
+            .field("registered_buffers", &self.registered_buffers)
+            .field("buffer_size_bytes", &self.read_buf.len())
+            .field("codec", &self.codec)
+            .finish()
+    }
+}
+
+impl UringHandle {
+    /// Create a new [`UringHandle`], setting up a ring according to the given options
+    fn new(
+        read_fd: OwnedFd,
+        write_fd: OwnedFd,
+        options: &UringOptions,
+        codec: RpcCodec,
+    ) -> Result<Self> {
+        ensure!(
+            options.buffer_size_bytes > LEN_PREFIX_SIZE_BYTES,
+            "io_uring buffer size must be larger than the length prefix"
+        );
+
+        let mut builder = IoUring::builder();
+        if options.sqpoll {
+            builder.setup_sqpoll(options.sqpoll_idle_millis);
+        }
+        let ring = builder
+            .build(RING_ENTRIES)
+            .with_context(|| format!("failed to set up io_uring with options {options:?}"))?;
+
+        let mut read_buf = vec![0u8; options.buffer_size_bytes].into_boxed_slice();
+        let mut write_buf = vec![0u8; options.buffer_size_bytes].into_boxed_slice();
+        if options.registered_buffers {
+            let iovecs = [
+                libc::iovec {
+                    iov_base: read_buf.as_mut_ptr().cast(),
+                    iov_len: read_buf.len(),
+                },
+                libc::iovec {
+                    iov_base: write_buf.as_mut_ptr().cast(),
+                    iov_len: write_buf.len(),
+                },
+            ];
+            // SAFETY: the buffers are heap allocated (so they do not move), and outlive the ring
+            unsafe { ring.submitter().register_buffers(&iovecs) }
+                .context("failed to register buffers with io_uring")?;
+        }
+        debug!(?options, "set up io_uring");
+
+        Ok(Self {
+            ring,
+            read_fd,
+            write_fd,
+            registered_buffers: options.registered_buffers,
+            read_buf,
+            read_start: 0,
+            read_end: 0,
+            write_buf,
+            codec,
+        })
+    }
+
+    /// Submit a single operation and wait for it to complete, returning the (non-negative) result
+    fn submit_and_wait(&mut self, entry: squeue::Entry) -> std::io::Result<usize> {
+        // SAFETY: the buffer(s) referenced by the entry are owned by this handle,
+        // and the operation is completed before this function returns
+        unsafe { self.ring.submission().push(&entry) }
+            .map_err(|_| std::io::Error::other("io_uring submission queue is full"))?;
+
+        loop {
+            match self.ring.submit_and_wait(1) {
+                Ok(_) => break,
+                Err(e) if e.kind() == std::io::ErrorKind::Interrupted => continue,
+                Err(e) => return Err(e),
+            }
+        }
+
+        let cqe = self
+            .ring
+            .completion()
+            .next()
+            .ok_or_else(|| std::io::Error::other("missing io_uring completion"))?;
+        usize::try_from(cqe.result()).map_err(|_| std::io::Error::from_raw_os_error(-cqe.result()))
+    }
+
+    /// Read more bytes into the read buffer (after any unconsumed bytes)
+    fn fill_read_buf(&mut self) -> Result<()> {
+        // Move any unconsumed bytes to the front, to make room
+        if self.read_start > 0 {
+            self.read_buf.copy_within(self.read_start..self.read_end, 0);
+            self.read_end -= self.read_start;
+            self.read_start = 0;
+        }
+
+        let fd = types::Fd(self.read_fd.as_raw_fd());
+        // SAFETY: we only take the pointer here, the buffer is not otherwise touched until the read completes
+        let buf = unsafe { self.read_buf.as_mut_ptr().add(self.read_end) };
+        let len = u32::try_from(self.read_buf.len() - self.read_end)
+            .context("io_uring buffer too large")?;
+        let entry = if self.registered_buffers {
+            opcode::ReadFixed::new(fd, buf, len, READ_BUF_INDEX)
+                .offset(CURRENT_POSITION_OFFSET)
+                .build()
+        } else {
+            opcode::Read::new(fd, buf, len)
+                .offset(CURRENT_POSITION_OFFSET)
+                .build()
+        };
+
+        let n = self
+            .submit_and_wait(entry)
+            .context("failed to read via io_uring")?;
+        if n == 0 {
+            return Err(std::io::Error::from(std::io::ErrorKind::UnexpectedEof))
+                .context("failed to read via io_uring");
+        }
+        self.read_end += n;
+        Ok(())
+    }
+
+    /// Read a single message, waiting on the ring as necessary
+    fn read_message<T: DeserializeOwned + ProtoMessage>(&mut self) -> Result<T> {
+        while self.read_end - self.read_start < LEN_PREFIX_SIZE_BYTES {
+            self.fill_read_buf()
+                .context("failed to read length prefix")?;
+        }
+        let mut len_bytes = [0u8; LEN_PREFIX_SIZE_BYTES];
+        len_bytes.copy_from_slice(
+            &self.read_buf[self.read_start..self.read_start + LEN_PREFIX_SIZE_BYTES],
+        );
+        let len = usize::try_from(u64::from_le_bytes(len_bytes))
+            .context("failed to convert u64 len into usize")?;
+        ensure!(
+            LEN_PREFIX_SIZE_BYTES + len <= self.read_buf.len(),
+            "message of len [{len}] does not fit in io_uring buffer of size [{}]",
+            self.read_buf.len()
+        );
+        debug!(len, "read length prefix via io_uring");
+
+        while self.read_end - self.read_start < LEN_PREFIX_SIZE_BYTES + len {
+            self.fill_read_buf()
+                .with_context(|| format!("failed to read message of len [{len}]"))?;
+        }
+        let msg_start = self.read_start + LEN_PREFIX_SIZE_BYTES;
+        self.read_start = msg_start + len;
+
+        self.codec
+            .decode(&self.read_buf[msg_start..msg_start + len])
+    }
+
+    /// Write a single message, waiting on the ring until it has been completely written
+    fn write_message<T: Serialize + ProtoMessage>(&mut self, obj: &T) -> Result<usize> {
+        let mut cursor = Cursor::new(&mut self.write_buf[LEN_PREFIX_SIZE_BYTES..]);
+        self.codec
+            .encode_to_writer(obj, &mut cursor)
+            .context("failed to serialize into io_uring buffer")?;
+        cursor.flush().context("failed to flush cursor")?;
+        let msg_len =
+            usize::try_from(cursor.position()).context("failed to convert msg len to usize")?;
+        self.write_buf[..LEN_PREFIX_SIZE_BYTES].copy_from_slice(
+            &u64::try_from(msg_len)
+                .context("failed to convert msg len to u64")?
+                .to_le_bytes(),
+        );
+
+        let total = LEN_PREFIX_SIZE_BYTES + msg_len;
+        let mut written = 0;
+        while written < total {
+            let fd = types::Fd(self.write_fd.as_raw_fd());
+            // SAFETY: written is always less than total, which is within the buffer
+            let buf = unsafe { self.write_buf.as_ptr().add(written) };
+            let len = u32::try_from(total - written).context("message too large")?;
+            let entry = if self.registered_buffers {
+                opcode::WriteFixed::new(fd, buf, len, WRITE_BUF_INDEX)
+                    .offset(CURRENT_POSITION_OFFSET)
+                    .build()
+            } else {
+                opcode::Write::new(fd, buf, len)
+                    .offset(CURRENT_POSITION_OFFSET)
+                    .build()
+            };
+            let n = self
+                .submit_and_wait(entry)
+                .context("failed to write message via io_uring")?;
+            if n == 0 {
+                return Err(std::io::Error::from(std::io::ErrorKind::WriteZero))
+                    .context("failed to write message via io_uring");
+            }
+            written += n;
+        }
+        debug!(msg_len, "wrote message via io_uring");
+
+        Ok(msg_len)
+    }
+}
@@ -0,0 +1,223 @@
+//! Parent-specific IPC implementation driven by `io_uring`
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::io::Write;
+use std::os::fd::OwnedFd;
+use std::os::unix::net::UnixListener;
+use std::process::{Child, Command, Stdio};
+
+use anyhow::{ensure, Context as _, Result};
+use tempfile::TempDir;
+use tracing::debug;
+use uuid::Uuid;
+
+use crate::codec::RpcCodec;
+use crate::latency::OneWayLatencies;
+use crate::uring::{UringHandle, UringInit, UringInitResponse, UringOptions, UringTransport};
+use crate::{
+    get_monotonic_time_nanos, payload_size_bytes_from_env_or_default, synthetic_payload, ChildId,
+    ChildName, ParentProcess, PingMessage, Pinger, PongMessage, RpcPong,
+};
+
+/// Bi-directional channel for communication with a single child
+struct UringChannel {
+    /// Self-reported ID of the child
+    child_id: ChildId,
+
+    /// Handle (with its own ring) connected to the child
+    handle: UringHandle,
+
+    /// Temporary directory that contains the socket file (for [`UringTransport::Uds`])
+    ///
+    /// NOTE: this must be held so that the directory is not removed while the child is running
+    _socket_dir: Option<TempDir>,
+}
+
+/// A parent process that performs IPC over a socket or pipes, driven by `io_uring`
+pub struct UringParent {
+    /// UUID of the parent process
+    uuid: Uuid,
+
+    /// Synthetic payload sent with every ping (see `RPC_PAYLOAD_SIZE_BYTES`)
+    payload: Vec<u8>,
+
+    /// Codec used to encode messages (sent to children in the init message)
+    codec: RpcCodec,
+
+    /// Transport used to communicate with children
+    transport: UringTransport,
+
+    /// Options for the rings used by the parent and children
+    options: UringOptions,
+
+    /// Channels for communicating with children, by child name
+    ///
+    /// SAFETY: We're safe using a `RefCell` here because this structure
+    /// is very much *not* multi-threaded.
+    channels: HashMap<ChildName, RefCell<UringChannel>>,
+}
+
+impl std::fmt::Debug for UringParent {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("UringParent")
+            .field("uuid", &self.uuid)
+            .field("transport", &self.transport)
+            .field("options", &self.options)
+            .finish()
+    }
+}
+
+impl Default for UringParent {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl UringParent {
+    /// Create a new [`UringParent`]
+    #[must_use]
+    pub fn new() -> Self {
+        Self {
+            uuid: Uuid::now_v7(),
+            payload: synthetic_payload(payload_size_bytes_from_env_or_default(std::env::vars())),
+            codec: RpcCodec::from_env_or_default(std::env::vars()),
+            transport: UringTransport::from_env_or_default(std::env::vars()),
+            options: UringOptions::from_env(),
+            channels: HashMap::new(),
+        }
+    }
+}
+
+impl ParentProcess for UringParent {
+    fn id(&self) -> String {
+        self.uuid.to_string()
+    }
+
+    fn spawn_child(&mut self, name: impl AsRef<str>, mut cmd: Command) -> Result<Child> {
+        let name = name.as_ref();
+
+        // Bind a socket in a private temp dir (if necessary), before the child is started
+        let listener = match self.transport {
+            UringTransport::Uds => {
+                let socket_dir = tempfile::Builder::new()
+                    .prefix("ipc-benchmark-uring-")
+                    .tempdir()
+                    .context("failed to create temp dir for socket")?;
+                let socket_path = socket_dir.path().join("parent.sock");
+                debug!(socket_path = %socket_path.display(), "binding unix socket");
+                let listener = UnixListener::bind(&socket_path).with_context(|| {
+                    format!("failed to bind unix socket @ [{}]", socket_path.display())
+                })?;
+                Some((socket_dir, socket_path, listener))
+            }
+            UringTransport::Pipe => None,
+        };
+
+        // Spawn the child
+        debug!(transport = ?self.transport, "spawning child");
+        cmd.stdin(Stdio::piped());
+        if self.transport == UringTransport::Pipe {
+            cmd.stdout(Stdio::piped());
+        }
+        let mut child = cmd.spawn().context("failed to spawn child process")?;
+
+        // Send the init message over STDIN
+        let init_msg = UringInit {
+            parent_id: self.id(),
+            codec: self.codec,
+            transport: self.transport,
+            socket_path: listener.as_ref().map(|(_, path, _)| path.clone()),
+            options: self.options.clone(),
+        };
+        debug!(?init_msg, "writing init to child STDIN");
+        let mut child_stdin = child.stdin.take().context("failed to get child STDIN")?;
+        child_stdin
+            .write_all(&serde_json::to_vec(&init_msg).context("failed to serialize init msg")?)
+            .context("failed to write init msg")?;
+        child_stdin
+            .write_all(b"\r\n")
+            .context("failed to write new line")?;
+        child_stdin.flush().context("failed to flush child STDIN")?;
+
+        // Set up the handle over the socket (once the child connects) or the pipes
+        let (read_fd, write_fd, socket_dir): (OwnedFd, OwnedFd, _) = match listener {
+            Some((socket_dir, _, listener)) => {
+                debug!("waiting for child to connect");
+                let (stream, _addr) = listener
+                    .accept()
+                    .context("failed to accept connection from child")?;
+                let read_stream = stream
+                    .try_clone()
+                    .context("failed to clone unix stream for reading")?;
+                (read_stream.into(), stream.into(), Some(socket_dir))
+            }
+            None => {
+                let child_stdout = child.stdout.take().context("failed to get child STDOUT")?;
+                (child_stdout.into(), child_stdin.into(), None)
+            }
+        };
+        let mut handle = UringHandle::new(read_fd, write_fd, &self.options, self.codec)
+            .context("failed to create io_uring handle")?;
+
+        debug!("waiting for init response from child");
+        let init_resp: UringInitResponse = handle
+            .read_message()
+            .context("failed to read init response from child")?;
+        ensure!(
+            init_resp.parent_id == self.id(),
+            "parent ID reported by child did not match"
+        );
+        debug!(child_id = init_resp.child_id, "received init response");
+
+        // Save information to local registry
+        self.channels.insert(
+            name.into(),
+            RefCell::new(UringChannel {
+                child_id: init_resp.child_id,
+                handle,
+                _socket_dir: socket_dir,
+            }),
+        );
+
+        Ok(child)
+    }
+}
+
+impl Pinger for UringParent {
+    fn roundtrip_ping(&self, child_name: impl AsRef<str>) -> Result<Option<OneWayLatencies>> {
+        let child = child_name.as_ref();
+        let mut chan = self
+            .channels
+            .get(child)
+            .with_context(|| format!("failed to find child [{child}]"))?
+            .borrow_mut();
+        let UringChannel {
+            child_id, handle, ..
+        } = &mut *chan;
+
+        debug!(child, "sending ping message to child");
+        handle
+            .write_message(&PingMessage {
+                sender_id: self.id(),
+                receiver_id: child_id.clone(),
+                sent_at_ns: get_monotonic_time_nanos()?,
+                payload: self.payload.clone(),
+            })
+            .context("failed to send ping message to child")?;
+
+        debug!(child, "reading pong message from child");
+        let pong_msg: PongMessage = handle
+            .read_message()
+            .context("failed to read pong message")?;
+        let latencies = pong_msg.one_way_latencies()?;
+        ensure!(pong_msg.sender_id() == child_id, "child ID matches");
+        ensure!(pong_msg.receiver_id() == self.id(), "parent ID matches");
+        ensure!(
+            pong_msg.payload() == self.payload.as_slice(),
+            "payload matches"
+        );
+
+        Ok(latencies)
+    }
+}
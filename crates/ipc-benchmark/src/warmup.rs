@@ -0,0 +1,67 @@
+/*!
+Warmup phase, run before the measured part of every benchmark.
+
+The first round-trips of a run pay for one-off costs (page faults on freshly mapped buffers, cold caches,
+lazily-initialized connections & allocators, CPU frequency ramping up), which skew results for short runs.
+Setting `WARMUP_SECONDS` makes every parent ping its child for that long before it starts counting round-trips,
+recording latencies and accounting for CPU usage, so none of the warmup is included in results.
+**/
+
+use std::time::{Duration, Instant};
+
+use anyhow::{Context as _, Result};
+use tracing::info;
+
+use crate::latency::OneWayLatencies;
+
+/// ENV variable for setting the duration of the warmup phase (in seconds)
+const ENV_VAR_WARMUP_SECONDS: &str = "WARMUP_SECONDS";
+
+/// Warmup phase, during which round-trips are performed but not measured
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Warmup {
+    /// How long round-trips are performed for
+    duration: Duration,
+}
+
+impl Warmup {
+    /// Create a new [`Warmup`] of a given duration
+    #[must_use]
+    pub fn new(duration: Duration) -> Self {
+        Self { duration }
+    }
+
+    /// Build a [`Warmup`] from the duration in `WARMUP_SECONDS`, if present in env (there is no warmup otherwise)
+    pub fn from_env(mut values: impl Iterator<Item = (String, String)>) -> Result<Self> {
+        values
+            .find(|(k, _)| k == ENV_VAR_WARMUP_SECONDS)
+            .map(|(_, v)| {
+                v.parse::<u64>()
+                    .with_context(|| format!("failed to parse warmup duration [{v}]"))
+            })
+            .transpose()
+            .map(|seconds| Self::new(Duration::from_secs(seconds.unwrap_or_default())))
+    }
+
+    /// Perform round-trips back-to-back until the warmup is over, returning how many were performed
+    pub fn run(
+        &self,
+        mut roundtrip: impl FnMut() -> Result<Option<OneWayLatencies>>,
+    ) -> Result<u64> {
+        if self.duration.is_zero() {
+            return Ok(0);
+        }
+
+        let start = Instant::now();
+        let mut roundtrips: u64 = 0;
+        while start.elapsed() < self.duration {
+            roundtrip().context("failed to ping during warmup")?;
+            roundtrips += 1;
+        }
+
+        let warmup_seconds = self.duration.as_secs();
+        info!(roundtrips, warmup_seconds, "completed warmup");
+        eprintln!("warmup: [{roundtrips}] round-trips over [{warmup_seconds}] seconds (excluded from results)");
+        Ok(roundtrips)
+    }
+}